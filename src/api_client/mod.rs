@@ -1,22 +1,52 @@
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use thiserror::Error;
 use url::Url;
 
 use crate::util::FlowletResult;
 
+/// Limits so an unreachable server does not hold up local commands.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
 pub struct ApiClient {
     base_url: Url,
     client: Client,
+    token: Option<String>,
 }
 
 #[derive(Debug, Error)]
-enum ApiClientError {
+pub enum ApiClientError {
     #[error("Failed to parse Base Server URL.")]
     UrlParseError,
 
+    #[error("Failed to create the HTTP client.")]
+    ClientBuildError,
+
     #[error("{0}")]
     PostError(String),
+
+    #[error("Not logged in or session expired. Please log in with `flowlet auth login`.")]
+    Unauthorized,
+
+    #[error("Server unreachable: {0}")]
+    Unreachable(String),
+
+    #[error("Server error: {0}")]
+    ServerError(String),
+}
+
+impl ApiClientError {
+    /// Whether the request may succeed later without changing it, as opposed to the
+    /// server rejecting the request itself. An expired session is not retryable, the
+    /// user has to log in again first.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ApiClientError::Unreachable(_) | ApiClientError::ServerError(_)
+        )
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,29 +59,90 @@ pub struct ApiResponse<T> {
 pub struct EmptyData;
 
 impl ApiClient {
-    pub fn new(base_url: &str) -> FlowletResult<Self> {
+    /// Creates a client for the server at `base_url`, sending `token` as a bearer
+    /// token with every request when given.
+    pub fn new(base_url: &str, token: Option<String>) -> FlowletResult<Self> {
+        // A trailing slash keeps a path prefix such as `/flowlet/` when joining
+        let base_url = if base_url.ends_with('/') {
+            base_url.to_string()
+        } else {
+            format!("{}/", base_url)
+        };
+
         Ok(Self {
-            base_url: Url::parse(base_url).map_err(|e| {
+            base_url: Url::parse(&base_url).map_err(|e| {
                 log::error!("{:?}", e);
                 ApiClientError::UrlParseError
             })?,
-            client: Client::new(),
+            client: Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .map_err(|e| {
+                    log::error!("{:?}", e);
+                    ApiClientError::ClientBuildError
+                })?,
+            token,
         })
     }
 
+    pub fn is_authenticated(&self) -> bool {
+        self.token.is_some()
+    }
+
     pub async fn post<T, K>(&self, path: &str, body: &T) -> FlowletResult<ApiResponse<K>>
     where
         T: Serialize,
         K: for<'de> Deserialize<'de>,
     {
-        let url = self.base_url.join(path).unwrap();
-        let response = self.client.post(url).json(body).send().await.map_err(|e| {
+        let url = self.url(path)?;
+        self.send(self.client.post(url).json(body)).await
+    }
+
+    pub async fn get<K>(&self, path: &str) -> FlowletResult<ApiResponse<K>>
+    where
+        K: for<'de> Deserialize<'de>,
+    {
+        let url = self.url(path)?;
+        self.send(self.client.get(url)).await
+    }
+
+    /// `path` relative to the base URL, even when it starts with `/`.
+    fn url(&self, path: &str) -> Result<Url, ApiClientError> {
+        self.base_url
+            .join(path.trim_start_matches('/'))
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                ApiClientError::UrlParseError
+            })
+    }
+
+    async fn send<K>(&self, request: RequestBuilder) -> FlowletResult<ApiResponse<K>>
+    where
+        K: for<'de> Deserialize<'de>,
+    {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+
+        let response = request.send().await.map_err(|e| {
             log::error!("{:?}", e);
-            ApiClientError::PostError(e.to_string())
+            ApiClientError::Unreachable(e.to_string())
         })?;
         let status = response.status();
+
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(Box::new(ApiClientError::Unauthorized));
+        }
+
         let body = response.text().await.unwrap_or_default();
 
+        if status.is_server_error() {
+            log::trace!("{:?}", body);
+            return Err(Box::new(ApiClientError::ServerError(status.to_string())));
+        }
+
         let parsed: ApiResponse<K> = serde_json::from_str(&body).map_err(|e| {
             log::error!("{:?}", e);
             log::trace!("{:?}", body);
@@ -69,3 +160,25 @@ impl ApiClient {
         Ok(parsed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_paths_below_the_base_path() {
+        for base in ["https://host/flowlet", "https://host/flowlet/"] {
+            let client = ApiClient::new(base, None).unwrap();
+            assert_eq!(
+                client.url("/insert-one/workflow").unwrap().as_str(),
+                "https://host/flowlet/insert-one/workflow"
+            );
+        }
+
+        let client = ApiClient::new("http://localhost:8080", None).unwrap();
+        assert_eq!(
+            client.url("/find-many/command").unwrap().as_str(),
+            "http://localhost:8080/find-many/command"
+        );
+    }
+}
@@ -1,11 +1,31 @@
+use clap::Parser;
+use thiserror::Error;
+
+use crate::cli::completions::CompletionsCli;
+use crate::cli::config::ConfigCli;
+use crate::cli::env::EnvCli;
+use crate::cli::history::{HistoryCli, HistoryFilter};
+use crate::cli::import::ImportCli;
 use crate::cli::project::ProjectCli;
+use crate::cli::sync::SyncCli;
 use crate::cli::task::TaskCli;
-use crate::cli::{Auth, Commands, Project, RootCommands, Task, Vars};
+use crate::cli::workflow::WorkflowCli;
+use crate::cli::{
+    Auth, Commands, Config, Env, History, Project, RootCommands, SyncCommands, Task, Vars, Workflow,
+};
 use crate::cli::{command::Command, variable::Variable};
 use crate::flowlet_context::{FlowletContext, WithContext};
 use crate::printer::{Icon, Printer};
+use crate::shell::Shell;
+use crate::tui::Tui;
 use crate::util::FlowletResult;
 
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("`{0}` could not be run as a saved command.")]
+    NotACommandRun(String),
+}
+
 pub struct App<'a> {
     pub ctx: &'a FlowletContext,
 }
@@ -18,15 +38,33 @@ impl<'a> WithContext for App<'a> {
 
 impl<'a> App<'a> {
     pub async fn run(&self, cli: crate::cli::Cli) -> FlowletResult<()> {
+        // Catch up on remote writes queued by earlier runs
+        if !matches!(
+            cli.root_commands,
+            RootCommands::Sync(_)
+                | RootCommands::Config(_)
+                | RootCommands::ShellInit { .. }
+                | RootCommands::Completions { .. }
+                | RootCommands::Complete { .. }
+        ) {
+            SyncCli::flush_pending(self).await;
+        }
+
         match cli.root_commands {
             RootCommands::Command(commands) => match commands {
-                Commands::Run {
+                Commands::Run(args) => Command::run(self, args).await,
+                Commands::Save {
                     name,
-                    save_var,
-                    json_path,
-                } => Command::run(self, name, save_var, json_path).await,
-                Commands::Save { name, cmd } => Command::save(self, name, cmd).await,
-                Commands::Ls { remote, global } => Command::list(self, remote, global).await,
+                    cmd,
+                    last,
+                    pick: _,
+                    settings,
+                } => Command::capture(self, name, cmd, last, settings).await,
+                Commands::Ls {
+                    remote,
+                    global,
+                    list,
+                } => Command::list(self, remote, global, list).await,
                 Commands::Show { name } => Command::show(self, name).await,
                 Commands::Rm { name } => Command::remove(self, name).await,
                 Commands::Edit { name } => Command::edit(self, name).await,
@@ -34,46 +72,135 @@ impl<'a> App<'a> {
                 Commands::Pull { name } => Command::pull(self, name).await,
             },
             RootCommands::Vars(vars) => match vars {
-                Vars::Ls => Variable::list(self).await,
-                Vars::Set { key, value } => Variable::add(self, key, value).await,
-                Vars::Rm { key } => Variable::remove(self, key).await,
+                Vars::Ls { all, list } => Variable::list(self, all, list).await,
+                Vars::Set {
+                    key,
+                    value,
+                    secret,
+                    no_secret,
+                    passphrase,
+                    project,
+                    env,
+                } => {
+                    // `--no-secret` downgrades an existing secret, neither flag keeps it as is
+                    let secret = (secret || no_secret).then_some(secret);
+                    Variable::add(self, key, value, secret, passphrase, project, env).await
+                }
+                Vars::Rm { key, project, env } => Variable::remove(self, key, project, env).await,
+            },
+            RootCommands::Env(env) => match env {
+                Env::Show => EnvCli::show(self).await,
+                Env::Use { name } => EnvCli::use_env(self, name).await,
+            },
+            RootCommands::Config(config) => match config {
+                Config::Get { key } => ConfigCli::get(self, key).await,
+                Config::Set { key, value } => ConfigCli::set(self, key, value).await,
+                Config::Ls => ConfigCli::list(self).await,
+            },
+            RootCommands::Sync(sync) => match sync.command {
+                None => SyncCli::run(self, sync.dry_run).await,
+                Some(SyncCommands::Status) => SyncCli::status(self).await,
+                Some(SyncCommands::Clear) => SyncCli::clear(self).await,
             },
             RootCommands::Auth(auth) => match auth {
                 Auth::Login => crate::cli::auth::Auth::login(self).await,
                 Auth::Register => crate::cli::auth::Auth::register(self).await,
                 Auth::Logout => crate::cli::auth::Auth::logout(self).await,
+                Auth::Status => crate::cli::auth::Auth::status(self).await,
+                Auth::Whoami => crate::cli::auth::Auth::whoami(self).await,
+            },
+            RootCommands::Completions { shell } => CompletionsCli::generate(shell),
+            RootCommands::Complete { kind } => CompletionsCli::complete(self, kind).await,
+            RootCommands::ShellInit { shell } => {
+                let shell = match shell {
+                    Some(shell) => shell,
+                    None => Shell::detect()?,
+                };
+                print!("{}", shell.init_script()?);
+                Ok(())
+            }
+            RootCommands::Import {
+                from,
+                all,
+                existing,
+            } => ImportCli::import(self, from, all, existing).await,
+            RootCommands::Tui { remote } => Tui::run(self, remote).await,
+            RootCommands::History(history) => match history {
+                History::Ls {
+                    command,
+                    project,
+                    status,
+                    since,
+                    until,
+                    limit,
+                } => {
+                    let filter = HistoryFilter {
+                        command,
+                        project,
+                        status,
+                        since,
+                        until,
+                        limit,
+                    };
+                    HistoryCli::list(self, filter).await
+                }
+                History::Show { _id } => HistoryCli::show(self, _id).await,
+                History::Rerun { _id } => HistoryCli::rerun(self, _id).await,
             },
             RootCommands::Project(project) => match project {
                 Project::New => ProjectCli::new(self).await,
                 Project::Rm { name } => ProjectCli::remove(self, name).await,
-                Project::Ls => ProjectCli::list(self).await,
+                Project::Ls { list } => ProjectCli::list(self, list).await,
+                Project::Apply => ProjectCli::apply(self).await,
             },
             RootCommands::Task(task) => match task {
                 Task::New => TaskCli::new(self).await,
-                Task::Ls { remote, global } => TaskCli::list(self, remote, global).await,
+                Task::Ls {
+                    remote,
+                    global,
+                    list,
+                } => TaskCli::list(self, remote, global, list).await,
                 Task::Rm { _id } => TaskCli::remove(self, _id).await,
                 Task::Show { _id } => TaskCli::show(self, _id).await,
                 Task::Edit { _id } => TaskCli::edit(self, _id).await,
             },
+            RootCommands::Workflow(workflow) => match workflow {
+                Workflow::New { name } => WorkflowCli::new(self, name).await,
+                Workflow::Run {
+                    name,
+                    on_missing,
+                    yes,
+                    args,
+                } => WorkflowCli::run(self, name, on_missing, yes, args).await,
+                Workflow::Ls {
+                    remote,
+                    global,
+                    list,
+                } => WorkflowCli::list(self, remote, global, list).await,
+                Workflow::Show { name } => WorkflowCli::show(self, name).await,
+                Workflow::Rm { name } => WorkflowCli::remove(self, name).await,
+                Workflow::Push { name } => WorkflowCli::push(self, name).await,
+                Workflow::Pull { name } => WorkflowCli::pull(self, name).await,
+            },
             RootCommands::Unknown(args) => {
                 if args.is_empty() {
                     Printer::error(Icon::Error, "Error", "No command provided.");
-                    Ok(())
-                } else if args.len() > 1 {
-                    Printer::error(
-                        Icon::Error,
-                        "Error",
-                        &format!(
-                            "Multiple arguments detected. Please use the proper command syntax: `flowlet command run {:?} ...`",
-                            args.first()
-                        ),
-                    );
-                    Ok(())
-                } else if let Some(name) = args.first() {
-                    Command::run(self, name.clone(), None, None).await
-                } else {
-                    Printer::error(Icon::Error, "Error", "No command provided.");
-                    Ok(())
+                    return Ok(());
+                }
+
+                let name = args[0].clone();
+
+                // `flowlet myCommand -- ...` is shorthand for `flowlet command run myCommand -- ...`
+                let argv = ["flowlet", "command", "run"]
+                    .into_iter()
+                    .map(String::from)
+                    .chain(args);
+
+                let cli = crate::cli::Cli::try_parse_from(argv).unwrap_or_else(|e| e.exit());
+
+                match cli.root_commands {
+                    RootCommands::Command(Commands::Run(args)) => Command::run(self, args).await,
+                    _ => Err(Box::new(AppError::NotACommandRun(name))),
                 }
             }
         }
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use dialoguer::{Input, Password};
 use serde_json::json;
 use thiserror::Error;
@@ -69,4 +70,53 @@ impl Auth {
         Printer::success(Icon::Auth, "Goodbye!", "You have been logged out.");
         Ok(())
     }
+
+    pub async fn status(ctx: &impl WithContext) -> FlowletResult<()> {
+        let Some(auth) = models::auth::Auth::read(ctx.get(), EmptyData).await? else {
+            Printer::warning(Icon::Warning, "Signed Out", "Not logged in.");
+            return Ok(());
+        };
+
+        if let Some(claims) = auth.claims() {
+            if let Some(email) = claims.email.or(claims.sub) {
+                Printer::info(Icon::Auth, "Token For:", &email);
+            }
+            if let Some(expires) = claims.exp.and_then(|exp| DateTime::from_timestamp(exp, 0)) {
+                let label = if expires < Utc::now() {
+                    "Expired:"
+                } else {
+                    "Expires:"
+                };
+                Printer::info(Icon::Info, label, &expires.to_rfc3339());
+            }
+        }
+
+        let session = models::auth::Auth::session(ctx.get()).await?;
+
+        Printer::success(
+            Icon::Auth,
+            "Signed In",
+            &format!(
+                "Session is valid for {}.",
+                session.email.unwrap_or(session.user_id)
+            ),
+        );
+        Ok(())
+    }
+
+    pub async fn whoami(ctx: &impl WithContext) -> FlowletResult<()> {
+        if !ctx.get().api_client.is_authenticated() {
+            Printer::warning(Icon::Warning, "Signed Out", "Not logged in.");
+            return Ok(());
+        }
+
+        let session = models::auth::Auth::session(ctx.get()).await?;
+
+        if Printer::serialized(&session)? {
+            return Ok(());
+        }
+
+        println!("{}", session.email.unwrap_or(session.user_id));
+        Ok(())
+    }
 }
@@ -1,21 +1,37 @@
+use chrono::{DateTime, Utc};
 use deeb::Query;
-use dialoguer::Confirm;
+use dialoguer::{Confirm, FuzzySelect, Input};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 use crate::{
+    cli::{CommandSettings, ListArgs, RunArgs, variable::Variable},
+    extract::{Extraction, Extractor, SaveVar, Source},
     flowlet_context::WithContext,
+    flowlet_db::metadata::Metadata,
     flowlet_db::models::{
         self, Api,
         command::{
-            CreateCommandInput, ListCommandInput, ReadCommandInput, RemoveCommandInput,
-            UpdateCommandInput,
+            CommandMode, CommandOptions, CreateCommandInput, ListCommandInput, ReadCommandInput,
+            RemoveCommandInput, UpdateCommandInput,
         },
-        variable::{ReadVariableInput, UpdateVariableInput},
+        run::{CreateRunInput, Run},
+        variable::UpdateVariableInput,
     },
     printer::{Icon, Printer},
+    project_file::{CommandSpec, ProjectFile},
+    runner::{OutputSink, RunOutput, prepare, run_streaming},
+    secrets::{KeySource, MASK, encrypt, find_secrets, mask_secrets},
+    shell,
     util::{
-        FlowletResult, clean_command, extract_json_path, find_project_config, inject_variables,
-        launch_editor,
+        CommandArgs, FlowletResult, InjectedCommand, MissingVariables, SortBy, Timespan,
+        find_project_config, find_project_environment, find_project_root, fold_continuations,
+        inject_variables, launch_editor, parse_placeholders, parse_positional_parameters,
+        resolve_variables, sort_records,
     },
 };
 
@@ -30,14 +46,221 @@ pub enum CliCommandError {
     #[error("Command execution failed.")]
     CommandExecutionFailed,
 
-    #[error("Command exited with error: {0}")]
-    CommandExitedWithError(String),
+    #[error("Command exited with code {0}.")]
+    CommandExitedWithError(i32),
+
+    #[error("`{0}` has to be confirmed before it runs. Pass `--yes` to run it without asking.")]
+    ConfirmationRequired(String),
+
+    #[error("Cancelled, `{0}` was not run.")]
+    NotConfirmed(String),
+}
+
+impl CliCommandError {
+    /// The exit code Flowlet should exit with when this error ends the process.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliCommandError::CommandExitedWithError(code) => *code,
+            _ => 1,
+        }
+    }
+}
+
+/// A finished run of a command, as returned by [`Command::execute`].
+pub struct Execution {
+    /// The command as executed, secrets masked.
+    pub command: String,
+    /// Decrypted secret values injected into the command.
+    pub secrets: Vec<String>,
+    /// The directory the command ran in.
+    pub cwd: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+    /// How many times the command was started, more than once when it was retried.
+    pub attempts: u32,
+    /// The output of the last attempt.
+    pub output: RunOutput,
+}
+
+/// A command with its variables, arguments and directory resolved, ready to start.
+pub struct ResolvedRun {
+    /// The text handed to the interpreter.
+    pub executed: String,
+    /// Whether `executed` runs from a script file.
+    pub script: bool,
+    pub interpreter: String,
+    pub positional: Vec<String>,
+    pub env: HashMap<String, String>,
+    /// Decrypted secret values used in the command or its environment.
+    pub secrets: Vec<String>,
+    pub cwd: PathBuf,
+}
+
+/// What a run would do, secrets masked, as shown by `--dry-run` and confirmations.
+#[derive(Serialize)]
+struct RunPlan {
+    name: String,
+    command: String,
+    interpreter: String,
+    cwd: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    environment: Option<String>,
+}
+
+impl ResolvedRun {
+    fn plan(&self, name: &str) -> RunPlan {
+        let mask = |value: &str| mask_secrets(value, &self.secrets);
+
+        RunPlan {
+            name: name.to_string(),
+            command: mask(&self.executed),
+            interpreter: self.interpreter.clone(),
+            cwd: self.cwd.to_string_lossy().to_string(),
+            args: self.positional.iter().map(|arg| mask(arg)).collect(),
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), mask(value)))
+                .collect(),
+            environment: find_project_environment().ok().flatten(),
+        }
+    }
+}
+
+/// A row of `flowlet command ls`, with where the command is defined.
+#[derive(Serialize)]
+struct CommandListing {
+    #[serde(flatten)]
+    listed: Listed,
+    source: String,
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Listed {
+    Stored(models::command::Command),
+    /// Only declared in `flowlet.toml`.
+    Declared {
+        name: String,
+        project: Option<String>,
+        #[serde(flatten)]
+        spec: CommandSpec,
+    },
+}
+
+impl Listed {
+    fn name(&self) -> &str {
+        match self {
+            Listed::Stored(command) => &command.name,
+            Listed::Declared { name, .. } => name,
+        }
+    }
+}
+
+/// Recent shell commands offered by `command save --pick`.
+const PICK_LIMIT: usize = 200;
+
 pub struct Command;
 
 impl Command {
-    pub async fn save(ctx: &impl WithContext, name: String, cmd: String) -> FlowletResult<()> {
+    /// Saves a command given on the command line or taken from shell history, offering
+    /// to move likely secrets into variables first.
+    ///
+    /// Without `cmd` or `last`, the command is picked from recent shell history.
+    pub async fn capture(
+        ctx: &impl WithContext,
+        name: String,
+        cmd: Option<String>,
+        last: bool,
+        settings: CommandSettings,
+    ) -> FlowletResult<()> {
+        let cmd = match cmd {
+            Some(cmd) => cmd,
+            None if last => {
+                let cmd = shell::recent_commands(1)?.remove(0);
+                Printer::info(Icon::Info, "Captured:", &cmd);
+                cmd
+            }
+            None => {
+                let recent = shell::recent_commands(PICK_LIMIT)?;
+                let selection = FuzzySelect::new()
+                    .with_prompt("Pick a command to save")
+                    .items(&recent)
+                    .default(0)
+                    .interact()?;
+                recent[selection].clone()
+            }
+        };
+
+        let cmd = Self::extract_secrets(ctx, cmd).await?;
+
+        Self::save(ctx, name, cmd, settings).await
+    }
+
+    /// Offers to replace each likely secret in `cmd` with a `${var}` placeholder, saving
+    /// the value as a secret variable. Commands are kept as they are when not interactive.
+    async fn extract_secrets(ctx: &impl WithContext, cmd: String) -> FlowletResult<String> {
+        if !std::io::stdin().is_terminal() {
+            return Ok(cmd);
+        }
+
+        let mut cmd = cmd;
+
+        for candidate in find_secrets(&cmd) {
+            let preview: String = candidate.value.chars().take(3).collect();
+
+            let replace = Confirm::new()
+                .with_prompt(format!(
+                    "`{}{}` looks like a secret. Save it as a secret variable?",
+                    preview, MASK
+                ))
+                .default(true)
+                .interact()?;
+
+            if !replace {
+                continue;
+            }
+
+            let var_name: String = Input::new()
+                .with_prompt("Variable name")
+                .default(candidate.name.clone())
+                .validate_with(|name: &String| {
+                    if !name.is_empty()
+                        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        Ok(())
+                    } else {
+                        Err("Use letters, numbers and underscores only.")
+                    }
+                })
+                .interact_text()?;
+
+            Variable::add(
+                ctx,
+                var_name.clone(),
+                Some(candidate.value.clone()),
+                Some(true),
+                false,
+                None,
+                None,
+            )
+            .await?;
+
+            cmd = cmd.replace(&candidate.value, &format!("${{{}}}", var_name));
+        }
+
+        Ok(cmd)
+    }
+
+    /// Saves `cmd` under `name`. An existing command keeps the options not set in
+    /// `settings`, a new one is a script when it starts with a shebang unless a mode is set.
+    pub async fn save(
+        ctx: &impl WithContext,
+        name: String,
+        cmd: String,
+        settings: CommandSettings,
+    ) -> FlowletResult<()> {
         // Detect Project Dir
         let project = find_project_config().ok().flatten();
 
@@ -50,22 +273,28 @@ impl Command {
         )
         .await?;
 
-        if command.is_some() {
+        if let Some(command) = command {
             models::command::Command::update(
                 ctx.get(),
                 UpdateCommandInput {
                     name: name.clone(),
                     cmd,
+                    options: (!settings.is_empty()).then(|| settings.apply(command.options)),
                 },
             )
             .await?;
         } else {
+            let detected = CommandOptions {
+                mode: CommandMode::detect(&cmd),
+                ..CommandOptions::default()
+            };
             models::command::Command::create(
                 ctx.get(),
                 CreateCommandInput {
                     name: name.clone(),
                     cmd,
                     project,
+                    options: settings.apply(detected),
                 },
             )
             .await?;
@@ -79,7 +308,12 @@ impl Command {
         Ok(())
     }
 
-    pub async fn list(ctx: &impl WithContext, remote: bool, global: bool) -> FlowletResult<()> {
+    pub async fn list(
+        ctx: &impl WithContext,
+        remote: bool,
+        global: bool,
+        list: ListArgs,
+    ) -> FlowletResult<()> {
         // Detect Project Dir
         let project = find_project_config().ok().flatten();
 
@@ -92,172 +326,748 @@ impl Command {
             }
         }
 
-        let commands =
+        let mut commands =
             models::command::Command::list(ctx.get(), ListCommandInput { query, remote }).await?;
 
-        let rows: Vec<Vec<String>> = commands
+        if let Some(sort) = list.sort {
+            sort_records(&mut commands, sort, |cmd| &cmd.name);
+        }
+
+        let file = ProjectFile::load()?;
+        let declared = file.as_ref().map(|f| &f.commands);
+        let is_declared = |name: &str| declared.is_some_and(|d| d.contains_key(name));
+
+        let stored_source = if remote { "remote" } else { "local" };
+
+        let mut listings: Vec<CommandListing> = Vec::new();
+
+        for command in commands {
+            let source = if is_declared(&command.name) {
+                format!("{} + flowlet.toml", stored_source)
+            } else {
+                stored_source.to_string()
+            };
+
+            listings.push(CommandListing {
+                listed: Listed::Stored(command),
+                source,
+            });
+        }
+
+        // Commands only declared in the file come after the stored ones
+        if let Some(file) = &file {
+            for (name, spec) in &file.commands {
+                if listings.iter().any(|l| l.listed.name() == name) {
+                    continue;
+                }
+
+                listings.push(CommandListing {
+                    listed: Listed::Declared {
+                        name: name.clone(),
+                        project: file.project_name(),
+                        spec: spec.clone(),
+                    },
+                    source: "flowlet.toml".to_string(),
+                });
+            }
+        }
+
+        if list.sort == Some(SortBy::Name) {
+            listings.sort_by(|a, b| a.listed.name().cmp(b.listed.name()));
+        }
+
+        if Printer::serialized(&listings)? {
+            return Ok(());
+        }
+
+        let rows: Vec<Vec<String>> = listings
             .into_iter()
-            .map(|cmd| vec![cmd.name, cmd.cmd, cmd.project.unwrap_or("--".to_string())])
+            .map(|listing| {
+                let (name, cmd, options, project, metadata) = match listing.listed {
+                    Listed::Stored(cmd) => (
+                        cmd.name,
+                        cmd.cmd,
+                        cmd.options,
+                        cmd.project,
+                        cmd.metadata.columns(),
+                    ),
+                    Listed::Declared {
+                        name,
+                        project,
+                        spec,
+                    } => (
+                        name,
+                        spec.cmd,
+                        spec.options,
+                        project,
+                        vec!["--".to_string(); Metadata::HEADERS.len()],
+                    ),
+                };
+
+                let interpreter = Self::interpreter(ctx, &options, &cmd);
+
+                let mut row = vec![
+                    name,
+                    cmd,
+                    interpreter,
+                    project.unwrap_or("--".to_string()),
+                    listing.source,
+                ];
+                if list.long {
+                    row.extend(metadata);
+                }
+                row
+            })
             .collect();
 
+        let mut headers = vec!["Name", "Command", "Interpreter", "Project", "Source"];
+        if list.long {
+            headers.extend(Metadata::HEADERS);
+        }
+
         Printer::success(Icon::Success, "Success", "Found commands!");
-        Printer::table(vec!["Name", "Command", "Project"], rows);
+        Printer::table(headers, rows);
         Ok(())
     }
 
-    pub async fn run(
-        ctx: &impl WithContext,
-        name: String,
-        save_var: Option<String>,
-        json_path: Option<String>,
-    ) -> FlowletResult<()> {
-        use crate::flowlet_db::models::variable::{CreateVariableInput, Variable};
+    pub async fn run(ctx: &impl WithContext, run_args: RunArgs) -> FlowletResult<()> {
+        let default_extractor = Self::default_extractor(&run_args);
+        let save_vars = run_args
+            .save_var
+            .iter()
+            .map(|spec| SaveVar::parse(spec, &default_extractor))
+            .collect::<FlowletResult<Vec<_>>>()?;
+
+        let RunArgs {
+            name,
+            on_missing,
+            secret,
+            export_vars,
+            limits,
+            dry_run,
+            yes,
+            args,
+            ..
+        } = run_args;
+        let parsed_args = CommandArgs::parse(&args)?;
+
+        let mut command = Self::find(ctx, &name).await?;
+        command.options = limits.apply(command.options);
+        command.options.export_vars |= export_vars;
+
+        let resolved = Self::resolve(
+            ctx,
+            &command.name,
+            &command.cmd,
+            &command.options,
+            &parsed_args,
+            on_missing,
+        )
+        .await?;
 
+        if dry_run {
+            return Self::dry_run(&command.name, &resolved);
+        }
+
+        Printer::info(Icon::Rocket, "Running Command:", &command.name);
+
+        let execution = Self::launch(
+            &command.name,
+            &command.options,
+            resolved,
+            yes,
+            OutputSink::Terminal,
+        )
+        .await?;
+
+        // History is best effort, a failure to record must not fail the run
+        if let Err(e) = Self::record_run(ctx, &command.name, &args, &execution).await {
+            log::warn!("Failed to record run: {:?}", e);
+        }
+
+        let output = execution.output;
+
+        if !output.success() {
+            return Err(Box::new(CliCommandError::CommandExitedWithError(
+                output.exit_code,
+            )));
+        }
+
+        // If we want to save the result
+        for save_var in &save_vars {
+            Self::save_output(ctx, save_var, &output, secret).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds a saved command, falling back to one declared in `flowlet.toml`.
+    pub async fn find(
+        ctx: &impl WithContext,
+        name: &str,
+    ) -> FlowletResult<models::command::Command> {
         let command = models::command::Command::read(
             ctx.get(),
             ReadCommandInput {
-                query: Query::eq("name", name.clone()),
+                query: Query::eq("name", name.to_string()),
                 remote: false,
             },
         )
         .await?;
 
-        let command = match command {
-            Some(c) => c,
-            None => return Err(Box::new(CliCommandError::CommandNotFound)),
+        if let Some(command) = command {
+            return Ok(command);
+        }
+
+        ProjectFile::load()?
+            .and_then(|file| file.command(name, ctx.get().author()))
+            .ok_or_else(|| Box::new(CliCommandError::CommandNotFound) as Box<dyn std::error::Error>)
+    }
+
+    /// Builds the extractor used by `--save-var` targets that don't name their own.
+    fn default_extractor(run_args: &RunArgs) -> Extractor {
+        let extraction = if let Some(path) = &run_args.json_path {
+            Extraction::JsonPath {
+                path: path.clone(),
+                join: run_args.join.clone(),
+            }
+        } else if let Some(pattern) = &run_args.regex {
+            Extraction::Regex {
+                pattern: pattern.clone(),
+                group: run_args.group.clone(),
+            }
+        } else if let Some(line) = run_args.line {
+            Extraction::Line(line)
+        } else {
+            Extraction::Raw
         };
 
-        if command.cmd.is_empty() {
-            return Err(Box::new(CliCommandError::EmptyCommand(command.name)));
+        Extractor {
+            source: if run_args.from_stderr {
+                Source::Stderr
+            } else {
+                Source::Stdout
+            },
+            extraction,
+            trim: run_args.trim,
         }
+    }
 
-        let injected_command = inject_variables(ctx, &command.cmd).await?;
-        let cleaned_command = clean_command(&injected_command);
+    /// Injects variables into `cmd` and runs it with `options`, streaming output to the
+    /// terminal. Commands needing confirmation ask for it unless `yes` is set.
+    ///
+    /// The returned output carries the exit code; a non-zero exit is not an error here so
+    /// callers such as workflows can decide whether to continue.
+    pub async fn execute(
+        ctx: &impl WithContext,
+        name: &str,
+        cmd: &str,
+        options: &CommandOptions,
+        args: &CommandArgs,
+        on_missing: MissingVariables,
+        yes: bool,
+    ) -> FlowletResult<Execution> {
+        let resolved = Self::resolve(ctx, name, cmd, options, args, on_missing).await?;
+        Self::launch(name, options, resolved, yes, OutputSink::Terminal).await
+    }
 
-        Printer::info(Icon::Rocket, "Running Command:", &command.name);
+    /// Like [`Command::execute`], forwarding output to `sink` instead of the terminal.
+    /// Commands needing confirmation are refused, as there is no terminal to ask on.
+    pub async fn execute_to(
+        ctx: &impl WithContext,
+        name: &str,
+        cmd: &str,
+        options: &CommandOptions,
+        args: &CommandArgs,
+        on_missing: MissingVariables,
+        sink: OutputSink,
+    ) -> FlowletResult<Execution> {
+        let resolved = Self::resolve(ctx, name, cmd, options, args, on_missing).await?;
+        Self::launch(name, options, resolved, false, sink).await
+    }
+
+    /// Injects variables into `cmd` and works out how it runs with `options`, without
+    /// starting anything.
+    pub async fn resolve(
+        ctx: &impl WithContext,
+        name: &str,
+        cmd: &str,
+        options: &CommandOptions,
+        args: &CommandArgs,
+        on_missing: MissingVariables,
+    ) -> FlowletResult<ResolvedRun> {
+        if cmd.is_empty() {
+            return Err(Box::new(CliCommandError::EmptyCommand(name.to_string())));
+        }
 
-        // Capture output
-        let output = tokio::process::Command::new("sh")
-            .arg("-c")
-            .arg(&cleaned_command)
-            .output()
-            .await
-            .map_err(|e| {
-                log::error!("Failed to run command: {:?}", e);
-                CliCommandError::CommandExecutionFailed
-            })?;
+        let args = args.with_defaults(&options.args)?;
+        let cwd = Self::working_dir(options)?;
+
+        let (injected, env) = Self::inject(ctx, cmd, options, &args, on_missing).await?;
+        let interpreter = options
+            .interpreter
+            .clone()
+            .unwrap_or_else(|| ctx.get().config.shell().to_string());
+
+        // Scripts keep their lines, one-liners have `\` continuations folded
+        let script =
+            options.mode == CommandMode::Script || injected.command.trim_start().starts_with("#!");
+        let executed = if script {
+            injected.command
+        } else {
+            fold_continuations(&injected.command)
+        };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        Ok(ResolvedRun {
+            executed,
+            script,
+            interpreter,
+            positional: args.positional,
+            env,
+            secrets: injected.secrets,
+            cwd,
+        })
+    }
 
-        if !stdout.trim().is_empty() {
-            println!("{}", stdout);
+    /// Starts a resolved command, retrying it as `options` say. Commands needing
+    /// confirmation are shown and asked about first unless `yes` is set.
+    pub async fn launch(
+        name: &str,
+        options: &CommandOptions,
+        resolved: ResolvedRun,
+        yes: bool,
+        sink: OutputSink,
+    ) -> FlowletResult<Execution> {
+        if Self::needs_confirmation(options) && !yes {
+            Self::confirm(name, &resolved, &sink)?;
         }
 
-        if !stderr.trim().is_empty() {
-            eprintln!("{}", stderr);
+        let ResolvedRun {
+            executed,
+            script,
+            interpreter,
+            positional,
+            env,
+            secrets,
+            cwd,
+        } = resolved;
+
+        // Kept until the run ends, the script file is removed when dropped
+        let (mut process, _script) = prepare(&executed, name, &interpreter, script)?;
+
+        process.args(&positional).current_dir(&cwd).envs(&env);
+
+        let started_at = Utc::now();
+        let timer = Instant::now();
+        let timeout = options.timeout.map(|timeout| timeout.0);
+        let tries = options.retries + 1;
+        let mut attempts = 1;
+
+        let output = loop {
+            // Stream output while capturing stdout for `--save-var`
+            let output = run_streaming(&mut process, &secrets, sink.clone(), timeout)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to run command: {:?}", e);
+                    CliCommandError::CommandExecutionFailed
+                })?;
+
+            if let (true, Some(timeout)) = (output.timed_out, options.timeout) {
+                Printer::warning(
+                    Icon::Warning,
+                    "Timed Out",
+                    &format!("`{}` was stopped after {}.", name, timeout),
+                );
+            }
+
+            if attempts == tries || !options.retries_on(output.exit_code) {
+                break output;
+            }
+
+            let delay = options.retry_delay_after(attempts);
+            Printer::warning(
+                Icon::Warning,
+                "Retrying",
+                &format!(
+                    "Attempt {} of {} exited with code {}, trying again in {}.",
+                    attempts,
+                    tries,
+                    output.exit_code,
+                    Timespan(delay)
+                ),
+            );
+
+            // Ctrl-C while waiting gives up on the remaining attempts
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = tokio::signal::ctrl_c() => break output,
+            }
+
+            attempts += 1;
+        };
+
+        if attempts > 1 && output.success() {
+            Printer::info(
+                Icon::Info,
+                "Attempts:",
+                &format!("Succeeded on attempt {} of {}.", attempts, tries),
+            );
         }
 
-        if !output.status.success() {
-            return Err(Box::new(CliCommandError::CommandExitedWithError(
-                output
-                    .status
-                    .code()
-                    .map_or("unknown".to_string(), |code| code.to_string()),
+        Ok(Execution {
+            command: mask_secrets(&executed, &secrets),
+            secrets,
+            cwd,
+            started_at,
+            duration: timer.elapsed(),
+            attempts,
+            output,
+        })
+    }
+
+    /// Whether a run has to be confirmed: the command asks for it, or the project is set to
+    /// its production environment.
+    pub fn needs_confirmation(options: &CommandOptions) -> bool {
+        let environment = find_project_environment().ok().flatten();
+        options.confirm || matches!(environment.as_deref(), Some("prod" | "production"))
+    }
+
+    /// Shows what is about to run and asks to go ahead.
+    fn confirm(name: &str, resolved: &ResolvedRun, sink: &OutputSink) -> FlowletResult<()> {
+        let interactive = matches!(sink, OutputSink::Terminal) && std::io::stdin().is_terminal();
+        if !interactive {
+            return Err(Box::new(CliCommandError::ConfirmationRequired(
+                name.to_string(),
             )));
         }
 
-        // If we want to save the result
-        if let Some(var_name) = save_var {
-            let exists = Variable::read(
-                ctx.get(),
-                ReadVariableInput {
-                    query: Query::eq("name", var_name.clone()),
-                },
-            )
-            .await?;
+        Printer::warning(
+            Icon::Warning,
+            "Confirm",
+            &format!("`{}` is marked as dangerous.", name),
+        );
+        let plan = resolved.plan(name);
+        Printer::details_kv(&plan.name, &Self::plan_fields(&plan));
+        Printer::multi_line_info("Runs:", plan.command.lines().collect());
 
-            let value_to_save = if let Some(path) = json_path {
-                // Try to parse as JSON and extract value at path
-                match serde_json::from_str::<serde_json::Value>(&stdout) {
-                    Ok(json_value) => extract_json_path(&json_value, &path)
-                        .map(|v| v.to_string())
-                        .unwrap_or_else(|| {
-                            Printer::warning(
-                                Icon::Warning,
-                                "Path Not Found",
-                                "Falling back to full response.",
-                            );
-                            stdout.clone()
-                        }),
-                    Err(_) => {
-                        Printer::warning(
-                            Icon::Warning,
-                            "Invalid JSON",
-                            "Falling back to raw output.",
-                        );
-                        stdout.clone()
-                    }
-                }
-            } else {
-                stdout.clone()
-            };
+        let confirmed = Confirm::new()
+            .with_prompt(format!("Run `{}`?", name))
+            .default(false)
+            .interact()?;
 
-            match exists {
-                Some(_) => {
-                    Variable::update(
-                        ctx.get(),
-                        UpdateVariableInput {
-                            name: var_name.clone(),
-                            value: value_to_save.clone(),
-                        },
-                    )
-                    .await?;
-                }
-                None => {
-                    // Save to Variable
-                    Variable::create(
-                        ctx.get(),
-                        CreateVariableInput {
-                            name: var_name.clone(),
-                            value: value_to_save.clone(),
-                        },
-                    )
-                    .await?;
-                }
-            }
+        if !confirmed {
+            return Err(Box::new(CliCommandError::NotConfirmed(name.to_string())));
+        }
 
-            Printer::success(
-                Icon::Success,
-                "Saved Variable",
-                &format!("${} = {}", var_name, value_to_save),
-            );
+        Ok(())
+    }
+
+    /// Prints what `name` would run without running it.
+    fn dry_run(name: &str, resolved: &ResolvedRun) -> FlowletResult<()> {
+        let plan = resolved.plan(name);
+
+        if Printer::serialized(&plan)? {
+            return Ok(());
         }
 
+        Printer::info(Icon::Info, "Dry Run:", "Nothing was run.");
+        Printer::block_kv(&plan.name, &Self::plan_fields(&plan));
+        Printer::multi_line_info("Runs:", plan.command.lines().collect());
         Ok(())
     }
 
-    pub async fn show(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
-        let command = models::command::Command::read(
+    fn plan_fields(plan: &RunPlan) -> Vec<(&'static str, String)> {
+        let or_dash = |value: String| if value.is_empty() { "-".into() } else { value };
+
+        let env: Vec<String> = plan
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+
+        vec![
+            (
+                "Environment",
+                plan.environment.clone().unwrap_or_else(|| "-".into()),
+            ),
+            ("Interpreter", plan.interpreter.clone()),
+            ("Directory", plan.cwd.clone()),
+            ("Arguments", or_dash(plan.args.join(" "))),
+            ("Variables", or_dash(env.join(" "))),
+        ]
+    }
+
+    /// Fills in the placeholders of `cmd` and the values of its environment variables.
+    ///
+    /// With `export_vars`, placeholder values are added to the environment under their own
+    /// name and the command is left as saved, for the shell to expand.
+    async fn inject(
+        ctx: &impl WithContext,
+        cmd: &str,
+        options: &CommandOptions,
+        args: &CommandArgs,
+        on_missing: MissingVariables,
+    ) -> FlowletResult<(InjectedCommand, HashMap<String, String>)> {
+        let (mut injected, mut env) = if options.export_vars {
+            let resolved = resolve_variables(ctx, cmd, args, on_missing).await?;
+            let injected = InjectedCommand {
+                command: cmd.to_string(),
+                secrets: resolved.secrets,
+            };
+            (injected, resolved.values)
+        } else {
+            let injected = inject_variables(ctx, cmd, args, on_missing).await?;
+            (injected, HashMap::new())
+        };
+
+        for (key, value) in &options.env {
+            let value = inject_variables(ctx, value, args, on_missing).await?;
+            injected.secrets.extend(value.secrets);
+            env.insert(key.clone(), value.command);
+        }
+
+        Ok((injected, env))
+    }
+
+    /// The interpreter running `cmd`, falling back to the `shell` setting.
+    fn interpreter(ctx: &impl WithContext, options: &CommandOptions, cmd: &str) -> String {
+        options
+            .interpreter_for(cmd)
+            .unwrap_or_else(|| ctx.get().config.shell().to_string())
+    }
+
+    /// The directory a command runs in. A relative `cwd` is taken from the project root,
+    /// or the current directory outside of a project.
+    fn working_dir(options: &CommandOptions) -> FlowletResult<PathBuf> {
+        let current = std::env::current_dir()?;
+
+        let Some(cwd) = &options.cwd else {
+            return Ok(current);
+        };
+
+        let root = find_project_root()?.unwrap_or(current);
+
+        Ok(root.join(cwd))
+    }
+
+    /// Saves a finished run to the history, masking secrets in everything stored.
+    ///
+    /// Older runs of the command beyond the `history_limit` setting are dropped.
+    pub async fn record_run(
+        ctx: &impl WithContext,
+        name: &str,
+        args: &[String],
+        execution: &Execution,
+    ) -> FlowletResult<()> {
+        let mask = |value: &str| mask_secrets(value, &execution.secrets);
+
+        Run::create(
             ctx.get(),
-            ReadCommandInput {
-                query: Query::eq("name", name),
-                remote: false,
+            CreateRunInput {
+                command: name.to_string(),
+                cmd: execution.command.clone(),
+                args: args.iter().map(|arg| mask(arg)).collect(),
+                cwd: execution.cwd.to_string_lossy().to_string(),
+                project: find_project_config().ok().flatten(),
+                environment: find_project_environment().ok().flatten(),
+                started_at: execution.started_at,
+                duration_ms: execution.duration.as_millis() as u64,
+                exit_code: execution.output.exit_code,
+                attempts: execution.attempts,
+                timed_out: execution.output.timed_out,
+                stdout: mask(&execution.output.stdout),
+                stderr: mask(&execution.output.stderr),
             },
         )
         .await?;
 
-        let command = match command {
-            Some(c) => c,
-            None => return Err(Box::new(CliCommandError::CommandNotFound)),
+        if let Some(limit) = ctx.get().config.history_limit() {
+            Run::prune(ctx.get(), name, limit).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves a value extracted from the output of a run to a variable.
+    ///
+    /// The value is encrypted when `secret` is set or the existing variable is a secret.
+    pub async fn save_output(
+        ctx: &impl WithContext,
+        save_var: &SaveVar,
+        output: &RunOutput,
+        secret: bool,
+    ) -> FlowletResult<()> {
+        use crate::flowlet_db::models::variable::{CreateVariableInput, Variable};
+
+        let var_name = save_var.name.clone();
+
+        // Save where `Variable::resolve` will look for it on the next run
+        let project = find_project_config().ok().flatten();
+        let environment = find_project_environment().ok().flatten();
+
+        let exists = Variable::find_scoped(
+            ctx.get(),
+            &var_name,
+            project.as_deref(),
+            environment.as_deref(),
+        )
+        .await?;
+
+        let extracted = save_var.extractor.extract(output)?;
+        let secret = secret || exists.as_ref().is_some_and(|v| v.secret);
+
+        let (value_to_save, displayed) = if secret {
+            (encrypt(&extracted, KeySource::KeyFile)?, MASK.to_string())
+        } else {
+            (extracted.clone(), extracted)
         };
 
+        match exists {
+            Some(_) => {
+                Variable::update(
+                    ctx.get(),
+                    UpdateVariableInput {
+                        name: var_name.clone(),
+                        value: value_to_save,
+                        project,
+                        environment,
+                        secret,
+                    },
+                )
+                .await?;
+            }
+            None => {
+                // Save to Variable
+                Variable::create(
+                    ctx.get(),
+                    CreateVariableInput {
+                        name: var_name.clone(),
+                        value: value_to_save,
+                        project,
+                        environment,
+                        secret,
+                    },
+                )
+                .await?;
+            }
+        }
+
+        Printer::success(
+            Icon::Success,
+            "Saved Variable",
+            &format!("${} = {}", var_name, displayed),
+        );
+
+        Ok(())
+    }
+
+    pub async fn show(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let command = Self::find(ctx, &name).await?;
+
+        if Printer::serialized(&command)? {
+            return Ok(());
+        }
+
         Printer::info(Icon::Rocket, "Show Command", &command.name);
 
-        let cleaned = clean_command(&command.cmd);
-        let cleaned_lines = cleaned.split('\n').collect::<Vec<&str>>();
+        let options = &command.options;
+        if let Some(description) = &options.description {
+            Printer::info(Icon::Info, "Description:", description);
+        }
 
-        Printer::multi_line_info("To run manually:", cleaned_lines);
+        Printer::info(Icon::Info, "Mode:", &options.mode.to_string());
+        Printer::info(
+            Icon::Info,
+            "Interpreter:",
+            &Self::interpreter(ctx, options, &command.cmd),
+        );
+        Printer::multi_line_info("Command:", command.cmd.lines().collect());
+
+        if !options.args.is_empty() {
+            Printer::info(Icon::Info, "Default Args:", &options.args.join(" "));
+        }
+
+        if let Some(cwd) = &options.cwd {
+            Printer::info(Icon::Info, "Runs In:", cwd);
+        }
+
+        if !options.env.is_empty() {
+            let env: Vec<String> = options
+                .env
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            Printer::multi_line_info("Environment:", env.iter().map(String::as_str).collect());
+        }
+
+        if options.export_vars {
+            Printer::info(
+                Icon::Info,
+                "Variables:",
+                "exported as environment variables",
+            );
+        }
+
+        if options.confirm {
+            Printer::info(Icon::Info, "Confirm:", "asked before every run");
+        }
+
+        if let Some(timeout) = options.timeout {
+            Printer::info(Icon::Info, "Timeout:", &timeout.to_string());
+        }
+
+        if options.retries > 0 {
+            let codes = if options.retry_on_exit_codes.is_empty() {
+                "any failure".to_string()
+            } else {
+                let codes: Vec<String> = options
+                    .retry_on_exit_codes
+                    .iter()
+                    .map(i32::to_string)
+                    .collect();
+                format!("exit codes {}", codes.join(", "))
+            };
+            Printer::info(
+                Icon::Info,
+                "Retries:",
+                &format!(
+                    "{} on {}, waiting {} at first",
+                    options.retries,
+                    codes,
+                    Timespan(options.retry_delay_after(1))
+                ),
+            );
+        }
+
+        let placeholders = parse_placeholders(&command.cmd);
+        let positional = parse_positional_parameters(&command.cmd);
+
+        if !placeholders.is_empty() || !positional.is_empty() {
+            let rows: Vec<Vec<String>> = placeholders
+                .into_iter()
+                .map(|p| {
+                    vec![
+                        p.name,
+                        if p.required { "yes" } else { "no" }.to_string(),
+                        p.default.unwrap_or_else(|| "--".to_string()),
+                        p.message.unwrap_or_else(|| "--".to_string()),
+                    ]
+                })
+                .chain(positional.into_iter().map(|p| {
+                    vec![
+                        p,
+                        "no".to_string(),
+                        "--".to_string(),
+                        "Positional argument".to_string(),
+                    ]
+                }))
+                .collect();
+
+            Printer::table(
+                vec!["Parameter", "Required", "Default", "Description"],
+                rows,
+            );
+        }
 
         Ok(())
     }
@@ -302,9 +1112,9 @@ impl Command {
             None => return Err(Box::new(CliCommandError::CommandNotFound)),
         };
 
-        let text = launch_editor(&command.cmd)?;
+        let text = launch_editor(&command.cmd, ctx.get().config.editor())?;
 
-        Self::save(ctx, name, text).await?;
+        Self::save(ctx, name, text, CommandSettings::default()).await?;
 
         Printer::success(Icon::Success, "Saved", "Command has been updated.");
 
@@ -342,6 +1152,7 @@ impl Command {
                 UpdateCommandInput {
                     name: name.clone(),
                     cmd: command.cmd,
+                    options: Some(command.options),
                 },
             )
             .await?;
@@ -352,6 +1163,7 @@ impl Command {
                     name: name.clone(),
                     cmd: command.cmd,
                     project: command.project,
+                    options: command.options,
                 },
             )
             .await?;
@@ -377,9 +1189,40 @@ impl Command {
             None => return Err(Box::new(CliCommandError::CommandNotFound)),
         };
 
-        Self::save(ctx, name, command.cmd).await?;
+        let stored = models::command::Command::read(
+            ctx.get(),
+            ReadCommandInput {
+                query: Query::eq("name", name.clone()),
+                remote: false,
+            },
+        )
+        .await?;
 
-        Printer::success(Icon::Success, "Saved", "Pushed command to remote.");
+        // Settings such as the interpreter and confirmation come along with the command
+        if stored.is_some() {
+            models::command::Command::update(
+                ctx.get(),
+                UpdateCommandInput {
+                    name,
+                    cmd: command.cmd,
+                    options: Some(command.options),
+                },
+            )
+            .await?;
+        } else {
+            models::command::Command::create(
+                ctx.get(),
+                CreateCommandInput {
+                    name,
+                    cmd: command.cmd,
+                    project: find_project_config().ok().flatten(),
+                    options: command.options,
+                },
+            )
+            .await?;
+        }
+
+        Printer::success(Icon::Success, "Saved", "Pulled command from remote.");
 
         Ok(())
     }
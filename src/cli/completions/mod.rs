@@ -0,0 +1,352 @@
+use clap::CommandFactory;
+use clap_complete::{Shell as Generator, generate};
+use deeb::Query;
+
+use crate::{
+    cli::Cli,
+    flowlet_context::WithContext,
+    flowlet_db::models::{
+        Api,
+        command::Command,
+        project::Project,
+        run::{ListRunInput, Run},
+        task::Task,
+        variable::{ListVariableInput, Variable},
+        workflow::Workflow,
+    },
+    sync::Syncable,
+    util::FlowletResult,
+};
+
+/// A shell `flowlet completions` can generate a script for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Values completed from the local store, printed by `flowlet __complete <kind>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CompletionKind {
+    Commands,
+    Vars,
+    Projects,
+    Tasks,
+    Workflows,
+    Runs,
+}
+
+impl CompletionKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CompletionKind::Commands => "commands",
+            CompletionKind::Vars => "vars",
+            CompletionKind::Projects => "projects",
+            CompletionKind::Tasks => "tasks",
+            CompletionKind::Workflows => "workflows",
+            CompletionKind::Runs => "runs",
+        }
+    }
+}
+
+/// Subcommands whose first argument names a stored record, as `(group, subcommands, kind)`.
+const POSITIONALS: &[(&str, &[&str], CompletionKind)] = &[
+    (
+        "command",
+        &["run", "show", "rm", "edit", "push", "pull"],
+        CompletionKind::Commands,
+    ),
+    ("vars", &["set", "rm"], CompletionKind::Vars),
+    ("project", &["rm"], CompletionKind::Projects),
+    ("task", &["rm", "show", "edit"], CompletionKind::Tasks),
+    (
+        "workflow",
+        &["run", "show", "rm", "push", "pull"],
+        CompletionKind::Workflows,
+    ),
+    ("history", &["show", "rerun"], CompletionKind::Runs),
+];
+
+/// Options taking the name of a stored record.
+const OPTIONS: &[(&str, CompletionKind)] = &[
+    ("--project", CompletionKind::Projects),
+    ("--command", CompletionKind::Commands),
+];
+
+/// Marks the start of the completer the PowerShell generator registers.
+const POWERSHELL_REGISTER: &str =
+    "Register-ArgumentCompleter -Native -CommandName 'flowlet' -ScriptBlock";
+
+pub struct CompletionsCli;
+
+impl CompletionsCli {
+    /// Prints the completion script for `shell`: clap's static completions plus a wrapper
+    /// that asks `flowlet __complete` for saved names.
+    pub fn generate(shell: CompletionShell) -> FlowletResult<()> {
+        let generator = match shell {
+            CompletionShell::Bash => Generator::Bash,
+            CompletionShell::Zsh => Generator::Zsh,
+            CompletionShell::Fish => Generator::Fish,
+            CompletionShell::Powershell => Generator::PowerShell,
+        };
+
+        let mut script = Vec::new();
+        generate(generator, &mut Cli::command(), "flowlet", &mut script);
+        let script = String::from_utf8(script)?;
+
+        let script = match shell {
+            CompletionShell::Bash => format!("{}\n{}", script, bash_dynamic()),
+            CompletionShell::Zsh => format!("{}\n{}", script, zsh_dynamic()),
+            CompletionShell::Fish => format!("{}\n{}", script, fish_dynamic()),
+            CompletionShell::Powershell => powershell_dynamic(&script),
+        };
+
+        print!("{}", script);
+        Ok(())
+    }
+
+    /// Prints the values of `kind` one per line. Errors are swallowed so a broken store
+    /// never disturbs the shell.
+    pub async fn complete(ctx: &impl WithContext, kind: CompletionKind) -> FlowletResult<()> {
+        for value in Self::values(ctx, kind).await.unwrap_or_default() {
+            println!("{}", value);
+        }
+
+        Ok(())
+    }
+
+    async fn values(ctx: &impl WithContext, kind: CompletionKind) -> FlowletResult<Vec<String>> {
+        let ctx = ctx.get();
+
+        let mut values: Vec<String> = match kind {
+            CompletionKind::Commands => Command::find_all_local(ctx)
+                .await?
+                .into_iter()
+                .map(|c| c.name)
+                .collect(),
+            CompletionKind::Vars => Variable::list(ctx, ListVariableInput { query: Query::All })
+                .await?
+                .into_iter()
+                .map(|v| v.name)
+                .collect(),
+            CompletionKind::Projects => Project::find_all_local(ctx)
+                .await?
+                .into_iter()
+                .map(|p| p.name)
+                .collect(),
+            CompletionKind::Tasks => Task::find_all_local(ctx)
+                .await?
+                .into_iter()
+                .map(|t| t._id.to_string())
+                .collect(),
+            CompletionKind::Workflows => Workflow::find_all_local(ctx)
+                .await?
+                .into_iter()
+                .map(|w| w.name)
+                .collect(),
+            CompletionKind::Runs => Run::list(ctx, ListRunInput { query: Query::All })
+                .await?
+                .into_iter()
+                .map(|r| r._id.to_string())
+                .collect(),
+        };
+
+        // Runs are listed most recent first, everything else alphabetically
+        if kind != CompletionKind::Runs {
+            values.sort();
+        }
+        values.dedup();
+
+        Ok(values)
+    }
+}
+
+/// `case` arms matching `<group> <subcommand>` to a kind, for bash and zsh.
+fn positional_case_arms(indent: &str) -> String {
+    POSITIONALS
+        .iter()
+        .map(|(group, subcommands, kind)| {
+            let patterns = subcommands
+                .iter()
+                .map(|sub| format!("\"{} {}\"", group, sub))
+                .collect::<Vec<_>>()
+                .join("|");
+            format!("{}{}) kind={} ;;", indent, patterns, kind.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn option_case_arms(indent: &str) -> String {
+    OPTIONS
+        .iter()
+        .map(|(option, kind)| format!("{}{}) kind={} ;;", indent, option, kind.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn bash_dynamic() -> String {
+    format!(
+        r#"_flowlet_saved() {{
+    local IFS=$'\n'
+    COMPREPLY+=($(compgen -W "$(flowlet __complete "$1" 2>/dev/null)" -- "${{COMP_WORDS[COMP_CWORD]}}"))
+}}
+_flowlet_dynamic() {{
+    local kind=""
+    case "${{COMP_WORDS[COMP_CWORD-1]}}" in
+{options}
+    esac
+    if [[ -z $kind && $COMP_CWORD -eq 3 ]]; then
+        case "${{COMP_WORDS[1]}} ${{COMP_WORDS[2]}}" in
+{positionals}
+        esac
+    fi
+    if [[ -n $kind ]]; then
+        COMPREPLY=()
+        _flowlet_saved "$kind"
+        return 0
+    fi
+    _flowlet "$@"
+    # `flowlet <name>` runs a saved command
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        _flowlet_saved commands
+    fi
+}}
+complete -F _flowlet_dynamic -o bashdefault -o default flowlet
+"#,
+        options = option_case_arms("        "),
+        positionals = positional_case_arms("            "),
+    )
+}
+
+fn zsh_dynamic() -> String {
+    format!(
+        r#"_flowlet_dynamic() {{
+    local kind
+    case "${{words[CURRENT-1]}}" in
+{options}
+    esac
+    if [[ -z $kind && $CURRENT -eq 4 ]]; then
+        case "${{words[2]}} ${{words[3]}}" in
+{positionals}
+        esac
+    fi
+    local -a values
+    if [[ -n $kind ]]; then
+        values=(${{(f)"$(flowlet __complete $kind 2>/dev/null)"}})
+        compadd -a values
+        return
+    fi
+    # `flowlet <name>` runs a saved command
+    if [[ $CURRENT -eq 2 ]]; then
+        values=(${{(f)"$(flowlet __complete commands 2>/dev/null)"}})
+        compadd -a values
+    fi
+    _flowlet "$@"
+}}
+compdef _flowlet_dynamic flowlet
+"#,
+        options = option_case_arms("        "),
+        positionals = positional_case_arms("            "),
+    )
+}
+
+fn fish_dynamic() -> String {
+    let mut lines = vec![
+        "function __flowlet_at".to_string(),
+        "    set -l tokens (commandline -opc)".to_string(),
+        "    test (count $tokens) -eq 3; and test \"$tokens[2]\" = $argv[1]; and contains -- $tokens[3] $argv[2..-1]".to_string(),
+        "end".to_string(),
+        // `flowlet <name>` runs a saved command
+        "complete -c flowlet -n 'test (count (commandline -opc)) -eq 1' -f -a '(flowlet __complete commands)'".to_string(),
+    ];
+
+    for (group, subcommands, kind) in POSITIONALS {
+        lines.push(format!(
+            "complete -c flowlet -n '__flowlet_at {} {}' -f -a '(flowlet __complete {})'",
+            group,
+            subcommands.join(" "),
+            kind.as_str()
+        ));
+    }
+
+    for (option, kind) in OPTIONS {
+        lines.push(format!(
+            "complete -c flowlet -n 'test (commandline -opc)[-1] = {}' -f -a '(flowlet __complete {})'",
+            option,
+            kind.as_str()
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// PowerShell allows one completer per command, so the generated one is kept in a
+/// variable and called by the dynamic completer for everything it does not handle.
+fn powershell_dynamic(script: &str) -> String {
+    if !script.contains(POWERSHELL_REGISTER) {
+        log::warn!("Unexpected PowerShell completion script, saved names are not completed.");
+        return script.to_string();
+    }
+
+    let options = OPTIONS
+        .iter()
+        .map(|(option, kind)| format!("        '{}' {{ $kind = '{}' }}", option, kind.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let positionals = POSITIONALS
+        .iter()
+        .flat_map(|(group, subcommands, kind)| {
+            subcommands.iter().map(move |sub| {
+                format!(
+                    "            '{} {}' {{ $kind = '{}' }}",
+                    group,
+                    sub,
+                    kind.as_str()
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"{static_script}
+Register-ArgumentCompleter -Native -CommandName 'flowlet' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $tokens = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})
+    if ($wordToComplete) {{ $tokens = @($tokens | Select-Object -SkipLast 1) }}
+
+    $kind = $null
+    switch ($tokens[-1]) {{
+{options}
+    }}
+    if (-not $kind -and $tokens.Count -eq 3) {{
+        switch ("$($tokens[1]) $($tokens[2])") {{
+{positionals}
+        }}
+    }}
+
+    $complete = {{
+        param($kind)
+        flowlet __complete $kind 2>$null | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+        }}
+    }}
+
+    if ($kind) {{
+        & $complete $kind
+        return
+    }}
+
+    & $global:__flowletStatic $wordToComplete $commandAst $cursorPosition
+    # `flowlet <name>` runs a saved command
+    if ($tokens.Count -eq 1) {{ & $complete 'commands' }}
+}}
+"#,
+        static_script = script.replacen(POWERSHELL_REGISTER, "$global:__flowletStatic =", 1),
+    )
+}
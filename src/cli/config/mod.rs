@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::{
+    config::{CONFIG_KEYS, Config},
+    flowlet_context::WithContext,
+    printer::{Icon, Printer},
+    util::FlowletResult,
+};
+
+pub struct ConfigCli;
+
+/// A row of `flowlet config ls`.
+#[derive(Serialize)]
+struct ConfigEntry {
+    key: &'static str,
+    value: Option<String>,
+    source: &'static str,
+}
+
+impl ConfigCli {
+    /// Prints the effective value of a key, bare so it can be used in scripts.
+    pub async fn get(ctx: &impl WithContext, key: String) -> FlowletResult<()> {
+        let value = ctx
+            .get()
+            .config
+            .get(&key)?
+            .cloned()
+            .or_else(|| Config::default_value(&key).map(String::from));
+
+        if Printer::serialized(&value)? {
+            return Ok(());
+        }
+
+        match value {
+            Some(value) => println!("{}", value),
+            None => Printer::warning(Icon::Warning, "Unset", &format!("`{}` is not set.", key)),
+        }
+
+        Ok(())
+    }
+
+    pub async fn set(_ctx: &impl WithContext, key: String, value: String) -> FlowletResult<()> {
+        let mut config = Config::load()?;
+        config.set(&key, value.clone())?;
+        let path = config.save()?;
+
+        Printer::success(
+            Icon::Success,
+            "Saved",
+            &format!("{} = {} in `{}`", key, value, path.display()),
+        );
+
+        Ok(())
+    }
+
+    pub async fn list(ctx: &impl WithContext) -> FlowletResult<()> {
+        let effective = &ctx.get().config;
+        let file = Config::load()?;
+        let env = Config::from_env();
+
+        let mut entries: Vec<ConfigEntry> = Vec::new();
+
+        for key in CONFIG_KEYS {
+            let value = effective.get(key)?;
+
+            // Flags win over env, env over the file, so compare from the top down
+            let source = match value {
+                None => "default",
+                Some(value) if env.get(key)? == Some(value) => "env",
+                Some(value) if file.get(key)? == Some(value) => "file",
+                Some(_) => "flag",
+            };
+
+            let value = value
+                .cloned()
+                .or_else(|| Config::default_value(key).map(String::from));
+
+            entries.push(ConfigEntry { key, value, source });
+        }
+
+        if Printer::serialized(&entries)? {
+            return Ok(());
+        }
+
+        let rows: Vec<Vec<String>> = entries
+            .into_iter()
+            .map(|e| {
+                vec![
+                    e.key.to_string(),
+                    e.value.unwrap_or_else(|| "--".to_string()),
+                    e.source.to_string(),
+                ]
+            })
+            .collect();
+
+        Printer::info(
+            Icon::Info,
+            "Config File:",
+            &Config::path()?.display().to_string(),
+        );
+        Printer::table(vec!["Key", "Value", "Source"], rows);
+
+        Ok(())
+    }
+}
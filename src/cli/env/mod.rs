@@ -0,0 +1,54 @@
+use thiserror::Error;
+
+use crate::{
+    flowlet_context::WithContext,
+    printer::{Icon, Printer},
+    util::{FlowletResult, find_project_config, find_project_environment, set_project_environment},
+};
+
+#[derive(Debug, Error)]
+pub enum CliEnvError {
+    #[error("Environment name cannot be empty.")]
+    EmptyName,
+}
+
+pub struct EnvCli;
+
+impl EnvCli {
+    pub async fn show(_ctx: &impl WithContext) -> FlowletResult<()> {
+        let project = find_project_config().ok().flatten();
+        let environment = find_project_environment().ok().flatten();
+
+        match (project, environment) {
+            (Some(project), Some(environment)) => {
+                Printer::info(Icon::Project, "Project:", &project);
+                Printer::info(Icon::Info, "Environment:", &environment);
+            }
+            (Some(project), None) => {
+                Printer::info(Icon::Project, "Project:", &project);
+                Printer::warning(Icon::Warning, "Environment", "None selected.");
+            }
+            _ => Printer::warning(Icon::Warning, "Environment", "Not inside a project."),
+        }
+
+        Ok(())
+    }
+
+    pub async fn use_env(_ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let name = name.trim().to_string();
+
+        if name.is_empty() {
+            return Err(Box::new(CliEnvError::EmptyName));
+        }
+
+        let path = set_project_environment(&name)?;
+
+        Printer::success(
+            Icon::Success,
+            "Environment",
+            &format!("Switched to `{}` in `{}`.", name, path.display()),
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{Local, NaiveDate};
+use deeb::Query;
+use thiserror::Error;
+
+use crate::{
+    cli::{RunArgs, command::Command},
+    flowlet_context::WithContext,
+    flowlet_db::models::{
+        Api,
+        run::{ListRunInput, ReadRunInput, Run},
+    },
+    printer::{Icon, Printer},
+    secrets::MASK,
+    util::{FlowletResult, truncate_with_ellipsis},
+};
+
+#[derive(Debug, Error)]
+pub enum HistoryCliError {
+    #[error("No run found with _id `{0}`.")]
+    RunNotFound(String),
+
+    #[error(
+        "Run `{0}` had secret arguments, which are stored masked. Use `flowlet command run {1}` instead."
+    )]
+    MaskedArguments(String, String),
+}
+
+/// Outcome of a run, for filtering the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RunStatus {
+    /// Exited with code 0.
+    Success,
+
+    /// Exited with any other code.
+    Failed,
+}
+
+/// Filters for `history ls`.
+pub struct HistoryFilter {
+    pub command: Option<String>,
+    pub project: Option<String>,
+    pub status: Option<RunStatus>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub limit: usize,
+}
+
+impl HistoryFilter {
+    fn matches(&self, run: &Run) -> bool {
+        let date = run.started_at.with_timezone(&Local).date_naive();
+
+        self.project
+            .as_ref()
+            .is_none_or(|project| run.project.as_ref() == Some(project))
+            && self.status.is_none_or(|status| match status {
+                RunStatus::Success => run.success(),
+                RunStatus::Failed => !run.success(),
+            })
+            && self.since.is_none_or(|since| date >= since)
+            && self.until.is_none_or(|until| date <= until)
+    }
+}
+
+pub struct HistoryCli;
+
+impl HistoryCli {
+    pub async fn list(ctx: &impl WithContext, filter: HistoryFilter) -> FlowletResult<()> {
+        let query = match &filter.command {
+            Some(command) => Query::eq("command", command.clone()),
+            None => Query::All,
+        };
+
+        let runs: Vec<Run> = Run::list(ctx.get(), ListRunInput { query })
+            .await?
+            .into_iter()
+            .filter(|run| filter.matches(run))
+            .take(filter.limit)
+            .collect();
+
+        if Printer::serialized(&runs)? {
+            return Ok(());
+        }
+
+        if runs.is_empty() {
+            Printer::warning(Icon::Warning, "Empty", "No runs found.");
+            return Ok(());
+        }
+
+        let rows: Vec<Vec<String>> = runs
+            .iter()
+            .map(|run| {
+                vec![
+                    run._id.to_string(),
+                    run.command.clone(),
+                    status_label(run),
+                    run.started_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string(),
+                    format_duration(run.duration_ms),
+                    run.project.clone().unwrap_or_else(|| "--".to_string()),
+                    truncate_with_ellipsis(&run.cmd, 40),
+                ]
+            })
+            .collect();
+
+        Printer::success(Icon::Rocket, "History", "Recent runs:");
+        Printer::table(
+            vec![
+                "_id", "Command", "Status", "Started", "Duration", "Project", "Ran",
+            ],
+            rows,
+        );
+
+        Ok(())
+    }
+
+    pub async fn show(ctx: &impl WithContext, _id: String) -> FlowletResult<()> {
+        let run = Self::find(ctx, &_id).await?;
+
+        if Printer::serialized(&run)? {
+            return Ok(());
+        }
+
+        let fields = vec![
+            ("Command", run.command.clone()),
+            ("Ran", run.cmd.clone()),
+            (
+                "Arguments",
+                if run.args.is_empty() {
+                    "-".into()
+                } else {
+                    run.args.join(" ")
+                },
+            ),
+            ("Directory", run.cwd.clone()),
+            ("Project", run.project.clone().unwrap_or_else(|| "-".into())),
+            (
+                "Environment",
+                run.environment.clone().unwrap_or_else(|| "-".into()),
+            ),
+            (
+                "Started",
+                run.started_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            ),
+            ("Duration", format_duration(run.duration_ms)),
+            ("Status", status_label(&run)),
+        ];
+
+        Printer::block_kv("Run", &fields);
+
+        if !run.stdout.is_empty() {
+            Printer::multi_line_info("Stdout", run.stdout.lines().collect());
+        }
+
+        if !run.stderr.is_empty() {
+            Printer::multi_line_info("Stderr", run.stderr.lines().collect());
+        }
+
+        Ok(())
+    }
+
+    /// Runs the command of a past run again, with the same arguments and directory.
+    pub async fn rerun(ctx: &impl WithContext, _id: String) -> FlowletResult<()> {
+        let run = Self::find(ctx, &_id).await?;
+
+        // Rerunning with the mask in place of a secret would run the wrong command
+        if run.args.iter().any(|arg| arg.contains(MASK)) {
+            return Err(Box::new(HistoryCliError::MaskedArguments(_id, run.command)));
+        }
+
+        if std::env::set_current_dir(&run.cwd).is_ok() {
+            Printer::info(Icon::Project, "Directory:", &run.cwd);
+        } else {
+            Printer::warning(
+                Icon::Warning,
+                "Directory",
+                &format!("`{}` no longer exists, running here instead.", run.cwd),
+            );
+        }
+
+        Command::run(
+            ctx,
+            RunArgs {
+                name: run.command,
+                args: run.args,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn find(ctx: &impl WithContext, _id: &str) -> FlowletResult<Run> {
+        let run = Run::read(
+            ctx.get(),
+            ReadRunInput {
+                query: Query::eq("_id", _id.to_string()),
+            },
+        )
+        .await?;
+
+        run.ok_or_else(|| HistoryCliError::RunNotFound(_id.to_string()).into())
+    }
+}
+
+fn status_label(run: &Run) -> String {
+    let status = if run.success() {
+        "success".to_string()
+    } else if run.timed_out {
+        format!("timed out ({})", run.exit_code)
+    } else {
+        format!("failed ({})", run.exit_code)
+    };
+
+    if run.attempts > 1 {
+        format!("{} after {} attempts", status, run.attempts)
+    } else {
+        status
+    }
+}
+
+fn format_duration(ms: u64) -> String {
+    match ms {
+        0..1_000 => format!("{}ms", ms),
+        1_000..60_000 => format!("{:.1}s", ms as f64 / 1_000.0),
+        _ => format!("{}m {:02}s", ms / 60_000, (ms % 60_000) / 1_000),
+    }
+}
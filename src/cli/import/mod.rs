@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use dialoguer::{MultiSelect, Select};
+use thiserror::Error;
+
+use crate::{
+    discover::{Script, ScriptSource, dedupe_names, discover},
+    flowlet_context::WithContext,
+    flowlet_db::models::{
+        Api,
+        command::{Command, CommandOptions, CreateCommandInput, UpdateCommandInput},
+    },
+    printer::{Icon, Printer},
+    sync::Syncable,
+    util::{FlowletResult, find_project_config, find_project_root},
+};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("No Makefile, justfile, package.json, Cargo aliases or compose services found.")]
+    NothingFound,
+
+    #[error("Not attached to a terminal. Pass `--all` to import everything found.")]
+    NotInteractive,
+}
+
+/// What `flowlet import` does with scripts whose name is already saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Existing {
+    /// Keep the saved command
+    Skip,
+    /// Replace the saved command text and description
+    Update,
+}
+
+pub struct ImportCli;
+
+impl ImportCli {
+    /// Saves scripts found in the project root, or the current directory outside of a
+    /// project, as commands of the current project.
+    pub async fn import(
+        ctx: &impl WithContext,
+        from: Vec<ScriptSource>,
+        all: bool,
+        existing: Option<Existing>,
+    ) -> FlowletResult<()> {
+        let ctx = ctx.get();
+
+        let project = find_project_config().ok().flatten();
+        let root = find_project_root()?;
+
+        // Inside a project commands run from its root wherever they are started
+        let (dir, cwd) = match root {
+            Some(root) => (root, ".".to_string()),
+            None => {
+                let dir = std::env::current_dir()?;
+                let cwd = dir.to_string_lossy().to_string();
+                (dir, cwd)
+            }
+        };
+
+        let sources = if from.is_empty() {
+            ScriptSource::ALL.to_vec()
+        } else {
+            from
+        };
+
+        let mut scripts: Vec<Script> = Vec::new();
+        for (source, found) in discover(&dir, &sources) {
+            match found {
+                Ok(found) => scripts.extend(found),
+                Err(e) => Printer::warning(
+                    Icon::Warning,
+                    "Skipped",
+                    &format!("{}: {}", source.label(), e),
+                ),
+            }
+        }
+        dedupe_names(&mut scripts);
+
+        if scripts.is_empty() {
+            return Err(Box::new(ImportError::NothingFound));
+        }
+
+        let saved: HashMap<String, Command> = Command::find_all_local(ctx)
+            .await?
+            .into_iter()
+            .map(|c| (c.name.clone(), c))
+            .collect();
+
+        let interactive = std::io::stdin().is_terminal();
+
+        let selected: Vec<Script> = if all {
+            scripts
+        } else if !interactive {
+            return Err(Box::new(ImportError::NotInteractive));
+        } else {
+            let width = scripts.iter().map(|s| s.name.len()).max().unwrap_or(0);
+            let items: Vec<String> = scripts
+                .iter()
+                .map(|s| {
+                    let saved = if saved.contains_key(&s.name) {
+                        " · saved"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        "{:<width$}  {}  ({}{})",
+                        s.name,
+                        s.cmd,
+                        s.source.label(),
+                        saved,
+                        width = width
+                    )
+                })
+                .collect();
+            let defaults: Vec<bool> = scripts
+                .iter()
+                .map(|s| !saved.contains_key(&s.name))
+                .collect();
+
+            let picked = MultiSelect::new()
+                .with_prompt("Pick scripts to save as commands (space to toggle)")
+                .items(&items)
+                .defaults(&defaults)
+                .interact()?;
+
+            picked.into_iter().map(|i| scripts[i].clone()).collect()
+        };
+
+        if selected.is_empty() {
+            Printer::info(Icon::Info, "Import", "Nothing selected.");
+            return Ok(());
+        }
+
+        let conflicts = selected
+            .iter()
+            .filter(|s| saved.contains_key(&s.name))
+            .count();
+
+        let existing = match existing {
+            Some(existing) => existing,
+            None if conflicts > 0 && interactive && !all => {
+                let choice = Select::new()
+                    .with_prompt(format!(
+                        "{} of the selected names are already saved",
+                        conflicts
+                    ))
+                    .items(&["Skip them", "Update them"])
+                    .default(0)
+                    .interact()?;
+
+                if choice == 0 {
+                    Existing::Skip
+                } else {
+                    Existing::Update
+                }
+            }
+            None => Existing::Skip,
+        };
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut imported = 0;
+
+        for script in selected {
+            let action = match (saved.get(&script.name), existing) {
+                (Some(_), Existing::Skip) => "skipped",
+                (Some(command), Existing::Update) => {
+                    Command::update(
+                        ctx,
+                        UpdateCommandInput {
+                            name: script.name.clone(),
+                            cmd: script.cmd.clone(),
+                            options: Some(CommandOptions {
+                                description: script.description.clone(),
+                                cwd: Some(cwd.clone()),
+                                ..command.options.clone()
+                            }),
+                        },
+                    )
+                    .await?;
+                    imported += 1;
+                    "updated"
+                }
+                (None, _) => {
+                    Command::create(
+                        ctx,
+                        CreateCommandInput {
+                            name: script.name.clone(),
+                            cmd: script.cmd.clone(),
+                            project: project.clone(),
+                            options: CommandOptions {
+                                description: script.description.clone(),
+                                cwd: Some(cwd.clone()),
+                                ..CommandOptions::default()
+                            },
+                        },
+                    )
+                    .await?;
+                    imported += 1;
+                    "created"
+                }
+            };
+
+            rows.push(vec![
+                script.name,
+                script.cmd,
+                script.source.label().to_string(),
+                action.to_string(),
+            ]);
+        }
+
+        Printer::success(
+            Icon::Success,
+            "Import",
+            &format!(
+                "Saved {} command(s){}.",
+                imported,
+                project
+                    .as_deref()
+                    .map(|p| format!(" to project `{}`", p))
+                    .unwrap_or_default()
+            ),
+        );
+        Printer::table(vec!["Name", "Command", "Source", "Action"], rows);
+
+        Ok(())
+    }
+}
@@ -1,19 +1,252 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+
+use chrono::NaiveDate;
+
+use crate::{
+    cli::{
+        completions::{CompletionKind, CompletionShell},
+        history::RunStatus,
+        import::Existing,
+    },
+    config::Config as FlowletConfig,
+    discover::ScriptSource,
+    flowlet_db::models::command::{CommandMode, CommandOptions},
+    shell::Shell,
+    util::{MissingVariables, SortBy, Timespan},
+};
 
 pub mod auth;
 pub mod command;
+pub mod completions;
+pub mod config;
+pub mod env;
+pub mod history;
+pub mod import;
 pub mod project;
+pub mod sync;
 pub mod task;
 pub mod variable;
+pub mod workflow;
 
 #[derive(Parser)]
 #[command(name = "flowlet")]
 #[command(about = "🌊 The CLI for developer flow.")]
 pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
     #[command(subcommand)]
     pub root_commands: RootCommands,
 }
 
+/// Overrides for `~/.config/flowlet/config.toml` and `FLOWLET_*` variables.
+#[derive(Args, Default)]
+pub struct GlobalArgs {
+    /// Server to sync with
+    #[arg(long, global = true)]
+    pub server_url: Option<String>,
+
+    /// Local database file
+    #[arg(long, global = true)]
+    pub data_file: Option<String>,
+
+    /// Editor for `command edit`
+    #[arg(long, global = true)]
+    pub editor: Option<String>,
+
+    /// Shell used to run commands
+    #[arg(long, global = true)]
+    pub shell: Option<String>,
+
+    /// When to color output
+    #[arg(long, global = true, value_parser = ["auto", "always", "never"])]
+    pub color: Option<String>,
+
+    /// Format of list and show results. Status messages go to stderr unless `table`
+    #[arg(long, global = true, value_parser = ["table", "json", "yaml", "plain"])]
+    pub output: Option<String>,
+}
+
+/// Options shared by the `ls` subcommands.
+#[derive(Args, Clone, Copy)]
+pub struct ListArgs {
+    /// Order of the rows
+    #[arg(long, value_enum)]
+    pub sort: Option<SortBy>,
+
+    /// Also show when and by whom each record was changed
+    #[arg(long, short)]
+    pub long: bool,
+}
+
+/// How a saved command is run. Unset options keep their saved value.
+#[derive(Args, Default)]
+pub struct CommandSettings {
+    /// How the command is run. New commands starting with `#!` default to `script`
+    #[arg(long, value_enum)]
+    pub mode: Option<CommandMode>,
+
+    /// Program running the command, such as `bash`, `python3` or `node`. An empty value
+    /// goes back to the `shell` setting
+    #[arg(long)]
+    pub interpreter: Option<String>,
+
+    /// Directory to run in, relative to the project root. An empty value runs in the
+    /// current directory again
+    #[arg(long)]
+    pub cwd: Option<String>,
+
+    /// Set an environment variable, `KEY=VALUE`. Values may use `${var}` placeholders
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env)]
+    pub env: Vec<(String, String)>,
+
+    /// Remove a saved environment variable
+    #[arg(long, value_name = "KEY")]
+    pub unset_env: Vec<String>,
+
+    /// Pass variables to the command as environment variables instead of writing them
+    /// into the command text
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub export_vars: Option<bool>,
+
+    /// Show the resolved command and ask before every run, unless run with `--yes`
+    #[arg(long, visible_alias = "danger", num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub confirm: Option<bool>,
+
+    #[command(flatten)]
+    pub limits: RunLimits,
+}
+
+/// Timeout and retries, saved with a command or given for a single run.
+#[derive(Args, Default, Clone)]
+pub struct RunLimits {
+    /// Kill the command and everything it started after this long, such as `30s` or `5m`.
+    /// Timed commands cannot read from the terminal, only from piped input. `0` removes a
+    /// saved timeout
+    #[arg(long, value_name = "DURATION")]
+    pub timeout: Option<Timespan>,
+
+    /// Try a failed command again up to N times
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Wait before the first retry, doubled for each one after. Defaults to `1s`
+    #[arg(long, value_name = "DURATION")]
+    pub retry_delay: Option<Timespan>,
+
+    /// Only retry these exit codes, such as `7,28`. `any` retries every failure
+    #[arg(long, value_name = "CODES", value_parser = parse_exit_codes)]
+    pub retry_on_exit_codes: Option<ExitCodes>,
+}
+
+/// Exit codes given as a comma separated list, empty for any.
+#[derive(Clone, Debug)]
+pub struct ExitCodes(pub Vec<i32>);
+
+impl RunLimits {
+    pub fn is_empty(&self) -> bool {
+        self.timeout.is_none()
+            && self.retries.is_none()
+            && self.retry_delay.is_none()
+            && self.retry_on_exit_codes.is_none()
+    }
+
+    /// `options` with the limits given on the command line.
+    pub fn apply(self, options: CommandOptions) -> CommandOptions {
+        let timeout = match self.timeout {
+            Some(timeout) if timeout.0.is_zero() => None,
+            Some(timeout) => Some(timeout),
+            None => options.timeout,
+        };
+
+        CommandOptions {
+            timeout,
+            retries: self.retries.unwrap_or(options.retries),
+            retry_delay: self.retry_delay.or(options.retry_delay),
+            retry_on_exit_codes: self
+                .retry_on_exit_codes
+                .map_or(options.retry_on_exit_codes, |codes| codes.0),
+            ..options
+        }
+    }
+}
+
+fn parse_exit_codes(raw: &str) -> Result<ExitCodes, String> {
+    if raw.trim() == "any" {
+        return Ok(ExitCodes(Vec::new()));
+    }
+
+    raw.split(',')
+        .map(|code| {
+            code.trim()
+                .parse()
+                .map_err(|_| format!("expected exit codes such as `7,28`, got `{}`", raw))
+        })
+        .collect::<Result<_, _>>()
+        .map(ExitCodes)
+}
+
+impl CommandSettings {
+    pub fn is_empty(&self) -> bool {
+        self.mode.is_none()
+            && self.interpreter.is_none()
+            && self.cwd.is_none()
+            && self.env.is_empty()
+            && self.unset_env.is_empty()
+            && self.export_vars.is_none()
+            && self.confirm.is_none()
+            && self.limits.is_empty()
+    }
+
+    /// `options` with the settings given on the command line.
+    pub fn apply(self, options: CommandOptions) -> CommandOptions {
+        let cleared = |value: Option<String>, saved: Option<String>| match value {
+            Some(value) if value.trim().is_empty() => None,
+            Some(value) => Some(value),
+            None => saved,
+        };
+
+        let options = self.limits.apply(options);
+
+        let mut env = options.env;
+        for key in &self.unset_env {
+            env.remove(key);
+        }
+        env.extend(self.env);
+
+        CommandOptions {
+            mode: self.mode.unwrap_or(options.mode),
+            interpreter: cleared(self.interpreter, options.interpreter),
+            cwd: cleared(self.cwd, options.cwd),
+            env,
+            export_vars: self.export_vars.unwrap_or(options.export_vars),
+            confirm: self.confirm.unwrap_or(options.confirm),
+            ..options
+        }
+    }
+}
+
+fn parse_env(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected `KEY=VALUE`, got `{}`", raw)),
+    }
+}
+
+impl GlobalArgs {
+    pub fn into_config(self) -> FlowletConfig {
+        FlowletConfig {
+            server_url: self.server_url,
+            data_file: self.data_file,
+            editor: self.editor,
+            shell: self.shell,
+            color: self.color,
+            output: self.output,
+            history_limit: None,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum RootCommands {
     /// Manage saved commands
@@ -24,10 +257,67 @@ pub enum RootCommands {
     #[command(subcommand)]
     Vars(Vars),
 
+    /// Show or switch the project environment
+    #[command(subcommand)]
+    Env(Env),
+
     /// Register, login, and  logout.
     #[command(subcommand)]
     Auth(Auth),
 
+    /// Read and change global settings
+    #[command(subcommand)]
+    Config(Config),
+
+    /// Sync commands, projects, tasks and workflows with the server
+    Sync(SyncArgs),
+
+    /// Print a completion script for your shell
+    Completions {
+        #[arg(value_enum, id = "target_shell", value_name = "SHELL")]
+        shell: CompletionShell,
+    },
+
+    /// Print saved names for completion scripts
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[arg(value_enum)]
+        kind: CompletionKind,
+    },
+
+    /// Print a hook that records shell commands for `command save --last/--pick`
+    ShellInit {
+        /// Defaults to the shell in $SHELL
+        #[arg(value_enum, id = "target_shell", value_name = "SHELL")]
+        shell: Option<Shell>,
+    },
+
+    /// Browse and repeat past command runs
+    #[command(subcommand)]
+    History(History),
+
+    /// Save Makefile targets, package.json scripts and other project scripts as commands
+    Import {
+        /// Only look for these kinds of scripts
+        #[arg(long, value_enum, value_delimiter = ',')]
+        from: Vec<ScriptSource>,
+
+        /// Save everything found without asking
+        #[arg(long)]
+        all: bool,
+
+        /// What to do with names that are already saved
+        #[arg(long, value_enum)]
+        existing: Option<Existing>,
+    },
+
+    /// Browse, run and edit commands, variables, projects and tasks full screen
+    Tui {
+        /// Show commands, projects and tasks from the server
+        #[arg(long)]
+        remote: bool,
+    },
+
     /// Manage projects
     #[command(subcommand)]
     Project(Project),
@@ -36,6 +326,10 @@ pub enum RootCommands {
     #[command(subcommand)]
     Task(Task),
 
+    /// Manage multi-step workflows
+    #[command(subcommand)]
+    Workflow(Workflow),
+
     /// Catch-all for unknown commands
     #[command(external_subcommand)]
     Unknown(Vec<String>),
@@ -44,22 +338,24 @@ pub enum RootCommands {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Run a saved command
-    Run {
+    Run(RunArgs),
+    /// Save a command
+    Save {
         #[arg(required = true)]
         name: String,
+        #[arg(required_unless_present_any = ["last", "pick"], conflicts_with_all = ["last", "pick"])]
+        cmd: Option<String>, // handles multi-word shell command
 
-        #[arg(long)]
-        save_var: Option<String>,
+        /// Save the previous command from your shell history
+        #[arg(long, conflicts_with = "pick")]
+        last: bool,
 
+        /// Pick from recent commands in your shell history
         #[arg(long)]
-        json_path: Option<String>,
-    },
-    /// Save a command
-    Save {
-        #[arg(required = true)]
-        name: String,
-        #[arg(required = true)]
-        cmd: String, // handles multi-word shell command
+        pick: bool,
+
+        #[command(flatten)]
+        settings: CommandSettings,
     },
     // List commands
     Ls {
@@ -67,6 +363,8 @@ pub enum Commands {
         remote: bool,
         #[arg(long)]
         global: bool,
+        #[command(flatten)]
+        list: ListArgs,
     },
     Show {
         name: String,
@@ -85,16 +383,197 @@ pub enum Commands {
     },
 }
 
+#[derive(Args, Default)]
+pub struct RunArgs {
+    #[arg(required = true)]
+    pub name: String,
+
+    /// Save output to a variable: `name`, or `name=extractor` to use a different extractor
+    /// per variable (`json:<path>`, `regex:<pattern>`, `regex#<group>:<pattern>`, `line:<n>`)
+    #[arg(long)]
+    pub save_var: Vec<String>,
+
+    /// Path expression to extract from JSON output, e.g. `items[0].id` or `users[*].email`
+    #[arg(long, conflicts_with_all = ["regex", "line"])]
+    pub json_path: Option<String>,
+
+    /// Join multiple `--json-path` matches with this separator instead of saving a JSON array
+    #[arg(long)]
+    pub join: Option<String>,
+
+    /// Extract the first match of a regex, using the first capture group when there is one
+    #[arg(long, conflicts_with = "line")]
+    pub regex: Option<String>,
+
+    /// Capture group of `--regex` to save, by number or name
+    #[arg(long, requires = "regex")]
+    pub group: Option<String>,
+
+    /// Extract a single line (1-based, negative counts from the end)
+    #[arg(long, allow_negative_numbers = true)]
+    pub line: Option<i64>,
+
+    /// Trim surrounding whitespace from saved values
+    #[arg(long)]
+    pub trim: bool,
+
+    /// Extract from stderr instead of stdout
+    #[arg(long)]
+    pub from_stderr: bool,
+
+    /// Encrypt saved variables as secrets
+    #[arg(long)]
+    pub secret: bool,
+
+    /// What to do when a `${var}` placeholder cannot be resolved
+    #[arg(long, value_enum, default_value_t = MissingVariables::Prompt)]
+    pub on_missing: MissingVariables,
+
+    /// Pass variables as environment variables for this run, as if saved with `--export-vars`
+    #[arg(long)]
+    pub export_vars: bool,
+
+    #[command(flatten)]
+    pub limits: RunLimits,
+
+    /// Print the command with variables filled in and secrets masked, without running it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Run without asking, even when the command needs confirmation
+    #[arg(long, short)]
+    pub yes: bool,
+
+    /// Arguments for this run: `--arg name=value` fills `${name}`, the rest become `$1`, `$@`.
+    #[arg(last = true, allow_hyphen_values = true)]
+    pub args: Vec<String>,
+}
+
 #[derive(Subcommand)]
 pub enum Vars {
-    /// List all variables
-    Ls,
+    /// List the variables visible in the current project and environment
+    Ls {
+        /// Include variables scoped to other projects and environments
+        #[arg(long)]
+        all: bool,
+        #[command(flatten)]
+        list: ListArgs,
+    },
 
     /// Add a variable
-    Set { key: String, value: String },
+    Set {
+        key: String,
+
+        /// Prompted for without echo when omitted
+        value: Option<String>,
+
+        /// Encrypt the value at rest and mask it in output
+        #[arg(long)]
+        secret: bool,
+
+        /// Store an existing secret as plain text instead of keeping it encrypted
+        #[arg(long, conflicts_with_all = ["secret", "passphrase"])]
+        no_secret: bool,
+
+        /// Derive the encryption key from a passphrase instead of the key file
+        #[arg(long, requires = "secret")]
+        passphrase: bool,
+
+        /// Scope the variable to a project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Scope the variable to an environment of the project
+        #[arg(long)]
+        env: Option<String>,
+    },
 
     /// Remove a variable
-    Rm { key: String },
+    Rm {
+        key: String,
+
+        #[arg(long)]
+        project: Option<String>,
+
+        #[arg(long)]
+        env: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum Env {
+    /// Show the current project environment
+    Show,
+
+    /// Switch the environment recorded in `flowlet.toml`
+    Use { name: String },
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Print what would change without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(subcommand)]
+    pub command: Option<SyncCommands>,
+}
+
+#[derive(Subcommand)]
+pub enum SyncCommands {
+    /// Show queued changes
+    Status,
+
+    /// Drop changes the server rejected
+    Clear,
+}
+
+#[derive(Subcommand)]
+pub enum Config {
+    /// Print the effective value of a setting
+    Get { key: String },
+
+    /// Save a setting to the config file
+    Set { key: String, value: String },
+
+    /// List settings and where each value comes from
+    Ls,
+}
+
+#[derive(Subcommand)]
+pub enum History {
+    /// List past runs, most recent first
+    Ls {
+        /// Only runs of this command
+        #[arg(long)]
+        command: Option<String>,
+
+        /// Only runs inside this project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Only successful or failed runs
+        #[arg(long, value_enum)]
+        status: Option<RunStatus>,
+
+        /// Only runs started on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+
+        /// Only runs started on or before this date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+
+        /// Maximum number of runs to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Show a run with its output
+    Show { _id: String },
+
+    /// Run the command of a past run again
+    Rerun { _id: String },
 }
 
 #[derive(Subcommand)]
@@ -107,6 +586,12 @@ pub enum Auth {
 
     /// End session
     Logout,
+
+    /// Show the stored token and verify the session with the server
+    Status,
+
+    /// Print the user of the current session
+    Whoami,
 }
 
 #[derive(Subcommand)]
@@ -118,7 +603,13 @@ pub enum Project {
     Rm { name: String },
 
     /// List all projects
-    Ls,
+    Ls {
+        #[command(flatten)]
+        list: ListArgs,
+    },
+
+    /// Import the commands, variables and workflows declared in flowlet.toml
+    Apply,
 }
 
 #[derive(Subcommand)]
@@ -135,6 +626,8 @@ pub enum Task {
         remote: bool,
         #[arg(long)]
         global: bool,
+        #[command(flatten)]
+        list: ListArgs,
     },
 
     /// View the details of a task
@@ -143,3 +636,48 @@ pub enum Task {
     /// Edit the details of a task
     Edit { _id: String },
 }
+
+#[derive(Subcommand)]
+pub enum Workflow {
+    /// Create or redefine a workflow interactively.
+    New { name: String },
+
+    /// Run every step of a workflow in order
+    Run {
+        name: String,
+
+        /// What to do when a `${var}` placeholder cannot be resolved
+        #[arg(long, value_enum, default_value_t = MissingVariables::Prompt)]
+        on_missing: MissingVariables,
+
+        /// Run steps without asking, even when they need confirmation
+        #[arg(long, short)]
+        yes: bool,
+
+        /// Arguments passed to every step, as with `command run`.
+        #[arg(last = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// List workflows
+    Ls {
+        #[arg(long)]
+        remote: bool,
+        #[arg(long)]
+        global: bool,
+        #[command(flatten)]
+        list: ListArgs,
+    },
+
+    /// View the steps of a workflow
+    Show { name: String },
+
+    /// Remove a workflow by name
+    Rm { name: String },
+
+    /// Push a workflow to the remote
+    Push { name: String },
+
+    /// Pull a workflow from the remote
+    Pull { name: String },
+}
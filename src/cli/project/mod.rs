@@ -1,3 +1,4 @@
+use deeb::Query;
 use dialoguer::{Confirm, Input, Select};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -5,13 +6,21 @@ use std::path::PathBuf;
 use thiserror::Error;
 
 use crate::{
+    cli::ListArgs,
     flowlet_context::WithContext,
+    flowlet_db::metadata::Metadata,
     flowlet_db::models::{
         self, Api,
-        project::{CreateProjectInput, ListProjectInput, Project, RemoveProjectInput},
+        command::{Command, CreateCommandInput, ReadCommandInput, UpdateCommandInput},
+        project::{
+            CreateProjectInput, ListProjectInput, Project, ReadProjectInput, RemoveProjectInput,
+        },
+        variable::{CreateVariableInput, Variable},
+        workflow::{CreateWorkflowInput, ReadWorkflowInput, UpdateWorkflowInput, Workflow},
     },
     printer::{Icon, Printer},
-    util::FlowletResult,
+    project_file::ProjectFile,
+    util::{FlowletResult, UtilError, sort_records},
 };
 
 #[derive(Debug, Error)]
@@ -125,8 +134,180 @@ impl ProjectCli {
         Ok(())
     }
 
-    pub async fn list(ctx: &impl WithContext) -> FlowletResult<()> {
-        let projects = models::project::Project::list(
+    /// Imports everything declared in `flowlet.toml` into the local store, creating the
+    /// project when it does not exist yet.
+    ///
+    /// Declared commands and workflows replace stored ones with the same name in the
+    /// project. Ones whose name is taken outside the project are skipped. Declared
+    /// variables are only defaults, so saved project values are kept.
+    pub async fn apply(ctx: &impl WithContext) -> FlowletResult<()> {
+        let ctx = ctx.get();
+
+        let file = ProjectFile::load()?.ok_or(UtilError::ProjectConfigNotFound)?;
+        let project = file
+            .project_name()
+            .ok_or(UtilError::ProjectConfigNotFound)?;
+
+        // Check every workflow before anything is written
+        let workflows = file
+            .workflows
+            .iter()
+            .map(|(name, spec)| Ok((name, spec, spec.steps(name)?)))
+            .collect::<FlowletResult<Vec<_>>>()?;
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut row = |kind: &str, name: &str, action: &str| {
+            rows.push(vec![kind.to_string(), name.to_string(), action.to_string()]);
+        };
+
+        let existing = Project::read(
+            ctx,
+            ReadProjectInput {
+                query: Query::eq("name", project.clone()),
+                remote: false,
+            },
+        )
+        .await?;
+
+        if existing.is_none() {
+            Project::create(
+                ctx,
+                CreateProjectInput {
+                    name: project.clone(),
+                    description: file.project.as_ref().and_then(|p| p.description.clone()),
+                },
+            )
+            .await?;
+            row("project", &project, "created");
+        }
+
+        for (name, spec) in &file.commands {
+            let stored = Command::read(
+                ctx,
+                ReadCommandInput {
+                    query: Query::eq("name", name.clone()),
+                    remote: false,
+                },
+            )
+            .await?;
+
+            let action = match stored {
+                Some(stored) if stored.project.as_ref() != Some(&project) => {
+                    warn_clash("Command", name, stored.project.as_deref());
+                    "skipped"
+                }
+                Some(stored) if spec.matches(&stored) => "unchanged",
+                Some(_) => {
+                    Command::update(
+                        ctx,
+                        UpdateCommandInput {
+                            name: name.clone(),
+                            cmd: spec.cmd.clone(),
+                            options: Some(spec.options.clone()),
+                        },
+                    )
+                    .await?;
+                    "updated"
+                }
+                None => {
+                    Command::create(
+                        ctx,
+                        CreateCommandInput {
+                            name: name.clone(),
+                            cmd: spec.cmd.clone(),
+                            project: Some(project.clone()),
+                            options: spec.options.clone(),
+                        },
+                    )
+                    .await?;
+                    "created"
+                }
+            };
+            row("command", name, action);
+        }
+
+        for (name, value) in &file.vars {
+            let stored = Variable::find_scoped(ctx, name, Some(&project), None).await?;
+
+            let action = if stored.is_some() {
+                "kept"
+            } else {
+                Variable::create(
+                    ctx,
+                    CreateVariableInput {
+                        name: name.clone(),
+                        value: value.clone(),
+                        project: Some(project.clone()),
+                        environment: None,
+                        secret: false,
+                    },
+                )
+                .await?;
+                "created"
+            };
+            row("variable", name, action);
+        }
+
+        for (name, spec, steps) in workflows {
+            let stored = Workflow::read(
+                ctx,
+                ReadWorkflowInput {
+                    query: Query::eq("name", name.clone()),
+                    remote: false,
+                },
+            )
+            .await?;
+
+            let action = match stored {
+                Some(stored) if stored.project.as_ref() != Some(&project) => {
+                    warn_clash("Workflow", name, stored.project.as_deref());
+                    "skipped"
+                }
+                Some(stored) if stored.description == spec.description && stored.steps == steps => {
+                    "unchanged"
+                }
+                Some(_) => {
+                    Workflow::update(
+                        ctx,
+                        UpdateWorkflowInput {
+                            name: name.clone(),
+                            description: spec.description.clone(),
+                            project: Some(project.clone()),
+                            steps,
+                        },
+                    )
+                    .await?;
+                    "updated"
+                }
+                None => {
+                    Workflow::create(
+                        ctx,
+                        CreateWorkflowInput {
+                            name: name.clone(),
+                            description: spec.description.clone(),
+                            project: Some(project.clone()),
+                            steps,
+                        },
+                    )
+                    .await?;
+                    "created"
+                }
+            };
+            row("workflow", name, action);
+        }
+
+        Printer::success(
+            Icon::Project,
+            "Project",
+            &format!("Applied `flowlet.toml` to project `{}`.", project),
+        );
+        Printer::table(vec!["Type", "Name", "Action"], rows);
+
+        Ok(())
+    }
+
+    pub async fn list(ctx: &impl WithContext, list: ListArgs) -> FlowletResult<()> {
+        let mut projects = models::project::Project::list(
             ctx.get(),
             ListProjectInput {
                 query: deeb::Query::All,
@@ -135,19 +316,54 @@ impl ProjectCli {
         )
         .await?;
 
+        if let Some(sort) = list.sort {
+            sort_records(&mut projects, sort, |p| &p.name);
+        }
+
+        if Printer::serialized(&projects)? {
+            return Ok(());
+        }
+
         let rows: Vec<Vec<String>> = projects
             .iter()
             .map(|p| {
-                vec![
+                let mut row = vec![
                     p.name.clone(),
                     p.description.clone().unwrap_or_else(|| "-".to_string()),
-                ]
+                ];
+                if list.long {
+                    row.extend(p.metadata.columns());
+                }
+                row
             })
             .collect();
 
+        let mut headers = vec!["Name", "Description"];
+        if list.long {
+            headers.extend(Metadata::HEADERS);
+        }
+
         Printer::success(Icon::Project, "Projects", "Found your projects!");
-        Printer::table(vec!["Name", "Description"], rows);
+        Printer::table(headers, rows);
 
         Ok(())
     }
 }
+
+/// Warns that a declared command or workflow was not applied because its name is taken
+/// by one outside the project.
+fn warn_clash(kind: &str, name: &str, owner: Option<&str>) {
+    let owner = match owner {
+        Some(owner) => format!("project `{}`", owner),
+        None => "no project".to_string(),
+    };
+
+    Printer::warning(
+        Icon::Warning,
+        "Name Clash",
+        &format!(
+            "{} `{}` belongs to {}, it was not updated.",
+            kind, name, owner
+        ),
+    );
+}
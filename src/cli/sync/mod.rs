@@ -0,0 +1,291 @@
+use deeb::Query;
+use dialoguer::Select;
+use thiserror::Error;
+
+use crate::{
+    flowlet_context::WithContext,
+    flowlet_db::models::{
+        Api,
+        command::Command,
+        outbox::{FlushReport, ListOutboxInput, Outbox, RemoveOutboxInput},
+        project::Project,
+        task::Task,
+        workflow::Workflow,
+    },
+    printer::{Icon, Printer},
+    sync::{self, Action, PlanItem, Resolution, Syncable},
+    util::{FlowletResult, launch_editor, truncate_with_ellipsis},
+};
+
+#[derive(Debug, Error)]
+pub enum SyncCliError {
+    #[error("{0} queued change(s) could not be sent. Sync again once the server is reachable.")]
+    PendingChanges(usize),
+}
+
+pub struct SyncCli;
+
+impl SyncCli {
+    /// Sends queued changes, then reconciles every synced model with the server.
+    pub async fn run(ctx: &impl WithContext, dry_run: bool) -> FlowletResult<()> {
+        if dry_run {
+            let pending = Outbox::pending(ctx.get()).await?;
+            if !pending.is_empty() {
+                Printer::info(
+                    Icon::Cloud,
+                    "Queued:",
+                    &format!("{} change(s) would be sent first.", pending.len()),
+                );
+            }
+        } else {
+            // Queued writes go first so the comparison sees them on the server
+            let report = Outbox::flush(ctx.get(), true).await?;
+            Self::print_report(&report);
+
+            if report.remaining > 0 {
+                return Err(Box::new(SyncCliError::PendingChanges(report.remaining)));
+            }
+        }
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+
+        Self::sync_entity::<Project>(ctx, dry_run, &mut rows).await?;
+        Self::sync_entity::<Command>(ctx, dry_run, &mut rows).await?;
+        Self::sync_entity::<Workflow>(ctx, dry_run, &mut rows).await?;
+        Self::sync_entity::<Task>(ctx, dry_run, &mut rows).await?;
+
+        if rows.is_empty() {
+            Printer::success(Icon::Cloud, "Sync", "Everything is up to date.");
+            return Ok(());
+        }
+
+        let label = if dry_run { "Plan" } else { "Synced" };
+        Printer::success(Icon::Cloud, label, &format!("{} change(s).", rows.len()));
+        Printer::table(
+            vec!["Type", "Name", "Action", "Local Rev", "Remote Rev"],
+            rows,
+        );
+
+        Ok(())
+    }
+
+    async fn sync_entity<T: Syncable>(
+        ctx: &impl WithContext,
+        dry_run: bool,
+        rows: &mut Vec<Vec<String>>,
+    ) -> FlowletResult<()> {
+        let plan = sync::plan::<T>(ctx.get()).await?;
+
+        for item in plan {
+            if item.action == Action::Unchanged && item.synced.is_some() {
+                continue;
+            }
+
+            let mut action = item.action.to_string();
+
+            if !dry_run {
+                if item.action == Action::Conflict {
+                    match Self::prompt_resolution(ctx, &item)? {
+                        Some(resolution) => {
+                            action = format!("{} ({})", action, resolution_label(&resolution));
+                            sync::resolve(ctx.get(), &item, resolution).await?;
+                        }
+                        None => action = format!("{} (skipped)", action),
+                    }
+                } else {
+                    sync::apply(ctx.get(), &item).await?;
+                }
+            }
+
+            // Recording a first sync of identical records is not worth reporting
+            if item.action == Action::Unchanged {
+                continue;
+            }
+
+            let revision = |rev: Option<u64>| rev.map_or("--".to_string(), |r| r.to_string());
+            rows.push(vec![
+                T::ENTITY.to_string(),
+                item.key.clone(),
+                action,
+                revision(item.local_revision()),
+                revision(item.remote_revision()),
+            ]);
+        }
+
+        Ok(())
+    }
+
+    /// Shows both versions of a conflicting record and asks which one wins. `None` skips
+    /// the record for this sync.
+    fn prompt_resolution<T: Syncable>(
+        ctx: &impl WithContext,
+        item: &PlanItem<T>,
+    ) -> FlowletResult<Option<Resolution<T>>> {
+        let show = |record: &Option<T>| -> FlowletResult<String> {
+            match record {
+                Some(record) => sync::to_editable(record),
+                None => Ok("(deleted)\n".to_string()),
+            }
+        };
+
+        Printer::warning(
+            Icon::Warning,
+            "Conflict",
+            &format!("{} `{}` changed on both sides.", T::ENTITY, item.key),
+        );
+        Printer::diff("remote", "local", &show(&item.remote)?, &show(&item.local)?);
+
+        let mut options = vec!["Keep local", "Keep remote"];
+        if item.local.is_some() && item.remote.is_some() {
+            options.push("Open in editor");
+        }
+        options.push("Skip");
+
+        loop {
+            let choice = Select::new()
+                .with_prompt("Resolve conflict")
+                .items(&options)
+                .default(0)
+                .interact()?;
+
+            match options[choice] {
+                "Keep local" => return Ok(Some(Resolution::KeepLocal)),
+                "Keep remote" => return Ok(Some(Resolution::KeepRemote)),
+                "Skip" => return Ok(None),
+                _ => {}
+            }
+
+            // Only offered when both sides exist
+            let Some(local) = &item.local else {
+                continue;
+            };
+
+            let text = launch_editor(&sync::to_editable(local)?, ctx.get().config.editor())?;
+            match sync::from_editable(&text, local) {
+                Ok(merged) => return Ok(Some(Resolution::Merged(merged))),
+                Err(e) => Printer::error(Icon::Error, "Invalid", &e.to_string()),
+            }
+        }
+    }
+
+    /// Sends queued changes that are due, on startup of any other command.
+    pub async fn flush_pending(ctx: &impl WithContext) {
+        if !ctx.get().api_client.is_authenticated() {
+            return;
+        }
+
+        match Outbox::flush(ctx.get(), false).await {
+            Ok(report) if report.sent > 0 || report.failed > 0 => Self::print_report(&report),
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to flush outbox: {:?}", e),
+        }
+    }
+
+    pub async fn status(ctx: &impl WithContext) -> FlowletResult<()> {
+        let mut entries = Outbox::list(ctx.get(), ListOutboxInput { query: Query::All }).await?;
+        entries.sort_by_key(|entry| entry._id);
+
+        if Printer::serialized(&entries)? {
+            return Ok(());
+        }
+
+        if entries.is_empty() {
+            Printer::success(Icon::Cloud, "Sync", "Everything is synced.");
+            return Ok(());
+        }
+
+        let rows = entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.label.clone(),
+                    entry.path.clone(),
+                    entry.status().to_string(),
+                    entry.attempts.to_string(),
+                    entry
+                        .next_attempt_at
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string(),
+                    truncate_with_ellipsis(entry.last_error.as_deref().unwrap_or("--"), 40),
+                ]
+            })
+            .collect();
+
+        Printer::info(
+            Icon::Cloud,
+            "Queued:",
+            &format!("{} change(s) waiting for the server.", entries.len()),
+        );
+        Printer::table(
+            vec![
+                "Target",
+                "Operation",
+                "Status",
+                "Attempts",
+                "Next Attempt",
+                "Error",
+            ],
+            rows,
+        );
+        Ok(())
+    }
+
+    /// Drops changes the server rejected.
+    pub async fn clear(ctx: &impl WithContext) -> FlowletResult<()> {
+        let entries = Outbox::list(ctx.get(), ListOutboxInput { query: Query::All }).await?;
+
+        let mut cleared = 0;
+        for entry in entries.iter().filter(|entry| entry.failed) {
+            Outbox::remove(
+                ctx.get(),
+                RemoveOutboxInput {
+                    query: Query::eq("_id", entry._id.to_string()),
+                },
+            )
+            .await?;
+            cleared += 1;
+        }
+
+        Printer::success(
+            Icon::Trash,
+            "Sync",
+            &format!("Dropped {} rejected change(s).", cleared),
+        );
+        Ok(())
+    }
+
+    fn print_report(report: &FlushReport) {
+        if report.sent > 0 {
+            Printer::success(
+                Icon::Cloud,
+                "Synced",
+                &format!("Sent {} queued change(s).", report.sent),
+            );
+        }
+        if report.failed > 0 {
+            Printer::warning(
+                Icon::Warning,
+                "Rejected",
+                &format!(
+                    "{} change(s) were rejected by the server. See `flowlet sync status`.",
+                    report.failed
+                ),
+            );
+        }
+        if report.remaining > 0 {
+            Printer::warning(
+                Icon::Cloud,
+                "Pending",
+                &format!("{} change(s) still queued.", report.remaining),
+            );
+        }
+    }
+}
+
+fn resolution_label<T>(resolution: &Resolution<T>) -> &'static str {
+    match resolution {
+        Resolution::KeepLocal => "kept local",
+        Resolution::KeepRemote => "kept remote",
+        Resolution::Merged(_) => "merged",
+    }
+}
@@ -4,7 +4,9 @@ use std::str::FromStr;
 use thiserror::Error;
 
 use crate::{
+    cli::ListArgs,
     flowlet_context::WithContext,
+    flowlet_db::metadata::Metadata,
     flowlet_db::models::{
         self, Api,
         task::{
@@ -13,7 +15,10 @@ use crate::{
         },
     },
     printer::{Icon, Printer},
-    util::{FlowletResult, find_project_config, request_date_input, truncate_with_ellipsis},
+    util::{
+        FlowletResult, find_project_config, request_date_input, sort_records,
+        truncate_with_ellipsis,
+    },
 };
 
 #[derive(Debug, Error)]
@@ -268,7 +273,12 @@ impl TaskCli {
         Ok(())
     }
 
-    pub async fn list(ctx: &impl WithContext, remote: bool, global: bool) -> FlowletResult<()> {
+    pub async fn list(
+        ctx: &impl WithContext,
+        remote: bool,
+        global: bool,
+        list: ListArgs,
+    ) -> FlowletResult<()> {
         let project = find_project_config().ok().flatten();
 
         let mut query = Query::All;
@@ -280,12 +290,21 @@ impl TaskCli {
             }
         }
 
-        let tasks = models::task::Task::list(ctx.get(), ListTaskInput { query, remote }).await?;
+        let mut tasks =
+            models::task::Task::list(ctx.get(), ListTaskInput { query, remote }).await?;
+
+        if let Some(sort) = list.sort {
+            sort_records(&mut tasks, sort, |t| &t.title);
+        }
+
+        if Printer::serialized(&tasks)? {
+            return Ok(());
+        }
 
         let rows: Vec<Vec<String>> = tasks
             .iter()
             .map(|t| {
-                vec![
+                let mut row = vec![
                     t._id.to_string(),
                     truncate_with_ellipsis(&t.title, 40),
                     t.status.to_string(),
@@ -294,15 +313,21 @@ impl TaskCli {
                         .map(|d| d.format("%Y-%m-%d").to_string())
                         .unwrap_or_else(|| "-".to_string()),
                     t.milestones.len().to_string(),
-                ]
+                ];
+                if list.long {
+                    row.extend(t.metadata.columns());
+                }
+                row
             })
             .collect();
 
+        let mut headers = vec!["_id", "Title", "Status", "Project", "Due", "Milestones"];
+        if list.long {
+            headers.extend(Metadata::HEADERS);
+        }
+
         Printer::success(Icon::Project, "Tasks", "Found your tasks!");
-        Printer::table(
-            vec!["_id", "Title", "Status", "Project", "Due", "Milestones"],
-            rows,
-        );
+        Printer::table(headers, rows);
 
         Ok(())
     }
@@ -329,6 +354,10 @@ impl TaskCli {
             }
         };
 
+        if Printer::serialized(&task)? {
+            return Ok(());
+        }
+
         Printer::info(Icon::Task, "Task Details", &task.title);
 
         let fields = vec![
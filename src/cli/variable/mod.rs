@@ -1,15 +1,22 @@
 use deeb::Query;
-use dialoguer::Confirm;
+use dialoguer::{Confirm, Password};
+use serde::Serialize;
+use std::cmp::Reverse;
 use thiserror::Error;
 
 use crate::{
+    cli::ListArgs,
     flowlet_context::WithContext,
+    flowlet_db::metadata::{Metadata, Tracked},
     flowlet_db::models::{
         self, Api,
-        variable::{CreateVariableInput, ListVariableInput, RemoveVariableInput},
+        variable::{
+            CreateVariableInput, ListVariableInput, RemoveVariableInput, UpdateVariableInput,
+        },
     },
     printer::{Icon, Printer},
-    util::FlowletResult,
+    secrets::{KeySource, encrypt, needs_passphrase},
+    util::{FlowletResult, SortBy, find_project_config, find_project_environment},
 };
 
 #[derive(Debug, Error)]
@@ -19,41 +26,198 @@ pub enum CliVariableError {
 
     #[error("Variable creation failed.")]
     CreateFailed,
+
+    #[error(
+        "Environment scoped variables need a project. Pass `--project` or run inside a project."
+    )]
+    ProjectRequired,
 }
 
 pub struct Variable;
 
+/// A row of `flowlet vars ls`, with secret values masked.
+#[derive(Serialize)]
+struct VariableListing<'a> {
+    #[serde(flatten)]
+    variable: &'a models::variable::Variable,
+    scope: String,
+    status: &'a str,
+}
+
 impl Variable {
-    pub async fn list(ctx: &impl WithContext) -> FlowletResult<()> {
-        let variables =
+    pub async fn list(ctx: &impl WithContext, all: bool, list: ListArgs) -> FlowletResult<()> {
+        let project = find_project_config().ok().flatten();
+        let environment = find_project_environment().ok().flatten();
+
+        let mut variables =
             models::variable::Variable::list(ctx.get(), ListVariableInput { query: Query::All })
                 .await?;
 
-        if variables.is_empty() {
+        let applies = |var: &models::variable::Variable| {
+            var.applies_to(project.as_deref(), environment.as_deref())
+        };
+
+        if !all {
+            variables.retain(applies);
+        }
+
+        // Most specific first so the winning scope of each name comes first
+        variables.sort_by(|a, b| a.name.cmp(&b.name).then(b.scope().cmp(&a.scope())));
+
+        let mut resolved: Vec<String> = Vec::new();
+        let mut listed: Vec<(models::variable::Variable, &str)> = Vec::new();
+
+        for mut var in variables {
+            var.value = var.display_value();
+
+            let status = if !applies(&var) {
+                "inactive"
+            } else if resolved.contains(&var.name) {
+                "shadowed"
+            } else {
+                resolved.push(var.name.clone());
+                "active"
+            };
+
+            listed.push((var, status));
+        }
+
+        // Statuses depend on the scope order above, so other orders apply afterwards
+        match list.sort {
+            Some(SortBy::Created) => listed.sort_by_key(|(v, _)| Reverse(v.metadata().created_at)),
+            Some(SortBy::Updated) => listed.sort_by_key(|(v, _)| Reverse(v.metadata().updated_at)),
+            Some(SortBy::Revision) => listed.sort_by_key(|(v, _)| Reverse(v.metadata().revision)),
+            Some(SortBy::Name) | None => {}
+        }
+
+        let listings: Vec<VariableListing> = listed
+            .iter()
+            .map(|(variable, status)| VariableListing {
+                variable,
+                scope: variable.scope().to_string(),
+                status,
+            })
+            .collect();
+
+        if Printer::serialized(&listings)? {
+            return Ok(());
+        }
+
+        if listed.is_empty() {
             Printer::warning(Icon::Warning, "Empty", "No variables found.");
             return Ok(());
         }
 
-        let rows: Vec<Vec<String>> = variables
+        if let Some(project) = &project {
+            Printer::info(
+                Icon::Project,
+                "Project Selected:",
+                &format!(
+                    "{} ({})",
+                    project,
+                    environment.as_deref().unwrap_or("no environment")
+                ),
+            );
+        }
+
+        let rows: Vec<Vec<String>> = listed
             .into_iter()
-            .map(|var| vec![var.name, var.value])
+            .map(|(var, status)| {
+                let mut row = vec![
+                    var.name.clone(),
+                    var.value.clone(),
+                    var.scope().to_string(),
+                    var.project.clone().unwrap_or_else(|| "--".to_string()),
+                    var.environment.clone().unwrap_or_else(|| "--".to_string()),
+                    status.to_string(),
+                ];
+                if list.long {
+                    row.extend(var.metadata.columns());
+                }
+                row
+            })
             .collect();
 
+        let mut headers = vec!["Name", "Value", "Scope", "Project", "Env", "Status"];
+        if list.long {
+            headers.extend(Metadata::HEADERS);
+        }
+
         Printer::success(Icon::Success, "Variables", "List of stored variables:");
-        Printer::table(vec!["Name", "Value"], rows);
+        Printer::table(headers, rows);
         Ok(())
     }
 
-    pub async fn add(ctx: &impl WithContext, name: String, value: String) -> FlowletResult<()> {
-        models::variable::Variable::create(
+    pub async fn add(
+        ctx: &impl WithContext,
+        name: String,
+        value: Option<String>,
+        secret: Option<bool>,
+        passphrase: bool,
+        project: Option<String>,
+        environment: Option<String>,
+    ) -> FlowletResult<()> {
+        let (project, environment) = Self::scope(project, environment)?;
+
+        // Prompt without echo so secrets stay out of shell history
+        let value = match value {
+            Some(value) => value,
+            None => Password::new()
+                .with_prompt(format!("Value for `{}`", name))
+                .allow_empty_password(true)
+                .interact()?,
+        };
+
+        let existing = models::variable::Variable::find_scoped(
             ctx.get(),
-            CreateVariableInput {
-                name: name.clone(),
-                value,
-            },
+            &name,
+            project.as_deref(),
+            environment.as_deref(),
         )
         .await?;
 
+        // Without an explicit choice an existing secret stays encrypted, with the same key
+        let existing_secret = existing.as_ref().filter(|v| v.secret && secret.is_none());
+        let secret = secret.unwrap_or(false) || existing_secret.is_some();
+        let passphrase = passphrase || existing_secret.is_some_and(|v| needs_passphrase(&v.value));
+
+        let value = if secret {
+            let source = if passphrase {
+                KeySource::Passphrase
+            } else {
+                KeySource::KeyFile
+            };
+            encrypt(&value, source)?
+        } else {
+            value
+        };
+
+        if existing.is_some() {
+            models::variable::Variable::update(
+                ctx.get(),
+                UpdateVariableInput {
+                    name: name.clone(),
+                    value,
+                    project,
+                    environment,
+                    secret,
+                },
+            )
+            .await?;
+        } else {
+            models::variable::Variable::create(
+                ctx.get(),
+                CreateVariableInput {
+                    name: name.clone(),
+                    value,
+                    project,
+                    environment,
+                    secret,
+                },
+            )
+            .await?;
+        }
+
         Printer::success(
             Icon::Success,
             "Saved",
@@ -62,7 +226,14 @@ impl Variable {
         Ok(())
     }
 
-    pub async fn remove(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+    pub async fn remove(
+        ctx: &impl WithContext,
+        name: String,
+        project: Option<String>,
+        environment: Option<String>,
+    ) -> FlowletResult<()> {
+        let (project, environment) = Self::scope(project, environment)?;
+
         let confirm = Confirm::new()
             .with_prompt(format!("Are you sure you want to delete '{}'? [y/N]", name))
             .default(false)
@@ -74,8 +245,15 @@ impl Variable {
             return Ok(());
         }
 
-        models::variable::Variable::remove(ctx.get(), RemoveVariableInput { name: name.clone() })
-            .await?;
+        models::variable::Variable::remove(
+            ctx.get(),
+            RemoveVariableInput {
+                name: name.clone(),
+                project,
+                environment,
+            },
+        )
+        .await?;
 
         Printer::success(
             Icon::Trash,
@@ -84,4 +262,19 @@ impl Variable {
         );
         Ok(())
     }
+
+    /// An environment scope implies a project; default it to the current one.
+    fn scope(
+        project: Option<String>,
+        environment: Option<String>,
+    ) -> FlowletResult<(Option<String>, Option<String>)> {
+        if environment.is_none() || project.is_some() {
+            return Ok((project, environment));
+        }
+
+        match find_project_config().ok().flatten() {
+            Some(current) => Ok((Some(current), environment)),
+            None => Err(Box::new(CliVariableError::ProjectRequired)),
+        }
+    }
 }
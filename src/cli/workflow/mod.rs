@@ -0,0 +1,525 @@
+use deeb::Query;
+use dialoguer::{Confirm, Input, Select};
+use thiserror::Error;
+
+use crate::{
+    cli::{
+        ListArgs,
+        command::{CliCommandError, Command},
+    },
+    extract::{Extraction, Extractor, SaveVar, Source},
+    flowlet_context::WithContext,
+    flowlet_db::metadata::Metadata,
+    flowlet_db::models::{
+        Api,
+        command::{CommandOptions, ListCommandInput},
+        workflow::{
+            CreateWorkflowInput, ListWorkflowInput, ReadWorkflowInput, RemoveWorkflowInput,
+            StepRun, UpdateWorkflowInput, Workflow, WorkflowStep,
+        },
+    },
+    printer::{Icon, Printer},
+    project_file::ProjectFile,
+    util::{CommandArgs, FlowletResult, MissingVariables, find_project_config, sort_records},
+};
+
+#[derive(Debug, Error)]
+pub enum CliWorkflowError {
+    #[error("Workflow not found.")]
+    NotFound,
+
+    #[error("Workflow has no steps.")]
+    NoSteps,
+
+    #[error("Step `{0}` references unknown command `{1}`.")]
+    StepCommandNotFound(String, String),
+}
+
+pub struct WorkflowCli;
+
+impl WorkflowCli {
+    pub async fn new(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let description: String = Input::<String>::new()
+            .with_prompt("Enter a description (optional)")
+            .allow_empty(true)
+            .interact_text()?
+            .trim()
+            .to_owned();
+
+        let description = if description.is_empty() {
+            None
+        } else {
+            Some(description)
+        };
+
+        let project = find_project_config().ok().flatten();
+
+        // Offer saved commands as step targets
+        let commands = crate::flowlet_db::models::command::Command::list(
+            ctx.get(),
+            ListCommandInput {
+                query: Query::All,
+                remote: false,
+            },
+        )
+        .await
+        .unwrap_or_default();
+        let command_names: Vec<String> = commands.into_iter().map(|c| c.name).collect();
+
+        let mut steps = Vec::new();
+        loop {
+            let add_step = Confirm::new()
+                .with_prompt(format!("Add step #{}?", steps.len() + 1))
+                .default(true)
+                .interact()?;
+
+            if !add_step {
+                break;
+            }
+
+            let kinds = vec!["Saved command", "Inline shell"];
+            let kind = Select::new()
+                .with_prompt("Step type")
+                .items(&kinds)
+                .default(0)
+                .interact()?;
+
+            let run = if kind == 0 && !command_names.is_empty() {
+                let selected = Select::new()
+                    .with_prompt("Command")
+                    .items(&command_names)
+                    .default(0)
+                    .interact()?;
+                StepRun::Command(command_names[selected].clone())
+            } else if kind == 0 {
+                let name: String = Input::new().with_prompt("Command name").interact_text()?;
+                StepRun::Command(name)
+            } else {
+                let cmd: String = Input::new().with_prompt("Shell command").interact_text()?;
+                StepRun::Shell(cmd)
+            };
+
+            let default_name = match &run {
+                StepRun::Command(name) => name.clone(),
+                StepRun::Shell(_) => format!("step-{}", steps.len() + 1),
+            };
+
+            let step_name: String = Input::new()
+                .with_prompt("Step name")
+                .default(default_name)
+                .interact_text()?;
+
+            let save_var: String = Input::new()
+                .with_prompt("Save output to variable (optional)")
+                .allow_empty(true)
+                .interact_text()?;
+            let save_var = Some(save_var.trim().to_owned()).filter(|s| !s.is_empty());
+
+            let json_path = if save_var.is_some() {
+                let path: String = Input::new()
+                    .with_prompt("JSON path to extract (optional)")
+                    .allow_empty(true)
+                    .interact_text()?;
+                Some(path.trim().to_owned()).filter(|s| !s.is_empty())
+            } else {
+                None
+            };
+
+            let continue_on_error = Confirm::new()
+                .with_prompt("Continue the workflow if this step fails?")
+                .default(false)
+                .interact()?;
+
+            steps.push(WorkflowStep {
+                name: step_name,
+                run,
+                save_var,
+                json_path,
+                continue_on_error,
+            });
+        }
+
+        if steps.is_empty() {
+            return Err(Box::new(CliWorkflowError::NoSteps));
+        }
+
+        let existing = Workflow::find_in_project(ctx.get(), &name, project.as_deref()).await?;
+
+        if existing.is_some() {
+            Workflow::update(
+                ctx.get(),
+                UpdateWorkflowInput {
+                    name: name.clone(),
+                    description,
+                    project,
+                    steps,
+                },
+            )
+            .await?;
+        } else {
+            Workflow::create(
+                ctx.get(),
+                CreateWorkflowInput {
+                    name: name.clone(),
+                    description,
+                    project,
+                    steps,
+                },
+            )
+            .await?;
+        }
+
+        Printer::success(
+            Icon::Workflow,
+            "Workflow",
+            &format!("Saved workflow. Run with `flowlet workflow run {}`.", name),
+        );
+        Ok(())
+    }
+
+    pub async fn run(
+        ctx: &impl WithContext,
+        name: String,
+        on_missing: MissingVariables,
+        yes: bool,
+        args: Vec<String>,
+    ) -> FlowletResult<()> {
+        let args = CommandArgs::parse(&args)?;
+        let workflow = Self::find(ctx, &name, false).await?;
+
+        if workflow.steps.is_empty() {
+            return Err(Box::new(CliWorkflowError::NoSteps));
+        }
+
+        Printer::info(Icon::Workflow, "Workflow:", &workflow.name);
+
+        let total = workflow.steps.len();
+        let mut results: Vec<Vec<String>> = Vec::new();
+        let mut failure: Option<i32> = None;
+
+        for (i, step) in workflow.steps.iter().enumerate() {
+            Printer::info(
+                Icon::Rocket,
+                &format!("Step {}/{}:", i + 1, total),
+                &step.name,
+            );
+
+            let (cmd, options) = match &step.run {
+                StepRun::Shell(cmd) => (cmd.clone(), CommandOptions::default()),
+                StepRun::Command(command_name) => match Command::find(ctx, command_name).await {
+                    Ok(c) => (c.cmd, c.options),
+                    Err(e)
+                        if matches!(
+                            e.downcast_ref::<CliCommandError>(),
+                            Some(CliCommandError::CommandNotFound)
+                        ) =>
+                    {
+                        return Err(Box::new(CliWorkflowError::StepCommandNotFound(
+                            step.name.clone(),
+                            command_name.clone(),
+                        )));
+                    }
+                    Err(e) => return Err(e),
+                },
+            };
+
+            let output = Command::execute(ctx, &step.name, &cmd, &options, &args, on_missing, yes)
+                .await?
+                .output;
+
+            if output.success() {
+                Printer::success(Icon::Success, "Step Passed", &step.name);
+                results.push(vec![
+                    step.name.clone(),
+                    "passed".to_string(),
+                    "0".to_string(),
+                ]);
+
+                if let Some(var_name) = &step.save_var {
+                    let extraction = match &step.json_path {
+                        Some(path) => Extraction::JsonPath {
+                            path: path.clone(),
+                            join: None,
+                        },
+                        None => Extraction::Raw,
+                    };
+
+                    let save_var = SaveVar {
+                        name: var_name.clone(),
+                        extractor: Extractor {
+                            source: Source::Stdout,
+                            extraction,
+                            trim: false,
+                        },
+                    };
+
+                    Command::save_output(ctx, &save_var, &output, false).await?;
+                }
+
+                continue;
+            }
+
+            results.push(vec![
+                step.name.clone(),
+                "failed".to_string(),
+                output.exit_code.to_string(),
+            ]);
+
+            if step.continue_on_error {
+                Printer::warning(
+                    Icon::Warning,
+                    "Step Failed",
+                    &format!("{} (exit {}), continuing.", step.name, output.exit_code),
+                );
+                continue;
+            }
+
+            Printer::error(
+                Icon::Failure,
+                "Step Failed",
+                &format!("{} (exit {})", step.name, output.exit_code),
+            );
+            failure = Some(output.exit_code);
+            break;
+        }
+
+        // Mark the steps that never ran
+        for step in workflow.steps.iter().skip(results.len()) {
+            results.push(vec![
+                step.name.clone(),
+                "skipped".to_string(),
+                "--".to_string(),
+            ]);
+        }
+
+        Printer::table(vec!["Step", "Status", "Exit Code"], results);
+
+        if let Some(code) = failure {
+            return Err(Box::new(CliCommandError::CommandExitedWithError(code)));
+        }
+
+        Printer::success(Icon::Success, "Workflow", "All steps completed.");
+        Ok(())
+    }
+
+    pub async fn list(
+        ctx: &impl WithContext,
+        remote: bool,
+        global: bool,
+        list: ListArgs,
+    ) -> FlowletResult<()> {
+        let project = find_project_config().ok().flatten();
+
+        let mut query = Query::All;
+
+        if let Some(project) = project
+            && !global
+        {
+            Printer::info(Icon::Project, "Project Selected:", project.as_str());
+            query = Query::eq("project", project);
+        }
+
+        let mut workflows = Workflow::list(ctx.get(), ListWorkflowInput { query, remote }).await?;
+
+        if let Some(sort) = list.sort {
+            sort_records(&mut workflows, sort, |w| &w.name);
+        }
+
+        if Printer::serialized(&workflows)? {
+            return Ok(());
+        }
+
+        let rows: Vec<Vec<String>> = workflows
+            .into_iter()
+            .map(|w| {
+                let mut row = vec![
+                    w.name,
+                    w.description.unwrap_or_else(|| "--".to_string()),
+                    w.steps.len().to_string(),
+                    w.project.unwrap_or_else(|| "--".to_string()),
+                ];
+                if list.long {
+                    row.extend(w.metadata.columns());
+                }
+                row
+            })
+            .collect();
+
+        let mut headers = vec!["Name", "Description", "Steps", "Project"];
+        if list.long {
+            headers.extend(Metadata::HEADERS);
+        }
+
+        Printer::success(Icon::Workflow, "Workflows", "Found your workflows!");
+        Printer::table(headers, rows);
+        Ok(())
+    }
+
+    pub async fn show(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let workflow = Self::find(ctx, &name, false).await?;
+
+        if Printer::serialized(&workflow)? {
+            return Ok(());
+        }
+
+        Printer::info(Icon::Workflow, "Show Workflow", &workflow.name);
+
+        let fields = vec![
+            ("Name", workflow.name.clone()),
+            (
+                "Description",
+                workflow.description.clone().unwrap_or_else(|| "-".into()),
+            ),
+            (
+                "Project",
+                workflow.project.clone().unwrap_or_else(|| "-".into()),
+            ),
+            ("Steps", workflow.steps.len().to_string()),
+        ];
+
+        Printer::block_kv("Workflow", &fields);
+
+        let rows: Vec<Vec<String>> = workflow
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                vec![
+                    (i + 1).to_string(),
+                    step.name.clone(),
+                    step.run.to_string(),
+                    step.save_var.clone().unwrap_or_else(|| "--".to_string()),
+                    step.json_path.clone().unwrap_or_else(|| "--".to_string()),
+                    if step.continue_on_error {
+                        "continue"
+                    } else {
+                        "stop"
+                    }
+                    .to_string(),
+                ]
+            })
+            .collect();
+
+        Printer::table(
+            vec!["#", "Step", "Runs", "Save Var", "JSON Path", "On Error"],
+            rows,
+        );
+
+        Ok(())
+    }
+
+    pub async fn remove(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let confirm = Confirm::new()
+            .with_prompt(format!(
+                "Are you sure you want to delete the workflow `{}`?",
+                name
+            ))
+            .default(false)
+            .interact()?;
+
+        if !confirm {
+            Printer::info(Icon::Warning, "Aborted", "Workflow deletion cancelled.");
+            return Ok(());
+        }
+
+        Workflow::remove(ctx.get(), RemoveWorkflowInput { name: name.clone() }).await?;
+
+        Printer::success(
+            Icon::Trash,
+            "Trashed",
+            &format!("Workflow Removed: `{}`", name),
+        );
+        Ok(())
+    }
+
+    pub async fn push(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let workflow = Self::find(ctx, &name, false).await?;
+
+        let remote = Workflow::read(
+            ctx.get(),
+            ReadWorkflowInput {
+                query: Query::eq("name", name.clone()),
+                remote: true,
+            },
+        )
+        .await?;
+
+        if remote.is_some() {
+            Workflow::update(
+                ctx.get(),
+                UpdateWorkflowInput {
+                    name,
+                    description: workflow.description,
+                    project: workflow.project,
+                    steps: workflow.steps,
+                },
+            )
+            .await?;
+        } else {
+            ctx.get()
+                .api_client
+                .post::<_, Workflow>("/insert-one/workflow", &workflow)
+                .await?;
+        }
+
+        Printer::success(Icon::Cloud, "Pushed", "Pushed workflow to remote.");
+        Ok(())
+    }
+
+    pub async fn pull(ctx: &impl WithContext, name: String) -> FlowletResult<()> {
+        let workflow = Self::find(ctx, &name, true).await?;
+
+        let local =
+            Workflow::find_in_project(ctx.get(), &name, workflow.project.as_deref()).await?;
+
+        if local.is_some() {
+            Workflow::update(
+                ctx.get(),
+                UpdateWorkflowInput {
+                    name,
+                    description: workflow.description,
+                    project: workflow.project,
+                    steps: workflow.steps,
+                },
+            )
+            .await?;
+        } else {
+            Workflow::create(
+                ctx.get(),
+                CreateWorkflowInput {
+                    name,
+                    description: workflow.description,
+                    project: workflow.project,
+                    steps: workflow.steps,
+                },
+            )
+            .await?;
+        }
+
+        Printer::success(Icon::Cloud, "Pulled", "Pulled workflow from remote.");
+        Ok(())
+    }
+
+    async fn find(ctx: &impl WithContext, name: &str, remote: bool) -> FlowletResult<Workflow> {
+        let workflow = Workflow::read(
+            ctx.get(),
+            ReadWorkflowInput {
+                query: Query::eq("name", name.to_string()),
+                remote,
+            },
+        )
+        .await?;
+
+        if let Some(workflow) = workflow {
+            return Ok(workflow);
+        }
+
+        // Workflows declared in `flowlet.toml` run without being applied first
+        let declared = match ProjectFile::load()? {
+            Some(file) if !remote => file.workflow(name, ctx.get().author())?,
+            _ => None,
+        };
+
+        declared.ok_or_else(|| Box::new(CliWorkflowError::NotFound) as Box<dyn std::error::Error>)
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    printer::{OutputFormat, Printer},
+    util::{FlowletResult, UtilError, config_dir},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_SERVER_URL: &str = "http://localhost:8080";
+const DEFAULT_DATA_FILE: &str = "~/.flowlet.json";
+const DEFAULT_SHELL: &str = "sh";
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Every key accepted by `flowlet config`, in display order.
+pub const CONFIG_KEYS: [&str; 7] = [
+    "server_url",
+    "data_file",
+    "editor",
+    "shell",
+    "color",
+    "output",
+    "history_limit",
+];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unknown config key `{0}`. Expected one of: {keys}.", keys = CONFIG_KEYS.join(", "))]
+    UnknownKey(String),
+
+    #[error("Invalid value `{1}` for `{0}`. {2}")]
+    InvalidValue(String, String, &'static str),
+
+    #[error("Failed to parse config file `{0}`.")]
+    ParseFailed(String),
+}
+
+/// Settings from `~/.config/flowlet/config.toml`.
+///
+/// Every field is optional so a config can also describe a single layer, such as the
+/// values set through `FLOWLET_*` variables or global flags. Layers are combined with
+/// [`Config::merge`] and defaults are applied by the accessors.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_file: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+
+    /// `auto`, `always` or `never`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// `table`, `json`, `yaml` or `plain`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// Runs kept in the history per command, `0` keeps all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_limit: Option<String>,
+}
+
+impl Config {
+    pub fn path() -> FlowletResult<PathBuf> {
+        Ok(config_dir()?.join(CONFIG_FILE))
+    }
+
+    /// Reads the config file, or an empty config when it does not exist yet.
+    pub fn load() -> FlowletResult<Self> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        let config = toml::from_str(&contents).map_err(|e| {
+            log::error!("{:?}", e);
+            ConfigError::ParseFailed(path.display().to_string())
+        })?;
+
+        Ok(config)
+    }
+
+    pub fn save(&self) -> FlowletResult<PathBuf> {
+        let path = Self::path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)?;
+
+        Ok(path)
+    }
+
+    /// Values set through `FLOWLET_SERVER_URL`, `FLOWLET_DATA_FILE` and so on.
+    pub fn from_env() -> Self {
+        let var = |key: &str| {
+            std::env::var(format!("FLOWLET_{}", key.to_uppercase()))
+                .ok()
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            server_url: var("server_url"),
+            data_file: var("data_file"),
+            editor: var("editor"),
+            shell: var("shell"),
+            color: var("color"),
+            output: var("output"),
+            history_limit: var("history_limit"),
+        }
+    }
+
+    /// Layers `other` on top of `self`, keeping values `other` does not set.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            server_url: other.server_url.or(self.server_url),
+            data_file: other.data_file.or(self.data_file),
+            editor: other.editor.or(self.editor),
+            shell: other.shell.or(self.shell),
+            color: other.color.or(self.color),
+            output: other.output.or(self.output),
+            history_limit: other.history_limit.or(self.history_limit),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> FlowletResult<Option<&String>> {
+        let value = match key {
+            "server_url" => self.server_url.as_ref(),
+            "data_file" => self.data_file.as_ref(),
+            "editor" => self.editor.as_ref(),
+            "shell" => self.shell.as_ref(),
+            "color" => self.color.as_ref(),
+            "output" => self.output.as_ref(),
+            "history_limit" => self.history_limit.as_ref(),
+            _ => return Err(Box::new(ConfigError::UnknownKey(key.to_string()))),
+        };
+
+        Ok(value)
+    }
+
+    pub fn set(&mut self, key: &str, value: String) -> FlowletResult<()> {
+        Self::validate(key, &value)?;
+
+        let field = match key {
+            "server_url" => &mut self.server_url,
+            "data_file" => &mut self.data_file,
+            "editor" => &mut self.editor,
+            "shell" => &mut self.shell,
+            "color" => &mut self.color,
+            "output" => &mut self.output,
+            "history_limit" => &mut self.history_limit,
+            _ => return Err(Box::new(ConfigError::UnknownKey(key.to_string()))),
+        };
+
+        *field = Some(value);
+        Ok(())
+    }
+
+    /// Checks that every value of this layer is valid for its key.
+    pub fn validated(self) -> FlowletResult<Self> {
+        for key in CONFIG_KEYS {
+            if let Some(value) = self.get(key)? {
+                Self::validate(key, value)?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    fn validate(key: &str, value: &str) -> FlowletResult<()> {
+        let invalid = |hint| {
+            Err(Box::new(ConfigError::InvalidValue(
+                key.to_string(),
+                value.to_string(),
+                hint,
+            ))
+            .into())
+        };
+
+        match key {
+            "server_url" if url::Url::parse(value).is_err() => {
+                invalid("Expected a URL such as `https://flowlet.example.com`.")
+            }
+            "color" if !matches!(value, "auto" | "always" | "never") => {
+                invalid("Expected `auto`, `always` or `never`.")
+            }
+            "output" if OutputFormat::parse(value).is_none() => {
+                invalid("Expected `table`, `json`, `yaml` or `plain`.")
+            }
+            "history_limit" if value.parse::<usize>().is_err() => {
+                invalid("Expected a number of runs, such as `100`.")
+            }
+            "data_file" | "editor" | "shell" if value.trim().is_empty() => {
+                invalid("Value cannot be empty.")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn server_url(&self) -> &str {
+        self.server_url.as_deref().unwrap_or(DEFAULT_SERVER_URL)
+    }
+
+    /// The local database file, with a leading `~` expanded to the home directory.
+    pub fn data_file(&self) -> FlowletResult<PathBuf> {
+        let path = self.data_file.as_deref().unwrap_or(DEFAULT_DATA_FILE);
+
+        match path.strip_prefix("~/") {
+            Some(rest) => {
+                let home = dirs::home_dir().ok_or(UtilError::HomeDirAccessDenied)?;
+                Ok(home.join(rest))
+            }
+            None => Ok(PathBuf::from(path)),
+        }
+    }
+
+    /// The configured editor. `None` falls back to `$EDITOR`.
+    pub fn editor(&self) -> Option<&str> {
+        self.editor.as_deref()
+    }
+
+    pub fn shell(&self) -> &str {
+        self.shell.as_deref().unwrap_or(DEFAULT_SHELL)
+    }
+
+    /// Runs kept per command, `None` when every run is kept.
+    pub fn history_limit(&self) -> Option<usize> {
+        let limit = self
+            .history_limit
+            .as_deref()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+        Some(limit).filter(|limit| *limit > 0)
+    }
+
+    /// Forces colored output on or off unless the mode is `auto`.
+    pub fn apply_color(&self) {
+        match self.color.as_deref() {
+            Some("always") => colored::control::set_override(true),
+            Some("never") => colored::control::set_override(false),
+            _ => {}
+        }
+    }
+
+    /// Sets how results are written to stdout.
+    pub fn apply_output(&self) {
+        let format = self.output.as_deref().and_then(OutputFormat::parse);
+        Printer::set_format(format.unwrap_or_default());
+    }
+
+    /// The value used when a key is not set anywhere.
+    pub fn default_value(key: &str) -> Option<&'static str> {
+        match key {
+            "server_url" => Some(DEFAULT_SERVER_URL),
+            "data_file" => Some(DEFAULT_DATA_FILE),
+            "shell" => Some(DEFAULT_SHELL),
+            "color" => Some("auto"),
+            "output" => Some("table"),
+            "history_limit" => Some("100"),
+            _ => None,
+        }
+    }
+}
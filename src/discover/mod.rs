@@ -0,0 +1,456 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::util::FlowletResult;
+
+#[derive(Debug, Error)]
+pub enum DiscoverError {
+    #[error("`{0}` is not valid JSON.")]
+    InvalidJson(String),
+
+    #[error("`{0}` is not valid TOML.")]
+    InvalidToml(String),
+
+    #[error("`{0}` is not valid YAML.")]
+    InvalidYaml(String),
+}
+
+/// A kind of file listing runnable scripts, which `flowlet import` can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScriptSource {
+    /// Makefile targets
+    Make,
+    /// justfile recipes
+    Just,
+    /// package.json scripts
+    Npm,
+    /// Aliases in .cargo/config.toml
+    Cargo,
+    /// docker compose services
+    Compose,
+}
+
+/// A script found in a project, ready to be saved as a command.
+#[derive(Debug, Clone)]
+pub struct Script {
+    pub name: String,
+    pub cmd: String,
+    pub description: Option<String>,
+    pub source: ScriptSource,
+}
+
+impl ScriptSource {
+    /// Sources in the order their names are preferred when two share one.
+    pub const ALL: [ScriptSource; 5] = [
+        ScriptSource::Make,
+        ScriptSource::Just,
+        ScriptSource::Npm,
+        ScriptSource::Cargo,
+        ScriptSource::Compose,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScriptSource::Make => "Makefile",
+            ScriptSource::Just => "justfile",
+            ScriptSource::Npm => "package.json",
+            ScriptSource::Cargo => ".cargo/config.toml",
+            ScriptSource::Compose => "docker compose",
+        }
+    }
+
+    /// Prefix for names taken by an earlier source.
+    fn prefix(&self) -> &'static str {
+        match self {
+            ScriptSource::Make => "make",
+            ScriptSource::Just => "just",
+            ScriptSource::Npm => "npm",
+            ScriptSource::Cargo => "cargo",
+            ScriptSource::Compose => "compose",
+        }
+    }
+
+    fn files(&self) -> &'static [&'static str] {
+        match self {
+            ScriptSource::Make => &["GNUmakefile", "makefile", "Makefile"],
+            ScriptSource::Just => &["justfile", "Justfile", ".justfile"],
+            ScriptSource::Npm => &["package.json"],
+            ScriptSource::Cargo => &[".cargo/config.toml", ".cargo/config"],
+            ScriptSource::Compose => &[
+                "compose.yaml",
+                "compose.yml",
+                "docker-compose.yaml",
+                "docker-compose.yml",
+            ],
+        }
+    }
+
+    /// Scripts listed in `dir`, or none when it has no file of this kind.
+    pub fn discover(&self, dir: &Path) -> FlowletResult<Vec<Script>> {
+        let Some(path) = self
+            .files()
+            .iter()
+            .map(|f| dir.join(f))
+            .find(|p| p.is_file())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let contents = fs::read_to_string(&path)?;
+        let file = path.display().to_string();
+
+        let found = match self {
+            ScriptSource::Make => parse_makefile(&contents),
+            ScriptSource::Just => parse_justfile(&contents),
+            ScriptSource::Npm => parse_package_json(&contents, &file, &npm_runner(dir))?,
+            ScriptSource::Cargo => parse_cargo_aliases(&contents, &file)?,
+            ScriptSource::Compose => parse_compose(&contents, &file)?,
+        };
+
+        Ok(found
+            .into_iter()
+            .map(|(name, cmd, description)| Script {
+                name,
+                cmd,
+                description,
+                source: *self,
+            })
+            .collect())
+    }
+}
+
+/// Scripts found in `dir` for each of `sources`, in the order of [`ScriptSource::ALL`].
+pub fn discover(
+    dir: &Path,
+    sources: &[ScriptSource],
+) -> Vec<(ScriptSource, FlowletResult<Vec<Script>>)> {
+    ScriptSource::ALL
+        .into_iter()
+        .filter(|source| sources.contains(source))
+        .map(|source| (source, source.discover(dir)))
+        .collect()
+}
+
+/// Keeps names unique by prefixing the later of two scripts sharing a name with its
+/// source, such as `npm-build`.
+pub fn dedupe_names(scripts: &mut [Script]) {
+    let mut taken: Vec<String> = Vec::new();
+
+    for script in scripts.iter_mut() {
+        if taken.contains(&script.name) {
+            script.name = format!("{}-{}", script.source.prefix(), script.name);
+        }
+        taken.push(script.name.clone());
+    }
+}
+
+type Found = (String, String, Option<String>);
+
+/// Explicit targets, described by a trailing `## text` or a comment on the line above.
+/// Pattern rules, special targets and variable assignments are skipped.
+fn parse_makefile(contents: &str) -> Vec<Found> {
+    let target = Regex::new(
+        r"^([A-Za-z0-9_][A-Za-z0-9_.-]*(?:[ \t]+[A-Za-z0-9_][A-Za-z0-9_.-]*)*)[ \t]*::?(?:[^:=]|$)(.*)$",
+    )
+    .unwrap();
+
+    let mut found: Vec<Found> = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut in_define = false;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+
+        if in_define {
+            in_define = trimmed != "endef";
+            continue;
+        }
+
+        if trimmed.starts_with("define ") {
+            in_define = true;
+            continue;
+        }
+
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+
+        let Some(caps) = target.captures(line) else {
+            comment = None;
+            continue;
+        };
+
+        let description = caps[2]
+            .split_once("##")
+            .map(|(_, text)| text.trim().to_string())
+            .or(comment.take())
+            .filter(|text| !text.is_empty());
+
+        for name in caps[1].split_whitespace() {
+            if found.iter().any(|(n, _, _)| n == name) {
+                continue;
+            }
+            found.push((
+                name.to_string(),
+                format!("make {}", name),
+                description.clone(),
+            ));
+        }
+    }
+
+    found
+}
+
+/// Public recipes, with parameters turned into placeholders so they can be passed as
+/// `--arg` when running.
+fn parse_justfile(contents: &str) -> Vec<Found> {
+    let recipe =
+        Regex::new(r"^@?([A-Za-z_][A-Za-z0-9_-]*)((?:[ \t]+[^:]*?)?)[ \t]*:(?:[^=]|$)").unwrap();
+    let keywords = ["set", "alias", "export", "import", "mod", "if", "else"];
+
+    let mut found: Vec<Found> = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+
+    for line in contents.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(text.trim().to_string());
+            }
+            continue;
+        }
+
+        // Attributes such as `[private]` apply to the next recipe
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+
+        let first = line.split_whitespace().next().unwrap_or_default();
+        let caps = match recipe.captures(line) {
+            Some(caps) if !keywords.contains(&first) => caps,
+            _ => {
+                comment = None;
+                private = false;
+                continue;
+            }
+        };
+
+        let name = &caps[1];
+
+        if !private && !name.starts_with('_') {
+            let params: Vec<String> = caps[2].split_whitespace().map(just_parameter).collect();
+
+            let cmd = std::iter::once(format!("just {}", name))
+                .chain(params)
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            found.push((name.to_string(), cmd, comment.take()));
+        }
+
+        comment = None;
+        private = false;
+    }
+
+    found
+}
+
+/// A recipe parameter as a placeholder: `env='dev'` becomes `${env:-dev}`, a `*rest`
+/// parameter may be left empty.
+fn just_parameter(param: &str) -> String {
+    let param = param.trim_start_matches('$');
+
+    let (param, optional) = match param.strip_prefix('*') {
+        Some(rest) => (rest, true),
+        None => (param.trim_start_matches('+'), false),
+    };
+
+    match param.split_once('=') {
+        Some((name, default)) => {
+            let default = default.trim_matches(|c| c == '"' || c == '\'');
+            format!("${{{}:-{}}}", name, default)
+        }
+        None if optional => format!("${{{}:-}}", param),
+        None => format!("${{{}}}", param),
+    }
+}
+
+/// The package manager the lock file in `dir` belongs to.
+fn npm_runner(dir: &Path) -> String {
+    let locks = [
+        ("pnpm-lock.yaml", "pnpm run"),
+        ("yarn.lock", "yarn run"),
+        ("bun.lock", "bun run"),
+        ("bun.lockb", "bun run"),
+    ];
+
+    locks
+        .iter()
+        .find(|(lock, _)| dir.join(lock).is_file())
+        .map_or("npm run", |(_, runner)| runner)
+        .to_string()
+}
+
+/// `scripts`, leaving out `pre` and `post` hooks of other scripts which run on their own.
+fn parse_package_json(contents: &str, file: &str, runner: &str) -> FlowletResult<Vec<Found>> {
+    let parsed: serde_json::Value =
+        serde_json::from_str(contents).map_err(|_| DiscoverError::InvalidJson(file.to_string()))?;
+
+    let Some(scripts) = parsed.get("scripts").and_then(|s| s.as_object()) else {
+        return Ok(Vec::new());
+    };
+
+    let is_hook = |name: &str| {
+        ["pre", "post"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|base| scripts.contains_key(base))
+        })
+    };
+
+    Ok(scripts
+        .iter()
+        .filter(|(name, _)| !is_hook(name))
+        .map(|(name, body)| {
+            (
+                name.clone(),
+                format!("{} {}", runner, name),
+                body.as_str().map(str::to_string),
+            )
+        })
+        .collect())
+}
+
+/// The `[alias]` table, written as a string or a list of arguments.
+fn parse_cargo_aliases(contents: &str, file: &str) -> FlowletResult<Vec<Found>> {
+    let parsed: toml::Table =
+        toml::from_str(contents).map_err(|_| DiscoverError::InvalidToml(file.to_string()))?;
+
+    let Some(aliases) = parsed.get("alias").and_then(|a| a.as_table()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(aliases
+        .iter()
+        .map(|(name, expansion)| {
+            let expansion = match expansion {
+                toml::Value::String(s) => Some(s.clone()),
+                toml::Value::Array(args) => Some(
+                    args.iter()
+                        .filter_map(|a| a.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => None,
+            };
+            (name.clone(), format!("cargo {}", name), expansion)
+        })
+        .collect())
+}
+
+/// One command per service, starting it with its dependencies.
+fn parse_compose(contents: &str, file: &str) -> FlowletResult<Vec<Found>> {
+    let parsed: serde_yaml::Value =
+        serde_yaml::from_str(contents).map_err(|_| DiscoverError::InvalidYaml(file.to_string()))?;
+
+    let Some(services) = parsed.get("services").and_then(|s| s.as_mapping()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(services
+        .iter()
+        .filter_map(|(name, service)| {
+            let name = name.as_str()?;
+            let image = service
+                .get("image")
+                .and_then(|i| i.as_str())
+                .map(|image| format!("image {}", image));
+
+            Some((
+                name.to_string(),
+                format!("docker compose up {}", name),
+                image,
+            ))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(found: &[Found]) -> Vec<&str> {
+        found.iter().map(|(name, _, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn makefile_targets_and_descriptions() {
+        let found = parse_makefile(
+            "# Build everything\nbuild: deps\n\tcargo build\n\ntest: ## Run the tests\n\tcargo test\n\nfmt lint:\n\tcargo fmt\n",
+        );
+
+        assert_eq!(names(&found), vec!["build", "test", "fmt", "lint"]);
+        assert_eq!(found[0].1, "make build");
+        assert_eq!(found[0].2.as_deref(), Some("Build everything"));
+        assert_eq!(found[1].2.as_deref(), Some("Run the tests"));
+        assert_eq!(found[2].2, None);
+    }
+
+    #[test]
+    fn makefile_skips_assignments_and_special_targets() {
+        let found = parse_makefile(
+            "CC := gcc\nFLAGS ::= -O2\nOUT = out\nPREFIX ?= /usr\n.PHONY: build\n.DEFAULT_GOAL := build\n%.o: %.c\n\t$(CC) -c $<\nbuild:\n\tmake all\n",
+        );
+
+        assert_eq!(names(&found), vec!["build"]);
+    }
+
+    #[test]
+    fn makefile_skips_define_blocks() {
+        let found = parse_makefile(
+            "define HELP\nusage: make target\nnot-a-target:\nendef\n\n# After the block\nrelease:\n\t./release.sh\n",
+        );
+
+        assert_eq!(names(&found), vec!["release"]);
+        assert_eq!(found[0].2.as_deref(), Some("After the block"));
+    }
+
+    #[test]
+    fn makefile_double_colon_rules_are_listed_once() {
+        let found = parse_makefile("clean::\n\trm -rf a\nclean::\n\trm -rf b\n");
+
+        assert_eq!(names(&found), vec!["clean"]);
+    }
+
+    #[test]
+    fn justfile_recipes_and_parameters() {
+        let found = parse_justfile(
+            "set shell := [\"bash\", \"-c\"]\nversion := \"1.0\"\n\n# Deploy the app\ndeploy env='dev' +targets:\n\t./deploy.sh {{env}}\n\n@fmt *args:\n\tcargo fmt {{args}}\n",
+        );
+
+        assert_eq!(names(&found), vec!["deploy", "fmt"]);
+        assert_eq!(found[0].1, "just deploy ${env:-dev} ${targets}");
+        assert_eq!(found[0].2.as_deref(), Some("Deploy the app"));
+        assert_eq!(found[1].1, "just fmt ${args:-}");
+    }
+
+    #[test]
+    fn justfile_skips_private_recipes() {
+        let found = parse_justfile(
+            "[private]\nsetup:\n\techo setup\n\n[no-cd, private]\nhelper:\n\techo helper\n\n_hidden:\n\techo hidden\n\n[linux]\nbuild:\n\tcargo build\n",
+        );
+
+        assert_eq!(names(&found), vec!["build"]);
+    }
+
+    #[test]
+    fn justfile_attributes_reset_after_a_recipe() {
+        let found = parse_justfile("[private]\nsetup:\n\techo setup\n\ntest:\n\tcargo test\n");
+
+        assert_eq!(names(&found), vec!["test"]);
+    }
+}
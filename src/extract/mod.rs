@@ -0,0 +1,215 @@
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{
+    json_path::{JsonPath, format_matches},
+    runner::RunOutput,
+    util::FlowletResult,
+};
+
+#[derive(Debug, Error)]
+pub enum ExtractError {
+    #[error("Invalid save target `{0}`. Expected `name` or `name=extractor`.")]
+    InvalidSaveVar(String),
+
+    #[error(
+        "Unknown extractor `{0}`. Expected `raw`, `json:<path>`, `regex:<pattern>` or `line:<n>`."
+    )]
+    UnknownExtractor(String),
+
+    #[error("Invalid regex `{0}`.")]
+    InvalidRegex(String),
+
+    #[error("Output is not valid JSON.")]
+    InvalidJson,
+
+    #[error("No value found at JSON path `{0}`.")]
+    JsonPathNotFound(String),
+
+    #[error("No match found for regex `{0}`.")]
+    RegexNotMatched(String),
+
+    #[error("Regex `{0}` has no capture group `{1}`.")]
+    GroupNotFound(String, String),
+
+    #[error("Output has no line {0}.")]
+    LineNotFound(i64),
+}
+
+/// Which output stream a value is extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub enum Extraction {
+    /// The whole stream.
+    Raw,
+    /// Matches of a path expression, see [`JsonPath`].
+    JsonPath { path: String, join: Option<String> },
+    /// The first match of a pattern. Without an explicit group the first capture group is
+    /// used when the pattern has one, otherwise the whole match.
+    Regex {
+        pattern: String,
+        group: Option<String>,
+    },
+    /// A single line, 1-based. Negative numbers count from the end.
+    Line(i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Extractor {
+    pub source: Source,
+    pub extraction: Extraction,
+    pub trim: bool,
+}
+
+/// A variable to save from a run, as given to `--save-var`.
+#[derive(Debug, Clone)]
+pub struct SaveVar {
+    pub name: String,
+    pub extractor: Extractor,
+}
+
+impl SaveVar {
+    /// Parses `name` or `name=extractor`. A bare name uses `default`, which carries the
+    /// run wide `--json-path`/`--regex`/`--line` flags.
+    ///
+    /// Extractors are `raw`, `json:<path>`, `regex:<pattern>`, `regex#<group>:<pattern>`
+    /// and `line:<n>`, optionally prefixed with `stdout:` or `stderr:`.
+    pub fn parse(spec: &str, default: &Extractor) -> FlowletResult<Self> {
+        let (name, extractor) = match spec.split_once('=') {
+            Some((name, extractor)) => (name.trim(), Some(extractor)),
+            None => (spec.trim(), None),
+        };
+
+        if name.is_empty() {
+            return Err(Box::new(ExtractError::InvalidSaveVar(spec.to_string())));
+        }
+
+        let extractor = match extractor {
+            Some(extractor) => parse_extractor(extractor, default)?,
+            None => default.clone(),
+        };
+
+        Ok(SaveVar {
+            name: name.to_string(),
+            extractor,
+        })
+    }
+}
+
+impl Extractor {
+    pub fn extract(&self, output: &RunOutput) -> FlowletResult<String> {
+        let text = match self.source {
+            Source::Stdout => output.stdout.as_str(),
+            Source::Stderr => output.stderr.as_str(),
+        };
+
+        let value = match &self.extraction {
+            Extraction::Raw => text.to_string(),
+            Extraction::JsonPath { path, join } => {
+                let json_path = JsonPath::parse(path)?;
+                let json_value = serde_json::from_str::<serde_json::Value>(text)
+                    .map_err(|_| ExtractError::InvalidJson)?;
+
+                format_matches(&json_path.select(&json_value), join.as_deref())
+                    .ok_or_else(|| ExtractError::JsonPathNotFound(path.clone()))?
+            }
+            Extraction::Regex { pattern, group } => extract_regex(text, pattern, group.as_deref())?,
+            Extraction::Line(n) => {
+                let lines: Vec<&str> = text.lines().collect();
+                let index = if *n < 0 {
+                    lines.len() as i64 + n
+                } else {
+                    n - 1
+                };
+
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|i| lines.get(i))
+                    .ok_or(ExtractError::LineNotFound(*n))?
+                    .to_string()
+            }
+        };
+
+        if self.trim {
+            Ok(value.trim().to_string())
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+fn parse_extractor(spec: &str, default: &Extractor) -> FlowletResult<Extractor> {
+    let (source, spec) = if let Some(rest) = spec.strip_prefix("stderr:") {
+        (Source::Stderr, rest)
+    } else if let Some(rest) = spec.strip_prefix("stdout:") {
+        (Source::Stdout, rest)
+    } else {
+        (default.source, spec)
+    };
+
+    let extraction = if spec.is_empty() || spec == "raw" {
+        Extraction::Raw
+    } else if let Some(path) = spec.strip_prefix("json:") {
+        Extraction::JsonPath {
+            path: path.to_string(),
+            join: None,
+        }
+    } else if let Some(pattern) = spec.strip_prefix("regex:") {
+        Extraction::Regex {
+            pattern: pattern.to_string(),
+            group: None,
+        }
+    } else if let Some((group, pattern)) = spec
+        .strip_prefix("regex#")
+        .and_then(|rest| rest.split_once(':'))
+    {
+        Extraction::Regex {
+            pattern: pattern.to_string(),
+            group: Some(group.to_string()),
+        }
+    } else if let Some(n) = spec.strip_prefix("line:") {
+        Extraction::Line(
+            n.trim()
+                .parse()
+                .map_err(|_| ExtractError::UnknownExtractor(spec.to_string()))?,
+        )
+    } else {
+        return Err(Box::new(ExtractError::UnknownExtractor(spec.to_string())));
+    };
+
+    Ok(Extractor {
+        source,
+        extraction,
+        trim: default.trim,
+    })
+}
+
+fn extract_regex(text: &str, pattern: &str, group: Option<&str>) -> FlowletResult<String> {
+    let regex = Regex::new(pattern).map_err(|e| {
+        log::error!("{:?}", e);
+        ExtractError::InvalidRegex(pattern.to_string())
+    })?;
+
+    let caps = regex
+        .captures(text)
+        .ok_or_else(|| ExtractError::RegexNotMatched(pattern.to_string()))?;
+
+    let matched = match group {
+        Some(group) => match group.parse::<usize>() {
+            Ok(index) => caps.get(index),
+            Err(_) => caps.name(group),
+        }
+        .ok_or_else(|| ExtractError::GroupNotFound(pattern.to_string(), group.to_string()))?,
+        None if caps.len() > 1 => caps
+            .get(1)
+            .ok_or_else(|| ExtractError::RegexNotMatched(pattern.to_string()))?,
+        None => caps.get(0).unwrap(),
+    };
+
+    Ok(matched.as_str().to_string())
+}
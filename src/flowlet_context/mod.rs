@@ -1,10 +1,19 @@
+use deeb::Query;
 use thiserror::Error;
 
-use crate::{api_client::ApiClient, flowlet_db::FlowletDb, util::FlowletResult};
+use crate::{
+    api_client::ApiClient,
+    config::Config,
+    flowlet_db::{FlowletDb, models::auth::Auth},
+    util::FlowletResult,
+};
 
 pub struct FlowletContext {
     pub flowlet_db: FlowletDb,
     pub api_client: ApiClient,
+    pub config: Config,
+    /// The signed in user, recorded as the author of changes.
+    pub user: Option<String>,
 }
 
 pub trait WithContext {
@@ -15,17 +24,35 @@ pub trait WithContext {
 pub enum FlowletContextError {}
 
 impl FlowletContext {
-    pub async fn new() -> FlowletResult<Self> {
+    pub async fn new(config: Config) -> FlowletResult<Self> {
         // Start Local DB
-        let flowlet_db = FlowletDb::new().await?;
+        let flowlet_db = FlowletDb::new(&config.data_file()?).await?;
 
-        // GET AUTH TOKEN FROM LOCAL DEEB DB
-        // let auth...
+        // Reuse the token stored by `flowlet auth login`, if any
+        let auth = Auth::find_one(&flowlet_db.deeb, Query::All, None)
+            .await
+            .map_err(|e| log::error!("Failed to read auth token: {:?}", e))
+            .ok()
+            .flatten();
+
+        let user = auth
+            .as_ref()
+            .and_then(|auth| auth.claims())
+            .and_then(|claims| claims.email.or(claims.sub));
+        let token = auth.map(|auth| auth.flowlet_token);
 
-        // CREATE A CLIENT WITH AUTH TOKEN IN HEADERS
         // Start Cloud DB
-        let api_client = ApiClient::new("http://localhost:8080")?;
+        let api_client = ApiClient::new(config.server_url(), token)?;
+
+        Ok(Self {
+            flowlet_db,
+            api_client,
+            config,
+            user,
+        })
+    }
 
-        Ok(Self { flowlet_db, api_client })
+    pub fn author(&self) -> Option<&str> {
+        self.user.as_deref()
     }
 }
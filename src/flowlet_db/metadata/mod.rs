@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Change tracking shared by every model, flattened into the record.
+///
+/// Records saved before metadata existed read as revision 0 until the backfill
+/// migration runs.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+
+    /// Incremented on every local change.
+    #[serde(default)]
+    pub revision: u64,
+
+    /// The signed in user that created the record, if any.
+    #[serde(default)]
+    pub created_by: Option<String>,
+
+    #[serde(default)]
+    pub updated_by: Option<String>,
+}
+
+impl Metadata {
+    /// Column headers for [`Metadata::columns`].
+    pub const HEADERS: [&'static str; 4] = ["Created", "Updated", "Rev", "By"];
+
+    pub fn new(author: Option<&str>) -> Self {
+        let now = Utc::now();
+
+        Self {
+            created_at: now,
+            updated_at: now,
+            revision: 1,
+            created_by: author.map(String::from),
+            updated_by: author.map(String::from),
+        }
+    }
+
+    /// Metadata for the next revision of a record.
+    pub fn next(&self, author: Option<&str>) -> Self {
+        Self {
+            updated_at: Utc::now(),
+            revision: self.revision + 1,
+            updated_by: author.map(String::from),
+            ..self.clone()
+        }
+    }
+
+    /// Metadata for a record saved before metadata existed, dated by its id.
+    pub fn backfilled(id: &ulid::Ulid) -> Self {
+        let created_at = DateTime::<Utc>::from(id.datetime());
+
+        Self {
+            created_at,
+            updated_at: created_at,
+            revision: 1,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    /// Values for the `--long` columns of list tables.
+    pub fn columns(&self) -> Vec<String> {
+        vec![
+            self.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            self.updated_at.format("%Y-%m-%d %H:%M").to_string(),
+            self.revision.to_string(),
+            self.updated_by.clone().unwrap_or_else(|| "--".to_string()),
+        ]
+    }
+}
+
+/// A record carrying [`Metadata`].
+pub trait Tracked {
+    fn metadata(&self) -> &Metadata;
+}
+
+/// An update document that also moves the record to its next revision.
+#[derive(Serialize)]
+pub struct Revised<T> {
+    #[serde(flatten)]
+    pub changes: T,
+
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
@@ -0,0 +1,95 @@
+use chrono::Utc;
+use deeb::{Deeb, Query};
+use thiserror::Error;
+
+use crate::{
+    flowlet_db::{
+        metadata::Metadata,
+        models::{
+            command::Command, migration::Migration, project::Project, task::Task,
+            variable::Variable, workflow::Workflow,
+        },
+    },
+    util::FlowletResult,
+};
+
+const BACKFILL_METADATA: &str = "0001_backfill_metadata";
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Migration `{0}` failed.")]
+    Failed(&'static str),
+}
+
+/// Gives records saved before metadata existed a creation date taken from their id
+/// and a first revision.
+macro_rules! backfill_metadata {
+    ($deeb:expr, $model:ty) => {{
+        let records = <$model>::find_many($deeb, Query::All, None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                MigrationError::Failed(BACKFILL_METADATA)
+            })?
+            .unwrap_or_default();
+
+        for record in records.iter().filter(|r| r.metadata.revision == 0) {
+            <$model>::update_one::<Metadata>(
+                $deeb,
+                Query::eq("_id", record._id.to_string()),
+                Metadata::backfilled(&record._id),
+                None,
+            )
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                MigrationError::Failed(BACKFILL_METADATA)
+            })?;
+        }
+    }};
+}
+
+/// Applies migrations that have not run against this data file yet.
+pub async fn run(deeb: &Deeb) -> FlowletResult<()> {
+    let applied: Vec<String> = Migration::find_many(deeb, Query::All, None, None)
+        .await
+        .map_err(|e| {
+            log::error!("{:?}", e);
+            MigrationError::Failed(BACKFILL_METADATA)
+        })?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| m.name)
+        .collect();
+
+    if !applied.iter().any(|name| name == BACKFILL_METADATA) {
+        log::info!("Running migration {}", BACKFILL_METADATA);
+
+        backfill_metadata!(deeb, Command);
+        backfill_metadata!(deeb, Project);
+        backfill_metadata!(deeb, Task);
+        backfill_metadata!(deeb, Workflow);
+        backfill_metadata!(deeb, Variable);
+
+        record(deeb, BACKFILL_METADATA).await?;
+    }
+
+    Ok(())
+}
+
+async fn record(deeb: &Deeb, name: &'static str) -> FlowletResult<()> {
+    let migration = Migration {
+        _id: ulid::Ulid::new(),
+        name: name.to_string(),
+        applied_at: Utc::now(),
+    };
+
+    Migration::insert_one(deeb, migration, None)
+        .await
+        .map_err(|e| {
+            log::error!("{:?}", e);
+            MigrationError::Failed(name)
+        })?;
+
+    Ok(())
+}
@@ -1,12 +1,16 @@
+use std::path::Path;
+
 use deeb::Deeb;
-use dirs::home_dir;
 use models::{
-    auth::Auth, command::Command, project::Project, task::Task, user::User, variable::Variable,
+    auth::Auth, command::Command, migration::Migration, outbox::Outbox, project::Project, run::Run,
+    sync_state::SyncState, task::Task, user::User, variable::Variable, workflow::Workflow,
 };
 use thiserror::Error;
 
 use crate::util::FlowletResult;
 
+pub mod metadata;
+pub mod migrations;
 pub mod models;
 
 pub struct FlowletDb {
@@ -17,13 +21,10 @@ pub struct FlowletDb {
 pub enum FlowletDbError {
     #[error("Failed to create DB Instance.")]
     InstanceCreationFailed,
-
-    #[error("Failed to access home directory.")]
-    HomeDirAccessDenied,
 }
 
 impl FlowletDb {
-    pub async fn new() -> FlowletResult<Self> {
+    pub async fn new(data_file: &Path) -> FlowletResult<Self> {
         let deeb = Deeb::new();
 
         // Init Models
@@ -33,14 +34,23 @@ impl FlowletDb {
         let variable = Variable::entity();
         let project = Project::entity();
         let task = Task::entity();
+        let workflow = Workflow::entity();
+        let outbox = Outbox::entity();
+        let sync_state = SyncState::entity();
+        let migration = Migration::entity();
+        let run = Run::entity();
 
-        // Persist Dir
-        let home = home_dir().ok_or(FlowletDbError::HomeDirAccessDenied)?;
+        if let Some(parent) = data_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
         deeb.add_instance(
             "local",
-            &format!("{}/.flowlet.json", home.to_str().unwrap()),
-            vec![auth, user, command, variable, project, task],
+            &data_file.to_string_lossy(),
+            vec![
+                auth, user, command, variable, project, task, workflow, outbox, sync_state,
+                migration, run,
+            ],
         )
         .await
         .map_err(|e| {
@@ -48,6 +58,8 @@ impl FlowletDb {
             FlowletDbError::InstanceCreationFailed
         })?;
 
+        migrations::run(&deeb).await?;
+
         Ok(FlowletDb { deeb })
     }
 }
@@ -1,3 +1,4 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use deeb::Collection;
 use deeb::*;
 use serde::{Deserialize, Serialize};
@@ -26,6 +27,26 @@ pub enum AuthError {
 
     #[error("Failed to remove auth details.")]
     RemoveAuthFailed,
+
+    #[error("Failed to read session.")]
+    SessionReadFailed,
+}
+
+/// The session the server associates with the stored token.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Session {
+    pub user_id: String,
+    pub email: Option<String>,
+    pub expires_at: Option<i64>,
+}
+
+/// Claims carried in the token payload. Decoded locally without verifying the signature,
+/// only the server can tell whether the token is still valid.
+#[derive(Debug, Deserialize)]
+pub struct TokenClaims {
+    pub sub: Option<String>,
+    pub email: Option<String>,
+    pub exp: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -45,6 +66,26 @@ struct LoginPayload {
     password: String,
 }
 
+impl Auth {
+    pub fn claims(&self) -> Option<TokenClaims> {
+        let payload = self.flowlet_token.split('.').nth(1)?;
+        let decoded = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+        serde_json::from_slice(&decoded).ok()
+    }
+
+    /// Asks the server for the session of the current token.
+    pub async fn session(flowlet_context: &FlowletContext) -> FlowletResult<Session> {
+        let client = &flowlet_context.api_client;
+
+        let res = client
+            .get::<Session>("/auth/session")
+            .await
+            .inspect_err(|e| log::error!("Failed to read session: {:?}", e))?;
+
+        Ok(res.data.ok_or(AuthError::SessionReadFailed)?)
+    }
+}
+
 impl Api for Auth {
     type CreateInput = CreateAuthInput;
 
@@ -1,3 +1,6 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use deeb::*;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -5,31 +8,174 @@ use thiserror::Error;
 
 use crate::{
     flowlet_context::FlowletContext,
+    flowlet_db::metadata::{Metadata, Revised, Tracked},
     printer::{Icon, Printer},
-    util::FlowletResult,
+    sync::Syncable,
+    util::{FlowletResult, Timespan},
 };
 
-use super::Api;
+use super::{Api, outbox::Outbox};
 
-#[derive(Collection, Deserialize, Serialize)]
+#[derive(Collection, Deserialize, Serialize, Clone)]
 pub struct Command {
     pub _id: ulid::Ulid,
     pub name: String,
     pub cmd: String,
-    pub project: Option<String>
+    pub project: Option<String>,
+
+    #[serde(flatten)]
+    pub options: CommandOptions,
+
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
+
+/// How a command is described and run, besides the command text itself.
+///
+/// Every field has a default so records saved before a field existed still load. Empty
+/// fields are still written so an update clears them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CommandOptions {
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Run arguments used before the ones given to `flowlet run`.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables set for the process. Values may use `${var}` placeholders.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Directory the command runs in instead of the current one, relative to the project
+    /// root when not absolute.
+    #[serde(default)]
+    pub cwd: Option<String>,
+
+    /// Pass placeholder values as environment variables, leaving the command text as
+    /// saved for the shell to expand.
+    #[serde(default)]
+    pub export_vars: bool,
+
+    #[serde(default)]
+    pub mode: CommandMode,
+
+    /// Program running the command, such as `bash` or `python3`. The `shell` setting
+    /// is used when unset.
+    #[serde(default)]
+    pub interpreter: Option<String>,
+
+    /// Kill the command, with everything it started, when it runs longer.
+    #[serde(default)]
+    pub timeout: Option<Timespan>,
+
+    /// Times a failed run is tried again.
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Wait before the first retry, doubled for each one after. A second when unset.
+    #[serde(default)]
+    pub retry_delay: Option<Timespan>,
+
+    /// Exit codes worth a retry. Any failure is retried when empty.
+    #[serde(default)]
+    pub retry_on_exit_codes: Vec<i32>,
+
+    /// Show the resolved command and ask before every run. Written as `danger` too.
+    #[serde(default, alias = "danger")]
+    pub confirm: bool,
+}
+
+/// Wait before the first retry when a command sets none.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Exit code of a run stopped with Ctrl-C, which is never retried.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+impl CommandOptions {
+    /// Whether a run exiting with `code` is tried again.
+    pub fn retries_on(&self, code: i32) -> bool {
+        code != 0
+            && code != INTERRUPTED_EXIT_CODE
+            && (self.retry_on_exit_codes.is_empty() || self.retry_on_exit_codes.contains(&code))
+    }
+
+    /// Wait before trying again after failed attempt number `attempt`, starting at 1.
+    pub fn retry_delay_after(&self, attempt: u32) -> Duration {
+        let delay = self.retry_delay.map_or(DEFAULT_RETRY_DELAY, |d| d.0);
+        delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+
+    /// The interpreter named by the `#!` line of `cmd`, or the saved one. `None` means
+    /// the `shell` setting.
+    pub fn interpreter_for(&self, cmd: &str) -> Option<String> {
+        shebang(cmd).or_else(|| self.interpreter.clone())
+    }
+}
+
+/// The program of the `#!` line of `cmd`, looking through `/usr/bin/env`.
+fn shebang(cmd: &str) -> Option<String> {
+    let line = cmd.trim_start().strip_prefix("#!")?.lines().next()?;
+    let mut words = line.split_whitespace();
+
+    let program = match words.next()? {
+        env if env.ends_with("/env") => words.find(|w| !w.starts_with('-'))?,
+        program => program,
+    };
+
+    std::path::Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// How the saved text of a command is run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommandMode {
+    /// Passed to the shell as one command, with trailing `\` line continuations folded.
+    #[default]
+    OneLiner,
+
+    /// Written to a file as saved and executed, so heredocs and blocks keep their lines.
+    Script,
+}
+
+impl CommandMode {
+    /// The mode for a newly saved command: a script when it starts with a shebang.
+    pub fn detect(cmd: &str) -> Self {
+        if cmd.trim_start().starts_with("#!") {
+            CommandMode::Script
+        } else {
+            CommandMode::OneLiner
+        }
+    }
+}
+
+impl std::fmt::Display for CommandMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            CommandMode::OneLiner => "one-liner",
+            CommandMode::Script => "script",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 #[derive(Serialize)]
 pub struct CreateCommandInput {
     pub name: String,
     pub cmd: String,
-    pub project: Option<String>
+    pub project: Option<String>,
+    pub options: CommandOptions,
 }
 
 #[derive(Serialize)]
 pub struct UpdateCommandInput {
     pub name: String,
     pub cmd: String,
+    /// Replaces the stored options when set, otherwise they are kept.
+    #[serde(flatten)]
+    pub options: Option<CommandOptions>,
 }
 
 #[derive(Serialize)]
@@ -63,9 +209,6 @@ pub enum CommandApiError {
     #[error("Command not found.")]
     CommandNotFound,
 
-    #[error("Failed to update remote.")]
-    UpdateRemoteFailed,
-
     #[error("Failed to delete command.")]
     DeleteCommandFailed,
 }
@@ -78,7 +221,6 @@ impl Api for Command {
         input: Self::CreateInput,
     ) -> FlowletResult<Self> {
         let deeb = &flowlet_context.flowlet_db.deeb;
-        let client = &flowlet_context.api_client;
 
         let command = Command::insert_one(
             deeb,
@@ -86,7 +228,9 @@ impl Api for Command {
                 _id: ulid::Ulid::new(),
                 name: input.name,
                 cmd: input.cmd,
-                project: input.project
+                project: input.project,
+                options: input.options,
+                metadata: Metadata::new(flowlet_context.author()),
             },
             None,
         )
@@ -96,16 +240,13 @@ impl Api for Command {
             CommandApiError::SaveCommandFailed
         })?;
 
-        let _ = client
-            .post::<Command, Command>("/insert-one/command", &command)
-            .await
-            .map_err(|_| {
-                Printer::error(
-                    Icon::Cloud,
-                    "Save Command",
-                    "Failed to save command to remote server.",
-                )
-            });
+        Outbox::send(
+            flowlet_context,
+            "/insert-one/command",
+            &command,
+            &format!("command `{}`", command.name),
+        )
+        .await?;
 
         Ok(command)
     }
@@ -116,16 +257,23 @@ impl Api for Command {
         input: Self::UpdateInput,
     ) -> FlowletResult<Self> {
         let deeb = &flowlet_context.flowlet_db.deeb;
-        let client = &flowlet_context.api_client;
 
         let query = Query::eq("name", input.name.clone());
 
-        let command = Command::update_one::<UpdateCommandInput>(
+        let current = Command::find_one(deeb, query.clone(), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                CommandApiError::ReadCommandFailed
+            })?
+            .ok_or(CommandApiError::CommandNotFound)?;
+
+        let command = Command::update_one::<Revised<UpdateCommandInput>>(
             deeb,
             query.clone(),
-            UpdateCommandInput {
-                name: input.name,
-                cmd: input.cmd,
+            Revised {
+                changes: input,
+                metadata: current.metadata.next(flowlet_context.author()),
             },
             None,
         )
@@ -144,17 +292,13 @@ impl Api for Command {
         Printer::info(Icon::Local, "Success", "Command updated on local.");
 
         // Update Remote
-        let remote_command = client
-            .post::<_, Command>(
-                "/update-one/command",
-                &json!({"query": query.clone(), "document": command}),
-            )
-            .await?;
-
-        if remote_command.data.is_none() {
-            log::error!("Command data not found.");
-            return Err(Box::new(CommandApiError::UpdateRemoteFailed));
-        }
+        Outbox::send(
+            flowlet_context,
+            "/update-one/command",
+            &json!({"query": query.clone(), "document": command}),
+            &format!("command `{}`", command.name),
+        )
+        .await?;
 
         Ok(command)
     }
@@ -234,7 +378,6 @@ impl Api for Command {
         input: Self::RemoveInput,
     ) -> FlowletResult<bool> {
         let deeb = &flowlet_context.flowlet_db.deeb;
-        let client = &flowlet_context.api_client;
 
         Printer::warning(
             Icon::Warning,
@@ -242,33 +385,95 @@ impl Api for Command {
             &format!("Removing command: `{}`", input.name),
         );
 
+        let label = format!("command `{}`", input.name);
         let query = Query::eq("name", input.name);
 
-        let success = client
-            .post::<_, bool>("/delete-one/command", &json!({"query": query}))
+        // If command on local is not found, the DB throws error
+        let removed = match Command::delete_one(deeb, query.clone(), None).await {
+            Ok(removed) => removed.unwrap_or(false),
+            Err(e) => {
+                log::error!("{:?}", e);
+                false
+            }
+        };
+
+        // Only queue the remote delete when there was something to delete
+        if !removed {
+            Printer::warning(Icon::Local, "Local Failed", "Command on local not found.");
+            return Ok(false);
+        }
+
+        Outbox::send(
+            flowlet_context,
+            "/delete-one/command",
+            &json!({"query": query}),
+            &label,
+        )
+        .await?;
+
+        Ok(true)
+    }
+}
+
+impl Tracked for Command {
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+impl Syncable for Command {
+    const ENTITY: &'static str = "command";
+
+    fn id(&self) -> ulid::Ulid {
+        self._id
+    }
+
+    fn key(&self) -> String {
+        self.name.clone()
+    }
+
+    fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    async fn find_all_local(ctx: &FlowletContext) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let records = Command::find_many(deeb, Query::All, None, None)
             .await
             .map_err(|e| {
                 log::error!("{:?}", e);
-                e
-            });
+                CommandApiError::ReadCommandFailed
+            })?;
 
-        // If command on remote is not found, the DB throws error
-        if success.is_err() {
-            log::error!("Failed to delete command from remote.");
-            Printer::warning(Icon::Cloud, "Remote Failed", "Command on remote not found.");
-        }
+        Ok(records.unwrap_or_default())
+    }
+
+    async fn insert_local(ctx: &FlowletContext, record: Self) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
 
-        let commands = Command::delete_one(deeb, query, None).await.map_err(|e| {
+        Command::insert_one(deeb, record, None).await.map_err(|e| {
             log::error!("{:?}", e);
-            CommandApiError::DeleteCommandFailed
-        });
+            CommandApiError::SaveCommandFailed
+        })?;
 
-        // If command on remote is not found, the DB throws error
-        if commands.is_err() {
-            Printer::warning(Icon::Local, "Local Failed", "Command on local not found.");
-            return Ok(false);
-        }
+        Ok(())
+    }
+
+    async fn delete_local(ctx: &FlowletContext, id: ulid::Ulid) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Command::delete_one(deeb, Query::eq("_id", id.to_string()), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                CommandApiError::DeleteCommandFailed
+            })?;
 
-        Ok(commands.unwrap().unwrap_or(false))
+        Ok(())
     }
 }
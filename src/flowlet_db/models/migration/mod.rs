@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use deeb::Collection;
+use serde::{Deserialize, Serialize};
+
+/// A data migration that has been applied to the local store.
+#[derive(Collection, Deserialize, Serialize)]
+pub struct Migration {
+    pub _id: ulid::Ulid,
+    pub name: String,
+    pub applied_at: DateTime<Utc>,
+}
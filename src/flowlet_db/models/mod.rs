@@ -8,6 +8,11 @@ pub mod user;
 pub mod variable;
 pub mod project;
 pub mod task;
+pub mod migration;
+pub mod outbox;
+pub mod run;
+pub mod sync_state;
+pub mod workflow;
 
 /// A trait that all models should implement
 pub trait Api: Sized {
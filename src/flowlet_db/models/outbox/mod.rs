@@ -0,0 +1,380 @@
+use chrono::{DateTime, Duration, Utc};
+use deeb::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    api_client::ApiClientError,
+    flowlet_context::FlowletContext,
+    printer::{Icon, Printer},
+    util::FlowletResult,
+};
+
+use super::Api;
+
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 60 * 60;
+
+/// A remote write that has not reached the server yet.
+#[derive(Collection, Deserialize, Serialize, Clone)]
+pub struct Outbox {
+    pub _id: ulid::Ulid,
+    /// Remote endpoint, such as `/insert-one/command`.
+    pub path: String,
+    pub body: Value,
+    /// What the write changes, for status output.
+    pub label: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    /// Set once the server rejected the write. Failed entries are only retried by
+    /// `flowlet sync`.
+    pub failed: bool,
+}
+
+#[derive(Serialize)]
+pub struct CreateOutboxInput {
+    pub path: String,
+    pub body: Value,
+    pub label: String,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateOutboxInput {
+    pub _id: ulid::Ulid,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub failed: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReadOutboxInput {
+    pub query: Query,
+}
+
+#[derive(Serialize)]
+pub struct ListOutboxInput {
+    pub query: Query,
+}
+
+#[derive(Serialize)]
+pub struct RemoveOutboxInput {
+    pub query: Query,
+}
+
+#[derive(Debug, Error)]
+pub enum OutboxApiError {
+    #[error("Failed to queue remote change.")]
+    SaveFailed,
+
+    #[error("Failed to read queued changes.")]
+    ReadFailed,
+
+    #[error("Failed to update queued change.")]
+    UpdateFailed,
+
+    #[error("Queued change not found.")]
+    NotFound,
+
+    #[error("Failed to remove queued change.")]
+    DeleteFailed,
+}
+
+/// What happened to a remote write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Sent,
+    /// The server could not be reached, the write is in the outbox.
+    Queued,
+    /// The server refused the write. It is not queued since resending would not help.
+    Rejected,
+    /// Not logged in, the change only exists locally.
+    Local,
+}
+
+impl Delivery {
+    /// Whether the server has the write, or will once the outbox is flushed.
+    pub fn reaches_remote(self) -> bool {
+        matches!(self, Delivery::Sent | Delivery::Queued)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    pub sent: usize,
+    /// Entries the server rejected during this flush.
+    pub failed: usize,
+    /// Entries still waiting to be sent, not counting failed ones.
+    pub remaining: usize,
+}
+
+impl Outbox {
+    pub fn status(&self) -> &'static str {
+        if self.failed { "failed" } else { "pending" }
+    }
+
+    /// Sends a remote write, or queues it when the server cannot be reached.
+    ///
+    /// While earlier writes are still pending the new one is queued behind them, so the
+    /// server always receives changes in the order they were made locally. Nothing is
+    /// sent while logged out, and remote failures are only warned about since the change
+    /// is already saved locally.
+    pub async fn send<T: Serialize>(
+        ctx: &FlowletContext,
+        path: &str,
+        body: &T,
+        label: &str,
+    ) -> FlowletResult<Delivery> {
+        if !ctx.api_client.is_authenticated() {
+            return Ok(Delivery::Local);
+        }
+
+        let body = serde_json::to_value(body)?;
+
+        let pending = Self::pending(ctx).await?;
+        if !pending.is_empty() {
+            Self::queue(ctx, path, body, label, None).await?;
+            return Ok(Delivery::Queued);
+        }
+
+        match ctx.api_client.post::<_, Value>(path, &body).await {
+            Ok(_) => Ok(Delivery::Sent),
+            Err(e) if is_unauthorized(&*e) => {
+                Printer::warning(
+                    Icon::Cloud,
+                    "Remote",
+                    &format!("Change to {} was only saved locally. {}", label, e),
+                );
+                Ok(Delivery::Rejected)
+            }
+            Err(e) if is_retryable(&*e) => {
+                log::error!("{:?}", e);
+                Self::queue(ctx, path, body, label, Some(e.to_string())).await?;
+                Ok(Delivery::Queued)
+            }
+            Err(e) => {
+                log::error!("{:?}", e);
+                Printer::warning(
+                    Icon::Cloud,
+                    "Remote",
+                    &format!("Remote rejected change to {}: {}", label, e),
+                );
+                Ok(Delivery::Rejected)
+            }
+        }
+    }
+
+    /// Sends queued writes in order, stopping at the first one the server cannot take
+    /// yet. Entries waiting on backoff and failed entries are skipped unless `force`.
+    pub async fn flush(ctx: &FlowletContext, force: bool) -> FlowletResult<FlushReport> {
+        let mut entries = Outbox::list(ctx, ListOutboxInput { query: Query::All }).await?;
+        entries.sort_by_key(|entry| entry._id);
+
+        let mut report = FlushReport::default();
+        let now = Utc::now();
+
+        for entry in &entries {
+            if !force && entry.failed {
+                continue;
+            }
+            if !force && entry.next_attempt_at > now {
+                break;
+            }
+
+            match ctx
+                .api_client
+                .post::<_, Value>(&entry.path, &entry.body)
+                .await
+            {
+                Ok(_) => {
+                    Outbox::remove(
+                        ctx,
+                        RemoveOutboxInput {
+                            query: Query::eq("_id", entry._id.to_string()),
+                        },
+                    )
+                    .await?;
+                    report.sent += 1;
+                }
+                // Keep the entry pending, it can be sent once the user logs in again
+                Err(e) if is_unauthorized(&*e) => return Err(e),
+                Err(e) => {
+                    log::error!("{:?}", e);
+                    let retryable = is_retryable(&*e);
+                    let attempts = entry.attempts + 1;
+
+                    Outbox::update(
+                        ctx,
+                        UpdateOutboxInput {
+                            _id: entry._id,
+                            attempts,
+                            last_error: Some(e.to_string()),
+                            next_attempt_at: now + backoff(attempts),
+                            failed: !retryable,
+                        },
+                    )
+                    .await?;
+
+                    // The server is unavailable, later entries would fail the same way
+                    if retryable {
+                        break;
+                    }
+                    report.failed += 1;
+                }
+            }
+        }
+
+        report.remaining = Self::pending(ctx).await?.len();
+        Ok(report)
+    }
+
+    /// Entries still waiting to be sent, oldest first.
+    pub async fn pending(ctx: &FlowletContext) -> FlowletResult<Vec<Outbox>> {
+        let mut entries = Outbox::list(ctx, ListOutboxInput { query: Query::All }).await?;
+        entries.retain(|entry| !entry.failed);
+        entries.sort_by_key(|entry| entry._id);
+        Ok(entries)
+    }
+
+    async fn queue(
+        ctx: &FlowletContext,
+        path: &str,
+        body: Value,
+        label: &str,
+        last_error: Option<String>,
+    ) -> FlowletResult<()> {
+        Outbox::create(
+            ctx,
+            CreateOutboxInput {
+                path: path.to_string(),
+                body,
+                label: label.to_string(),
+                last_error,
+            },
+        )
+        .await?;
+
+        Printer::warning(
+            Icon::Cloud,
+            "Queued",
+            &format!(
+                "Change to {} will sync when the server is reachable.",
+                label
+            ),
+        );
+        Ok(())
+    }
+}
+
+fn is_retryable(e: &(dyn std::error::Error + 'static)) -> bool {
+    e.downcast_ref::<ApiClientError>()
+        .is_some_and(ApiClientError::is_retryable)
+}
+
+fn is_unauthorized(e: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<ApiClientError>(),
+        Some(ApiClientError::Unauthorized)
+    )
+}
+
+/// Doubles the wait after each attempt, up to an hour.
+fn backoff(attempts: u32) -> Duration {
+    let secs = BASE_BACKOFF_SECS.saturating_mul(1 << attempts.saturating_sub(1).min(16));
+    Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+}
+
+impl Api for Outbox {
+    type CreateInput = CreateOutboxInput;
+
+    async fn create(ctx: &FlowletContext, input: Self::CreateInput) -> FlowletResult<Self> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        // Writes queued behind others have not been tried yet and are due right away
+        let (attempts, next_attempt_at) = match input.last_error {
+            Some(_) => (1, Utc::now() + backoff(1)),
+            None => (0, Utc::now()),
+        };
+
+        let entry = Outbox {
+            _id: ulid::Ulid::new(),
+            path: input.path,
+            body: input.body,
+            label: input.label,
+            attempts,
+            last_error: input.last_error,
+            next_attempt_at,
+            failed: false,
+        };
+
+        let saved = Outbox::insert_one(deeb, entry, None).await.map_err(|e| {
+            log::error!("{:?}", e);
+            OutboxApiError::SaveFailed
+        })?;
+
+        Ok(saved)
+    }
+
+    type UpdateInput = UpdateOutboxInput;
+
+    async fn update(ctx: &FlowletContext, input: Self::UpdateInput) -> FlowletResult<Self> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let query = Query::eq("_id", input._id.to_string());
+
+        let updated = Outbox::update_one::<UpdateOutboxInput>(deeb, query, input, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                OutboxApiError::UpdateFailed
+            })?;
+
+        updated.ok_or_else(|| Box::new(OutboxApiError::NotFound).into())
+    }
+
+    type ReadInput = ReadOutboxInput;
+
+    async fn read(ctx: &FlowletContext, input: Self::ReadInput) -> FlowletResult<Option<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Ok(Outbox::find_one(deeb, input.query, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                OutboxApiError::ReadFailed
+            })?)
+    }
+
+    type ListInput = ListOutboxInput;
+
+    async fn list(ctx: &FlowletContext, input: Self::ListInput) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let entries = Outbox::find_many(deeb, input.query, None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                OutboxApiError::ReadFailed
+            })?;
+
+        Ok(entries.unwrap_or_default())
+    }
+
+    type RemoveInput = RemoveOutboxInput;
+
+    async fn remove(ctx: &FlowletContext, input: Self::RemoveInput) -> FlowletResult<bool> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let deleted = Outbox::delete_many(deeb, input.query, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                OutboxApiError::DeleteFailed
+            })?;
+
+        Ok(deleted.unwrap_or(false))
+    }
+}
@@ -5,17 +5,22 @@ use thiserror::Error;
 
 use crate::{
     flowlet_context::FlowletContext,
+    flowlet_db::metadata::{Metadata, Revised, Tracked},
     printer::{Icon, Printer},
+    sync::Syncable,
     util::FlowletResult,
 };
 
-use super::Api;
+use super::{Api, outbox::Outbox};
 
 #[derive(Collection, Deserialize, Serialize, Clone)]
 pub struct Project {
     pub _id: ulid::Ulid,
     pub name: String,
     pub description: Option<String>,
+
+    #[serde(flatten)]
+    pub metadata: Metadata,
 }
 
 #[derive(Serialize, Clone)]
@@ -67,12 +72,12 @@ impl Api for Project {
 
     async fn create(ctx: &FlowletContext, input: Self::CreateInput) -> FlowletResult<Self> {
         let deeb = &ctx.flowlet_db.deeb;
-        let client = &ctx.api_client;
 
         let project = Project {
             _id: ulid::Ulid::new(),
             name: input.name,
             description: input.description,
+            metadata: Metadata::new(ctx.author()),
         };
 
         let saved = Project::insert_one(deeb, project.clone(), None)
@@ -83,12 +88,13 @@ impl Api for Project {
             })?;
 
         // Try syncing to remote
-        let _ = client
-            .post::<_, Project>("/insert-one/project", &saved)
-            .await
-            .map_err(|_| {
-                Printer::warning(Icon::Cloud, "Remote", "Failed to sync project to remote.")
-            });
+        Outbox::send(
+            ctx,
+            "/insert-one/project",
+            &saved,
+            &format!("project `{}`", saved.name),
+        )
+        .await?;
 
         Printer::success(Icon::Local, "Project", "Created successfully.");
         Ok(saved)
@@ -120,12 +126,24 @@ impl Api for Project {
 
     async fn update(ctx: &FlowletContext, input: Self::UpdateInput) -> FlowletResult<Self> {
         let deeb = &ctx.flowlet_db.deeb;
-        let client = &ctx.api_client;
 
         let query = Query::eq("name", input.name.clone());
 
+        let current = Project::find_one(deeb, query.clone(), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                ProjectApiError::UpdateFailed
+            })?
+            .ok_or(ProjectApiError::ProjectNotFound)?;
+
+        let revised = Revised {
+            changes: input,
+            metadata: current.metadata.next(ctx.author()),
+        };
+
         let updated =
-            Project::update_one::<UpdateProjectInput>(deeb, query.clone(), input.clone(), None)
+            Project::update_one::<Revised<UpdateProjectInput>>(deeb, query.clone(), revised, None)
                 .await
                 .map_err(|e| {
                     log::error!("{:?}", e);
@@ -138,18 +156,16 @@ impl Api for Project {
 
         let project = updated.unwrap();
 
-        let _ = client
-            .post::<_, Project>(
-                "/update-one/project",
-                &json!({
-                    "query": query,
-                    "document": project.clone()
-                }),
-            )
-            .await
-            .map_err(|_| {
-                Printer::warning(Icon::Cloud, "Remote", "Failed to update project remotely.");
-            });
+        Outbox::send(
+            ctx,
+            "/update-one/project",
+            &json!({
+                "query": query,
+                "document": project.clone()
+            }),
+            &format!("project `{}`", project.name),
+        )
+        .await?;
 
         Printer::success(Icon::Local, "Project", "Updated successfully.");
         Ok(project)
@@ -183,28 +199,89 @@ impl Api for Project {
 
     async fn remove(ctx: &FlowletContext, input: Self::RemoveInput) -> FlowletResult<bool> {
         let deeb = &ctx.flowlet_db.deeb;
-        let client = &ctx.api_client;
 
         let query = Query::eq("name", input.name.clone());
 
-        let _ = client
-            .post::<_, bool>("/delete-one/project", &json!({ "query": query.clone() }))
+        let deleted = Project::delete_one(deeb, query.clone(), None)
             .await
             .map_err(|e| {
                 log::error!("{:?}", e);
-                e
-            });
-
-        let deleted = Project::delete_one(deeb, query, None).await.map_err(|e| {
-            log::error!("{:?}", e);
-            ProjectApiError::DeleteFailed
-        })?;
+                ProjectApiError::DeleteFailed
+            })?;
 
-        if deleted.is_none() {
+        // Only queue the remote delete when there was something to delete
+        if deleted != Some(true) {
             return Err(Box::new(ProjectApiError::ProjectNotFound));
         }
 
+        Outbox::send(
+            ctx,
+            "/delete-one/project",
+            &json!({ "query": query }),
+            &format!("project `{}`", input.name),
+        )
+        .await?;
+
         Printer::success(Icon::Trash, "Project", "Deleted.");
         Ok(true)
     }
 }
+
+impl Tracked for Project {
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+impl Syncable for Project {
+    const ENTITY: &'static str = "project";
+
+    fn id(&self) -> ulid::Ulid {
+        self._id
+    }
+
+    fn key(&self) -> String {
+        self.name.clone()
+    }
+
+    fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    async fn find_all_local(ctx: &FlowletContext) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let records = Project::find_many(deeb, Query::All, None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                ProjectApiError::ProjectNotFound
+            })?;
+
+        Ok(records.unwrap_or_default())
+    }
+
+    async fn insert_local(ctx: &FlowletContext, record: Self) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Project::insert_one(deeb, record, None).await.map_err(|e| {
+            log::error!("{:?}", e);
+            ProjectApiError::SaveFailed
+        })?;
+
+        Ok(())
+    }
+
+    async fn delete_local(ctx: &FlowletContext, id: ulid::Ulid) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Project::delete_one(deeb, Query::eq("_id", id.to_string()), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                ProjectApiError::DeleteFailed
+            })?;
+
+        Ok(())
+    }
+}
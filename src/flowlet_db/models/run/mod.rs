@@ -0,0 +1,222 @@
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
+use deeb::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{api_client::EmptyData, flowlet_context::FlowletContext, util::FlowletResult};
+
+use super::Api;
+
+/// Bytes of stdout and stderr kept per run. Longer output keeps its end, where errors
+/// usually are.
+const OUTPUT_LIMIT: usize = 4096;
+
+/// A finished run of a saved command. Runs stay on this device and are never synced.
+#[derive(Collection, Deserialize, Serialize, Clone)]
+pub struct Run {
+    pub _id: ulid::Ulid,
+    /// Name of the saved command.
+    pub command: String,
+    /// The command as executed, with variables injected and secrets masked.
+    pub cmd: String,
+    /// Arguments given after `--`, secrets masked.
+    pub args: Vec<String>,
+    pub cwd: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    /// Times the command was started, more than one when it was retried.
+    #[serde(default = "first_attempt")]
+    pub attempts: u32,
+    /// Whether the last attempt was killed for running past its timeout.
+    #[serde(default)]
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs recorded before retries existed were started once.
+fn first_attempt() -> u32 {
+    1
+}
+
+impl Run {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Deletes all but the `keep` most recent runs of `command`, returning how many went.
+    pub async fn prune(ctx: &FlowletContext, command: &str, keep: usize) -> FlowletResult<usize> {
+        let runs = Run::list(
+            ctx,
+            ListRunInput {
+                query: Query::eq("command", command.to_string()),
+            },
+        )
+        .await?;
+
+        let expired = runs.iter().skip(keep).collect::<Vec<_>>();
+
+        for run in &expired {
+            Run::remove(
+                ctx,
+                RemoveRunInput {
+                    query: Query::eq("_id", run._id.to_string()),
+                },
+            )
+            .await?;
+        }
+
+        Ok(expired.len())
+    }
+}
+
+#[derive(Serialize)]
+pub struct CreateRunInput {
+    pub command: String,
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    pub attempts: u32,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Serialize)]
+pub struct ReadRunInput {
+    pub query: Query,
+}
+
+#[derive(Serialize)]
+pub struct ListRunInput {
+    pub query: Query,
+}
+
+#[derive(Serialize)]
+pub struct RemoveRunInput {
+    pub query: Query,
+}
+
+#[derive(Debug, Error)]
+pub enum RunApiError {
+    #[error("Failed to save run.")]
+    SaveFailed,
+
+    #[error("Failed to read run history.")]
+    ReadFailed,
+
+    #[error("Failed to delete run.")]
+    DeleteFailed,
+
+    #[error("Call not supported.")]
+    CallNotSupported,
+}
+
+impl Api for Run {
+    type CreateInput = CreateRunInput;
+
+    async fn create(ctx: &FlowletContext, input: Self::CreateInput) -> FlowletResult<Self> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let run = Run {
+            _id: ulid::Ulid::new(),
+            command: input.command,
+            cmd: input.cmd,
+            args: input.args,
+            cwd: input.cwd,
+            project: input.project,
+            environment: input.environment,
+            started_at: input.started_at,
+            duration_ms: input.duration_ms,
+            exit_code: input.exit_code,
+            attempts: input.attempts,
+            timed_out: input.timed_out,
+            stdout: truncate_output(input.stdout),
+            stderr: truncate_output(input.stderr),
+        };
+
+        let saved = Run::insert_one(deeb, run, None).await.map_err(|e| {
+            log::error!("{:?}", e);
+            RunApiError::SaveFailed
+        })?;
+
+        Ok(saved)
+    }
+
+    type UpdateInput = EmptyData;
+
+    async fn update(_ctx: &FlowletContext, _input: Self::UpdateInput) -> FlowletResult<Self> {
+        Err(Box::new(RunApiError::CallNotSupported))
+    }
+
+    type ReadInput = ReadRunInput;
+
+    async fn read(ctx: &FlowletContext, input: Self::ReadInput) -> FlowletResult<Option<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let run = Run::find_one(deeb, input.query, None).await.map_err(|e| {
+            log::error!("{:?}", e);
+            RunApiError::ReadFailed
+        })?;
+
+        Ok(run)
+    }
+
+    type ListInput = ListRunInput;
+
+    /// Runs matching the query, most recent first.
+    async fn list(ctx: &FlowletContext, input: Self::ListInput) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let mut runs = Run::find_many(deeb, input.query, None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                RunApiError::ReadFailed
+            })?
+            .unwrap_or_default();
+
+        runs.sort_by_key(|run| Reverse(run.started_at));
+
+        Ok(runs)
+    }
+
+    type RemoveInput = RemoveRunInput;
+
+    async fn remove(ctx: &FlowletContext, input: Self::RemoveInput) -> FlowletResult<bool> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let deleted = Run::delete_many(deeb, input.query, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                RunApiError::DeleteFailed
+            })?;
+
+        Ok(deleted.unwrap_or(false))
+    }
+}
+
+/// Keeps the last [`OUTPUT_LIMIT`] bytes of `output`, on a character boundary.
+fn truncate_output(output: String) -> String {
+    if output.len() <= OUTPUT_LIMIT {
+        return output;
+    }
+
+    let mut start = output.len() - OUTPUT_LIMIT;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+
+    format!("[... truncated]\n{}", &output[start..])
+}
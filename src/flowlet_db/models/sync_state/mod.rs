@@ -0,0 +1,141 @@
+use deeb::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{api_client::EmptyData, flowlet_context::FlowletContext, util::FlowletResult};
+
+use super::Api;
+
+/// The revisions of a record on both sides as of its last sync, used to tell which side
+/// changed since.
+#[derive(Collection, Deserialize, Serialize, Clone)]
+pub struct SyncState {
+    pub _id: ulid::Ulid,
+    pub entity: String,
+    pub key: String,
+    pub local_revision: u64,
+    pub remote_revision: u64,
+}
+
+#[derive(Serialize)]
+pub struct CreateSyncStateInput {
+    pub entity: String,
+    pub key: String,
+    pub local_revision: u64,
+    pub remote_revision: u64,
+}
+
+#[derive(Serialize)]
+pub struct ListSyncStateInput {
+    pub entity: String,
+}
+
+#[derive(Serialize)]
+pub struct RemoveSyncStateInput {
+    pub entity: String,
+    pub key: String,
+}
+
+#[derive(Debug, Error)]
+pub enum SyncStateApiError {
+    #[error("Failed to save sync state.")]
+    SaveFailed,
+
+    #[error("Failed to read sync state.")]
+    ReadFailed,
+
+    #[error("Failed to remove sync state.")]
+    DeleteFailed,
+
+    #[error("Call not supported.")]
+    CallNotSupported,
+}
+
+impl Api for SyncState {
+    /// Replaces any earlier state of the same record.
+    type CreateInput = CreateSyncStateInput;
+
+    async fn create(ctx: &FlowletContext, input: Self::CreateInput) -> FlowletResult<Self> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        SyncState::remove(
+            ctx,
+            RemoveSyncStateInput {
+                entity: input.entity.clone(),
+                key: input.key.clone(),
+            },
+        )
+        .await?;
+
+        let state = SyncState {
+            _id: ulid::Ulid::new(),
+            entity: input.entity,
+            key: input.key,
+            local_revision: input.local_revision,
+            remote_revision: input.remote_revision,
+        };
+
+        let saved = SyncState::insert_one(deeb, state, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                SyncStateApiError::SaveFailed
+            })?;
+
+        Ok(saved)
+    }
+
+    type UpdateInput = EmptyData;
+
+    async fn update(_: &FlowletContext, _: Self::UpdateInput) -> FlowletResult<Self> {
+        Err(Box::new(SyncStateApiError::CallNotSupported))
+    }
+
+    type ReadInput = EmptyData;
+
+    async fn read(_: &FlowletContext, _: Self::ReadInput) -> FlowletResult<Option<Self>> {
+        Err(Box::new(SyncStateApiError::CallNotSupported))
+    }
+
+    type ListInput = ListSyncStateInput;
+
+    async fn list(ctx: &FlowletContext, input: Self::ListInput) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let states = SyncState::find_many(deeb, Query::eq("entity", input.entity), None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                SyncStateApiError::ReadFailed
+            })?;
+
+        Ok(states.unwrap_or_default())
+    }
+
+    type RemoveInput = RemoveSyncStateInput;
+
+    async fn remove(ctx: &FlowletContext, input: Self::RemoveInput) -> FlowletResult<bool> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let existing = SyncState::list(
+            ctx,
+            ListSyncStateInput {
+                entity: input.entity,
+            },
+        )
+        .await?;
+
+        let mut removed = false;
+        for state in existing.iter().filter(|state| state.key == input.key) {
+            SyncState::delete_one(deeb, Query::eq("_id", state._id.to_string()), None)
+                .await
+                .map_err(|e| {
+                    log::error!("{:?}", e);
+                    SyncStateApiError::DeleteFailed
+                })?;
+            removed = true;
+        }
+
+        Ok(removed)
+    }
+}
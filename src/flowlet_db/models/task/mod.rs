@@ -5,11 +5,16 @@ use thiserror::Error;
 
 use crate::{
     flowlet_context::FlowletContext,
+    flowlet_db::metadata::{Metadata, Revised, Tracked},
     printer::{Icon, Printer},
+    sync::Syncable,
     util::FlowletResult,
 };
 
-use super::Api;
+use super::{
+    Api,
+    outbox::{Delivery, Outbox},
+};
 
 #[derive(Collection, Deserialize, Serialize, Clone)]
 pub struct Task {
@@ -21,6 +26,9 @@ pub struct Task {
     pub due_date: Option<chrono::NaiveDate>,
     pub tags: Vec<String>,
     pub milestones: Vec<Milestone>,
+
+    #[serde(flatten)]
+    pub metadata: Metadata,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -111,7 +119,6 @@ impl Api for Task {
     type CreateInput = CreateTaskInput;
     async fn create(ctx: &FlowletContext, input: Self::CreateInput) -> FlowletResult<Self> {
         let deeb = &ctx.flowlet_db.deeb;
-        let client = &ctx.api_client;
 
         let task = Task {
             _id: ulid::Ulid::new(),
@@ -122,6 +129,7 @@ impl Api for Task {
             due_date: input.due_date,
             tags: input.tags,
             milestones: input.milestones,
+            metadata: Metadata::new(ctx.author()),
         };
 
         let saved = Task::insert_one(deeb, task, None).await.map_err(|e| {
@@ -132,15 +140,15 @@ impl Api for Task {
         Printer::success(Icon::Local, "Task", "Saved to local store.");
 
         // Try syncing to remote
-        let remote_saved = client.post::<_, Task>("/insert-one/task", &saved).await;
-
-        if let Err(e) = &remote_saved {
-            Printer::warning(
-                Icon::Cloud,
-                "Remote",
-                &format!("Failed to sync task to remote: {:?}", e),
-            );
-        } else {
+        let delivery = Outbox::send(
+            ctx,
+            "/insert-one/task",
+            &saved,
+            &format!("task `{}`", saved.title),
+        )
+        .await?;
+
+        if delivery == Delivery::Sent {
             Printer::success(Icon::Cloud, "Task", "Saved to cloud.");
         }
 
@@ -173,16 +181,29 @@ impl Api for Task {
     type UpdateInput = UpdateTaskInput;
     async fn update(ctx: &FlowletContext, input: Self::UpdateInput) -> FlowletResult<Self> {
         let deeb = &ctx.flowlet_db.deeb;
-        let client = &ctx.api_client;
 
         let query = Query::eq("title", input.title.clone());
 
-        let updated = Task::update_one::<UpdateTaskInput>(deeb, query.clone(), input, None)
+        let current = Task::find_one(deeb, query.clone(), None)
             .await
             .map_err(|e| {
                 log::error!("{:?}", e);
-                TaskApiError::UpdateFailed
-            })?;
+                TaskApiError::ReadFailed
+            })?
+            .ok_or(TaskApiError::TaskNotFound)?;
+
+        let revised = Revised {
+            changes: input,
+            metadata: current.metadata.next(ctx.author()),
+        };
+
+        let updated =
+            Task::update_one::<Revised<UpdateTaskInput>>(deeb, query.clone(), revised, None)
+                .await
+                .map_err(|e| {
+                    log::error!("{:?}", e);
+                    TaskApiError::UpdateFailed
+                })?;
 
         if updated.is_none() {
             return Err(Box::new(TaskApiError::TaskNotFound));
@@ -190,18 +211,16 @@ impl Api for Task {
 
         let task = updated.unwrap();
 
-        let _ = client
-            .post::<_, Task>(
-                "/update-one/task",
-                &json!({
-                    "query": query,
-                    "document": task.clone()
-                }),
-            )
-            .await
-            .map_err(|_| {
-                Printer::warning(Icon::Cloud, "Remote", "Failed to update project remotely.");
-            });
+        Outbox::send(
+            ctx,
+            "/update-one/task",
+            &json!({
+                "query": query,
+                "document": task.clone()
+            }),
+            &format!("task `{}`", task.title),
+        )
+        .await?;
 
         Printer::success(Icon::Local, "Task", "Updated successfully.");
         Ok(task)
@@ -237,28 +256,85 @@ impl Api for Task {
     type RemoveInput = RemoveTaskInput;
     async fn remove(ctx: &FlowletContext, input: Self::RemoveInput) -> FlowletResult<bool> {
         let deeb = &ctx.flowlet_db.deeb;
-        let client = &ctx.api_client;
 
+        let label = format!("task `{}`", input.title);
         let query = Query::eq("title", input.title);
 
-        let _ = client
-            .post::<_, bool>("/delete-one/task", &json!({ "query": query.clone() }))
+        let deleted = Task::delete_one(deeb, query.clone(), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                TaskApiError::DeleteFailed
+            })?;
+
+        // Only queue the remote delete when there was something to delete
+        if deleted != Some(true) {
+            return Err(Box::new(TaskApiError::TaskNotFound));
+        }
+
+        Outbox::send(ctx, "/delete-one/task", &json!({ "query": query }), &label).await?;
+
+        Printer::success(Icon::Trash, "Task", "Deleted successfully.");
+        Ok(true)
+    }
+}
+
+impl Tracked for Task {
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+impl Syncable for Task {
+    const ENTITY: &'static str = "task";
+
+    fn id(&self) -> ulid::Ulid {
+        self._id
+    }
+
+    /// Titles are not unique, tasks are addressed by `_id` everywhere else too.
+    fn key(&self) -> String {
+        self._id.to_string()
+    }
+
+    fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    async fn find_all_local(ctx: &FlowletContext) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let records = Task::find_many(deeb, Query::All, None, None)
             .await
             .map_err(|e| {
                 log::error!("{:?}", e);
-                e
-            });
+                TaskApiError::ReadFailed
+            })?;
 
-        let deleted = Task::delete_one(deeb, query, None).await.map_err(|e| {
+        Ok(records.unwrap_or_default())
+    }
+
+    async fn insert_local(ctx: &FlowletContext, record: Self) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Task::insert_one(deeb, record, None).await.map_err(|e| {
             log::error!("{:?}", e);
-            TaskApiError::DeleteFailed
+            TaskApiError::CreateFailed
         })?;
 
-        if let Some(true) = deleted {
-            Printer::success(Icon::Trash, "Task", "Deleted successfully.");
-            Ok(true)
-        } else {
-            Err(Box::new(TaskApiError::TaskNotFound))
-        }
+        Ok(())
+    }
+
+    async fn delete_local(ctx: &FlowletContext, id: ulid::Ulid) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Task::delete_one(deeb, Query::eq("_id", id.to_string()), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                TaskApiError::DeleteFailed
+            })?;
+
+        Ok(())
     }
 }
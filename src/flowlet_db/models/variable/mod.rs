@@ -4,34 +4,71 @@ use thiserror::Error;
 
 use crate::{
     flowlet_context::FlowletContext,
+    flowlet_db::metadata::{Metadata, Revised, Tracked},
     printer::{Icon, Printer},
+    secrets::MASK,
     util::FlowletResult,
 };
 
 use super::Api;
 
-#[derive(Collection, Deserialize, Serialize)]
+#[derive(Collection, Deserialize, Serialize, Clone)]
 pub struct Variable {
     pub _id: ulid::Ulid,
     pub name: String,
     pub value: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
+    /// When set, `value` holds ciphertext produced by `secrets::encrypt`.
+    #[serde(default)]
+    pub secret: bool,
+
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
+
+/// How specific a variable is. Lookups prefer the most specific scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VariableScope {
+    Global,
+    Project,
+    Environment,
+}
+
+impl std::fmt::Display for VariableScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            VariableScope::Global => "global",
+            VariableScope::Project => "project",
+            VariableScope::Environment => "project+env",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 #[derive(Serialize)]
 pub struct CreateVariableInput {
     pub name: String,
     pub value: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
+    pub secret: bool,
 }
 
 #[derive(Serialize)]
 pub struct UpdateVariableInput {
     pub name: String,
     pub value: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
+    pub secret: bool,
 }
 
 #[derive(Serialize)]
 pub struct RemoveVariableInput {
     pub name: String,
+    pub project: Option<String>,
+    pub environment: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -65,6 +102,87 @@ pub enum VariableApiError {
     VariableNotFound,
 }
 
+impl Variable {
+    /// The value for display, masked when the variable is a secret.
+    pub fn display_value(&self) -> String {
+        if self.secret {
+            MASK.to_string()
+        } else {
+            self.value.clone()
+        }
+    }
+
+    pub fn scope(&self) -> VariableScope {
+        match (&self.project, &self.environment) {
+            (Some(_), Some(_)) => VariableScope::Environment,
+            (Some(_), None) => VariableScope::Project,
+            _ => VariableScope::Global,
+        }
+    }
+
+    /// Whether this variable applies inside the given project and environment.
+    pub fn applies_to(&self, project: Option<&str>, environment: Option<&str>) -> bool {
+        match self.scope() {
+            VariableScope::Global => true,
+            VariableScope::Project => self.project.as_deref() == project,
+            VariableScope::Environment => {
+                self.project.as_deref() == project && self.environment.as_deref() == environment
+            }
+        }
+    }
+
+    /// Finds the variable saved with exactly this name and scope.
+    pub async fn find_scoped(
+        flowlet_context: &FlowletContext,
+        name: &str,
+        project: Option<&str>,
+        environment: Option<&str>,
+    ) -> FlowletResult<Option<Self>> {
+        let candidates = Self::find_by_name(flowlet_context, name).await?;
+
+        Ok(candidates
+            .into_iter()
+            .find(|v| v.project.as_deref() == project && v.environment.as_deref() == environment))
+    }
+
+    /// Resolves a variable most-specific-first: project+env, then project, then global.
+    pub async fn resolve(
+        flowlet_context: &FlowletContext,
+        name: &str,
+        project: Option<&str>,
+        environment: Option<&str>,
+    ) -> FlowletResult<Option<Self>> {
+        let candidates = Self::find_by_name(flowlet_context, name).await?;
+
+        Ok(candidates
+            .into_iter()
+            .filter(|v| v.applies_to(project, environment))
+            .max_by_key(|v| v.scope()))
+    }
+
+    async fn find_by_name(
+        flowlet_context: &FlowletContext,
+        name: &str,
+    ) -> FlowletResult<Vec<Self>> {
+        let deeb = &flowlet_context.flowlet_db.deeb;
+
+        let result = Variable::find_many(deeb, Query::eq("name", name.to_string()), None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                VariableApiError::ReadFailed
+            })?;
+
+        Ok(result.unwrap_or_default())
+    }
+}
+
+impl Tracked for Variable {
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
 impl Api for Variable {
     type CreateInput = CreateVariableInput;
     async fn create(
@@ -77,6 +195,10 @@ impl Api for Variable {
             _id: ulid::Ulid::new(),
             name: input.name,
             value: input.value,
+            project: input.project,
+            environment: input.environment,
+            secret: input.secret,
+            metadata: Metadata::new(flowlet_context.author()),
         };
 
         let saved = Variable::insert_one(deeb, variable, None)
@@ -115,14 +237,29 @@ impl Api for Variable {
     ) -> FlowletResult<Self> {
         let deeb = &flowlet_context.flowlet_db.deeb;
 
-        let query = Query::eq("name", input.name.clone());
+        let existing = Variable::find_scoped(
+            flowlet_context,
+            &input.name,
+            input.project.as_deref(),
+            input.environment.as_deref(),
+        )
+        .await?
+        .ok_or(VariableApiError::VariableNotFound)?;
 
-        let updated = Variable::update_one::<UpdateVariableInput>(deeb, query, input, None)
-            .await
-            .map_err(|e| {
-                log::error!("{:?}", e);
-                VariableApiError::UpdateFailed
-            })?;
+        let query = Query::eq("_id", existing._id.to_string());
+
+        let revised = Revised {
+            changes: input,
+            metadata: existing.metadata.next(flowlet_context.author()),
+        };
+
+        let updated =
+            Variable::update_one::<Revised<UpdateVariableInput>>(deeb, query, revised, None)
+                .await
+                .map_err(|e| {
+                    log::error!("{:?}", e);
+                    VariableApiError::UpdateFailed
+                })?;
 
         if let Some(var) = updated {
             Printer::success(Icon::Local, "Variable", "Updated successfully.");
@@ -160,7 +297,16 @@ impl Api for Variable {
     ) -> FlowletResult<bool> {
         let deeb = &flowlet_context.flowlet_db.deeb;
 
-        let query = Query::eq("name", input.name);
+        let existing = Variable::find_scoped(
+            flowlet_context,
+            &input.name,
+            input.project.as_deref(),
+            input.environment.as_deref(),
+        )
+        .await?
+        .ok_or(VariableApiError::VariableNotFound)?;
+
+        let query = Query::eq("_id", existing._id.to_string());
 
         let deleted = Variable::delete_one(deeb, query, None).await.map_err(|e| {
             log::error!("{:?}", e);
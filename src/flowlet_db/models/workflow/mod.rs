@@ -0,0 +1,360 @@
+use deeb::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    flowlet_context::FlowletContext,
+    flowlet_db::metadata::{Metadata, Revised, Tracked},
+    printer::{Icon, Printer},
+    sync::Syncable,
+    util::FlowletResult,
+};
+
+use super::{Api, outbox::Outbox};
+
+#[derive(Collection, Deserialize, Serialize, Clone)]
+pub struct Workflow {
+    pub _id: ulid::Ulid,
+    pub name: String,
+    pub description: Option<String>,
+    pub project: Option<String>,
+    pub steps: Vec<WorkflowStep>,
+
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkflowStep {
+    pub name: String,
+    pub run: StepRun,
+    pub save_var: Option<String>,
+    pub json_path: Option<String>,
+    pub continue_on_error: bool,
+}
+
+/// What a workflow step executes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepRun {
+    /// A saved command, referenced by name.
+    Command(String),
+    /// An inline shell snippet.
+    Shell(String),
+}
+
+impl std::fmt::Display for StepRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepRun::Command(name) => write!(f, "command: {}", name),
+            StepRun::Shell(cmd) => write!(f, "shell: {}", cmd),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CreateWorkflowInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub project: Option<String>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateWorkflowInput {
+    pub name: String,
+    pub description: Option<String>,
+    /// Names are only unique within a project, this picks which workflow is updated.
+    pub project: Option<String>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Serialize)]
+pub struct RemoveWorkflowInput {
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct ReadWorkflowInput {
+    pub query: Query,
+    pub remote: bool,
+}
+
+#[derive(Serialize)]
+pub struct ListWorkflowInput {
+    pub query: Query,
+    pub remote: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum WorkflowApiError {
+    #[error("Failed to save workflow.")]
+    SaveFailed,
+
+    #[error("Failed to read workflow.")]
+    ReadFailed,
+
+    #[error("Failed to update workflow.")]
+    UpdateFailed,
+
+    #[error("Failed to delete workflow.")]
+    DeleteFailed,
+
+    #[error("Workflow not found.")]
+    WorkflowNotFound,
+}
+
+impl Api for Workflow {
+    type CreateInput = CreateWorkflowInput;
+
+    async fn create(ctx: &FlowletContext, input: Self::CreateInput) -> FlowletResult<Self> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let workflow = Workflow {
+            _id: ulid::Ulid::new(),
+            name: input.name,
+            description: input.description,
+            project: input.project,
+            steps: input.steps,
+            metadata: Metadata::new(ctx.author()),
+        };
+
+        let saved = Workflow::insert_one(deeb, workflow, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::SaveFailed
+            })?;
+
+        Printer::success(Icon::Local, "Workflow", "Saved to local store.");
+
+        // Try syncing to remote
+        Outbox::send(
+            ctx,
+            "/insert-one/workflow",
+            &saved,
+            &format!("workflow `{}`", saved.name),
+        )
+        .await?;
+
+        Ok(saved)
+    }
+
+    type ReadInput = ReadWorkflowInput;
+
+    async fn read(ctx: &FlowletContext, input: Self::ReadInput) -> FlowletResult<Option<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+        let client = &ctx.api_client;
+
+        if input.remote {
+            Printer::info(Icon::Cloud, "Remote", "Fetching workflow...");
+            let res = client
+                .post::<_, Workflow>("/find-one/workflow", &json!({ "query": input.query }))
+                .await?;
+
+            return Ok(res.data);
+        }
+
+        let workflow = Workflow::find_one(deeb, input.query, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::ReadFailed
+            })?;
+
+        Ok(workflow)
+    }
+
+    type UpdateInput = UpdateWorkflowInput;
+
+    async fn update(ctx: &FlowletContext, input: Self::UpdateInput) -> FlowletResult<Self> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let current = Workflow::find_in_project(ctx, &input.name, input.project.as_deref())
+            .await?
+            .ok_or(WorkflowApiError::WorkflowNotFound)?;
+
+        let query = Query::eq("_id", current._id.to_string());
+
+        let revised = Revised {
+            changes: input,
+            metadata: current.metadata.next(ctx.author()),
+        };
+
+        let updated = Workflow::update_one::<Revised<UpdateWorkflowInput>>(
+            deeb,
+            query.clone(),
+            revised,
+            None,
+        )
+        .await
+        .map_err(|e| {
+            log::error!("{:?}", e);
+            WorkflowApiError::UpdateFailed
+        })?;
+
+        let workflow = match updated {
+            Some(w) => w,
+            None => return Err(Box::new(WorkflowApiError::WorkflowNotFound)),
+        };
+
+        Outbox::send(
+            ctx,
+            "/update-one/workflow",
+            &json!({
+                "query": query,
+                "document": workflow.clone()
+            }),
+            &format!("workflow `{}`", workflow.name),
+        )
+        .await?;
+
+        Printer::success(Icon::Local, "Workflow", "Updated successfully.");
+        Ok(workflow)
+    }
+
+    type ListInput = ListWorkflowInput;
+
+    async fn list(ctx: &FlowletContext, input: Self::ListInput) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+        let client = &ctx.api_client;
+
+        if input.remote {
+            Printer::info(Icon::Cloud, "Remote", "Fetching workflows...");
+            let res = client
+                .post::<_, Vec<Workflow>>("/find-many/workflow", &json!({ "query": input.query }))
+                .await?;
+
+            return Ok(res.data.unwrap_or_default());
+        }
+
+        let workflows = Workflow::find_many(deeb, input.query, None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::ReadFailed
+            })?;
+
+        Ok(workflows.unwrap_or_default())
+    }
+
+    type RemoveInput = RemoveWorkflowInput;
+
+    async fn remove(ctx: &FlowletContext, input: Self::RemoveInput) -> FlowletResult<bool> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let label = format!("workflow `{}`", input.name);
+        let query = Query::eq("name", input.name);
+
+        let deleted = Workflow::delete_one(deeb, query.clone(), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::DeleteFailed
+            })?;
+
+        // Only queue the remote delete when there was something to delete
+        if deleted != Some(true) {
+            return Err(Box::new(WorkflowApiError::WorkflowNotFound));
+        }
+
+        Outbox::send(
+            ctx,
+            "/delete-one/workflow",
+            &json!({ "query": query }),
+            &label,
+        )
+        .await?;
+
+        Printer::success(Icon::Trash, "Workflow", "Deleted successfully.");
+        Ok(true)
+    }
+}
+
+impl Workflow {
+    /// The local workflow called `name` in `project`, or outside any project when `None`.
+    pub async fn find_in_project(
+        ctx: &FlowletContext,
+        name: &str,
+        project: Option<&str>,
+    ) -> FlowletResult<Option<Self>> {
+        let workflows = Workflow::list(
+            ctx,
+            ListWorkflowInput {
+                query: Query::eq("name", name.to_string()),
+                remote: false,
+            },
+        )
+        .await?;
+
+        Ok(workflows
+            .into_iter()
+            .find(|workflow| workflow.project.as_deref() == project))
+    }
+}
+
+impl Tracked for Workflow {
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+impl Syncable for Workflow {
+    const ENTITY: &'static str = "workflow";
+
+    fn id(&self) -> ulid::Ulid {
+        self._id
+    }
+
+    fn key(&self) -> String {
+        self.name.clone()
+    }
+
+    fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    async fn find_all_local(ctx: &FlowletContext) -> FlowletResult<Vec<Self>> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        let records = Workflow::find_many(deeb, Query::All, None, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::ReadFailed
+            })?;
+
+        Ok(records.unwrap_or_default())
+    }
+
+    async fn insert_local(ctx: &FlowletContext, record: Self) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Workflow::insert_one(deeb, record, None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::SaveFailed
+            })?;
+
+        Ok(())
+    }
+
+    async fn delete_local(ctx: &FlowletContext, id: ulid::Ulid) -> FlowletResult<()> {
+        let deeb = &ctx.flowlet_db.deeb;
+
+        Workflow::delete_one(deeb, Query::eq("_id", id.to_string()), None)
+            .await
+            .map_err(|e| {
+                log::error!("{:?}", e);
+                WorkflowApiError::DeleteFailed
+            })?;
+
+        Ok(())
+    }
+}
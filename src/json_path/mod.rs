@@ -0,0 +1,606 @@
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum JsonPathError {
+    #[error("Invalid JSON path `{0}`: {1}")]
+    Parse(String, String),
+}
+
+/// A compiled path expression such as `data.users[*].email` or `$.items[?(@.id > 2)]`.
+///
+/// Supported syntax:
+/// - `.key`, `['key.with.dots']` and `["key"]` for object members
+/// - `[0]`, `[-1]` and `[0,2]` for array indices
+/// - `*` and `[*]` wildcards, `..key` for recursive descent
+/// - `[start:end:step]` slices
+/// - `[?(@.field OP literal)]` filters with `==`, `!=`, `<`, `<=`, `>`, `>=`, `&&` and `||`,
+///   or `[?(@.field)]` to keep elements where the field is present and truthy
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Keys(Vec<String>),
+    Indices(Vec<i64>),
+    Wildcard,
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Filter),
+    Descendants,
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Exists(JsonPath),
+    Compare(JsonPath, Operator, Value),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, JsonPathError> {
+        let error = |message: &str| JsonPathError::Parse(path.to_string(), message.to_string());
+        let chars: Vec<char> = path.trim().chars().collect();
+        let mut segments = Vec::new();
+        let mut i = 0;
+
+        // Both `$.a.b` and `a.b` are accepted
+        if chars.first() == Some(&'$') || chars.first() == Some(&'@') {
+            i += 1;
+        }
+
+        while i < chars.len() {
+            match chars[i] {
+                '.' if chars.get(i + 1) == Some(&'.') => {
+                    segments.push(Segment::Descendants);
+                    i += 2;
+                    if chars.get(i) != Some(&'[') {
+                        i = parse_member(&chars, i, &mut segments).map_err(|m| error(&m))?;
+                    }
+                }
+                '.' => {
+                    i = parse_member(&chars, i + 1, &mut segments).map_err(|m| error(&m))?;
+                }
+                '[' => {
+                    let end =
+                        find_closing_bracket(&chars, i).ok_or_else(|| error("unclosed `[`"))?;
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    segments.push(parse_bracket(inner.trim()).map_err(|m| error(&m))?);
+                    i = end + 1;
+                }
+                _ if segments.is_empty() => {
+                    i = parse_member(&chars, i, &mut segments).map_err(|m| error(&m))?;
+                }
+                c => return Err(error(&format!("unexpected `{}`", c))),
+            }
+        }
+
+        Ok(JsonPath { segments })
+    }
+
+    /// Returns every value matched by the path, in document order.
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut nodes = vec![root];
+
+        for segment in &self.segments {
+            let mut next = Vec::new();
+
+            for node in nodes {
+                match segment {
+                    Segment::Keys(keys) => {
+                        next.extend(keys.iter().filter_map(|key| node.get(key)));
+                    }
+                    Segment::Indices(indices) => {
+                        if let Value::Array(items) = node {
+                            next.extend(
+                                indices
+                                    .iter()
+                                    .filter_map(|i| resolve_index(*i, items.len()))
+                                    .map(|i| &items[i]),
+                            );
+                        }
+                    }
+                    Segment::Wildcard => match node {
+                        Value::Array(items) => next.extend(items.iter()),
+                        Value::Object(map) => next.extend(map.values()),
+                        _ => {}
+                    },
+                    Segment::Slice(start, end, step) => {
+                        if let Value::Array(items) = node {
+                            next.extend(slice(items, *start, *end, *step));
+                        }
+                    }
+                    Segment::Filter(filter) => match node {
+                        Value::Array(items) => {
+                            next.extend(items.iter().filter(|item| filter.matches(item)))
+                        }
+                        Value::Object(map) => {
+                            next.extend(map.values().filter(|item| filter.matches(item)))
+                        }
+                        _ => {}
+                    },
+                    Segment::Descendants => collect_descendants(node, &mut next),
+                }
+            }
+
+            nodes = next;
+        }
+
+        nodes
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Filter::Or(filters) => filters.iter().any(|f| f.matches(value)),
+            Filter::And(filters) => filters.iter().all(|f| f.matches(value)),
+            Filter::Exists(path) => path.select(value).into_iter().any(is_truthy),
+            Filter::Compare(path, operator, literal) => path
+                .select(value)
+                .into_iter()
+                .any(|found| compare(found, *operator, literal)),
+        }
+    }
+}
+
+/// Formats matched values for saving: strings without quotes, other scalars and objects
+/// as compact JSON. Multiple matches are joined with `join` or saved as a JSON array.
+pub fn format_matches(matches: &[&Value], join: Option<&str>) -> Option<String> {
+    match (matches, join) {
+        ([], _) => None,
+        ([single], _) => Some(format_value(single)),
+        (many, Some(separator)) => Some(
+            many.iter()
+                .map(|v| format_value(v))
+                .collect::<Vec<_>>()
+                .join(separator),
+        ),
+        (many, None) => {
+            let values: Vec<Value> = many.iter().map(|v| (*v).clone()).collect();
+            Some(Value::Array(values).to_string())
+        }
+    }
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_member(
+    chars: &[char],
+    start: usize,
+    segments: &mut Vec<Segment>,
+) -> Result<usize, String> {
+    let mut end = start;
+    while end < chars.len() && chars[end] != '.' && chars[end] != '[' {
+        end += 1;
+    }
+
+    let name: String = chars[start..end].iter().collect();
+    if name.is_empty() {
+        return Err("empty member name".to_string());
+    }
+
+    segments.push(if name == "*" {
+        Segment::Wildcard
+    } else {
+        Segment::Keys(vec![name])
+    });
+
+    Ok(end)
+}
+
+fn find_closing_bracket(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+
+    for (i, c) in chars.iter().enumerate().skip(open) {
+        match (quote, c) {
+            (Some(q), c) if *c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(*c),
+            (None, '[') => depth += 1,
+            (None, ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn parse_bracket(inner: &str) -> Result<Segment, String> {
+    if inner == "*" {
+        return Ok(Segment::Wildcard);
+    }
+
+    if let Some(expr) = inner.strip_prefix('?') {
+        let expr = expr.trim();
+        let expr = expr
+            .strip_prefix('(')
+            .and_then(|e| e.strip_suffix(')'))
+            .unwrap_or(expr);
+        return parse_filter(expr.trim()).map(Segment::Filter);
+    }
+
+    let parts = split_outside_quotes(inner, ",");
+
+    if parts.iter().all(|p| is_quoted(p.trim())) {
+        return Ok(Segment::Keys(
+            parts.iter().map(|p| unquote(p.trim())).collect(),
+        ));
+    }
+
+    if inner.contains(':') {
+        let bounds: Vec<&str> = inner.split(':').collect();
+        if bounds.len() > 3 {
+            return Err(format!("invalid slice `{}`", inner));
+        }
+
+        let bound = |i: usize| -> Result<Option<i64>, String> {
+            match bounds.get(i).map(|b| b.trim()) {
+                None | Some("") => Ok(None),
+                Some(b) => b
+                    .parse::<i64>()
+                    .map(Some)
+                    .map_err(|_| format!("invalid slice bound `{}`", b)),
+            }
+        };
+
+        return Ok(Segment::Slice(bound(0)?, bound(1)?, bound(2)?));
+    }
+
+    let indices: Result<Vec<i64>, _> = parts.iter().map(|p| p.trim().parse::<i64>()).collect();
+    match indices {
+        Ok(indices) => Ok(Segment::Indices(indices)),
+        // Bare names such as `[name]` are treated as keys
+        Err(_) => Ok(Segment::Keys(
+            parts.iter().map(|p| p.trim().to_string()).collect(),
+        )),
+    }
+}
+
+fn parse_filter(expr: &str) -> Result<Filter, String> {
+    let ors = split_outside_quotes(expr, "||");
+    if ors.len() > 1 {
+        return ors
+            .iter()
+            .map(|e| parse_filter(e.trim()))
+            .collect::<Result<_, _>>()
+            .map(Filter::Or);
+    }
+
+    let ands = split_outside_quotes(expr, "&&");
+    if ands.len() > 1 {
+        return ands
+            .iter()
+            .map(|e| parse_filter(e.trim()))
+            .collect::<Result<_, _>>()
+            .map(Filter::And);
+    }
+
+    let expr = expr
+        .strip_prefix('(')
+        .and_then(|e| e.strip_suffix(')'))
+        .unwrap_or(expr)
+        .trim();
+
+    // Longest operators first so `<=` is not read as `<`
+    let operators = [
+        ("==", Operator::Eq),
+        ("!=", Operator::Ne),
+        ("<=", Operator::Le),
+        (">=", Operator::Ge),
+        ("<", Operator::Lt),
+        (">", Operator::Gt),
+    ];
+
+    for (token, operator) in operators {
+        let parts = split_outside_quotes(expr, token);
+        if parts.len() == 2 {
+            let path = parse_relative_path(parts[0].trim())?;
+            let literal = parse_literal(parts[1].trim())?;
+            return Ok(Filter::Compare(path, operator, literal));
+        }
+    }
+
+    Ok(Filter::Exists(parse_relative_path(expr)?))
+}
+
+fn parse_relative_path(expr: &str) -> Result<JsonPath, String> {
+    if !expr.starts_with('@') {
+        return Err(format!(
+            "filter paths must start with `@`, found `{}`",
+            expr
+        ));
+    }
+
+    JsonPath::parse(expr).map_err(|e| e.to_string())
+}
+
+fn parse_literal(literal: &str) -> Result<Value, String> {
+    if is_quoted(literal) {
+        return Ok(Value::String(unquote(literal)));
+    }
+
+    serde_json::from_str(literal).map_err(|_| format!("invalid literal `{}`", literal))
+}
+
+fn is_quoted(s: &str) -> bool {
+    s.len() >= 2
+        && ((s.starts_with('\'') && s.ends_with('\'')) || (s.starts_with('"') && s.ends_with('"')))
+}
+
+fn unquote(s: &str) -> String {
+    s[1..s.len() - 1].to_string()
+}
+
+/// Splits on `separator` while ignoring separators inside quotes.
+fn split_outside_quotes<'a>(s: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < s.len() {
+        let c = s[i..].chars().next().unwrap();
+
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if s[i..].starts_with(separator) => {
+                parts.push(&s[start..i]);
+                i += separator.len();
+                start = i;
+                continue;
+            }
+            None => {}
+        }
+
+        i += c.len_utf8();
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let resolved = if index < 0 { len + index } else { index };
+
+    if resolved >= 0 && resolved < len {
+        Some(resolved as usize)
+    } else {
+        None
+    }
+}
+
+fn slice(items: &[Value], start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<&Value> {
+    let len = items.len() as i64;
+    let step = step.unwrap_or(1);
+
+    if step == 0 {
+        return Vec::new();
+    }
+
+    let clamp = |i: i64| -> i64 {
+        let i = if i < 0 { len + i } else { i };
+        i.clamp(0, len)
+    };
+
+    let mut selected = Vec::new();
+
+    if step > 0 {
+        let mut i = clamp(start.unwrap_or(0));
+        let end = clamp(end.unwrap_or(len));
+        while i < end {
+            selected.push(&items[i as usize]);
+            i += step;
+        }
+    } else {
+        let mut i = start.map(clamp).unwrap_or(len).min(len - 1);
+        let end = end.map(clamp).unwrap_or(-1);
+        while i > end && i >= 0 {
+            selected.push(&items[i as usize]);
+            i += step;
+        }
+    }
+
+    selected
+}
+
+fn collect_descendants<'a>(node: &'a Value, out: &mut Vec<&'a Value>) {
+    out.push(node);
+
+    match node {
+        Value::Array(items) => items.iter().for_each(|v| collect_descendants(v, out)),
+        Value::Object(map) => map.values().for_each(|v| collect_descendants(v, out)),
+        _ => {}
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn compare(found: &Value, operator: Operator, literal: &Value) -> bool {
+    let ordering = match (found, literal) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+
+    match operator {
+        Operator::Eq => found == literal || ordering == Some(std::cmp::Ordering::Equal),
+        Operator::Ne => found != literal && ordering != Some(std::cmp::Ordering::Equal),
+        Operator::Lt => ordering == Some(std::cmp::Ordering::Less),
+        Operator::Le => matches!(
+            ordering,
+            Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)
+        ),
+        Operator::Gt => ordering == Some(std::cmp::Ordering::Greater),
+        Operator::Ge => matches!(
+            ordering,
+            Some(std::cmp::Ordering::Greater | std::cmp::Ordering::Equal)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "data": {
+                "users": [
+                    {"id": 1, "name": "ada", "email": "ada@example.com", "admin": true},
+                    {"id": 2, "name": "bob", "email": "bob@example.com", "admin": false},
+                    {"id": 3, "name": "cy", "email": null}
+                ],
+                "meta": {"total": 3, "page.size": 10}
+            }
+        })
+    }
+
+    fn select(path: &str) -> Vec<Value> {
+        let root = sample();
+        JsonPath::parse(path)
+            .unwrap()
+            .select(&root)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn selects_members_and_indices() {
+        assert_eq!(select("data.meta.total"), vec![json!(3)]);
+        assert_eq!(select("$.data.users[0].name"), vec![json!("ada")]);
+        assert_eq!(select("data.users[-1].name"), vec![json!("cy")]);
+        assert_eq!(select("data.users[0,2].id"), vec![json!(1), json!(3)]);
+        assert_eq!(select("data.meta['page.size']"), vec![json!(10)]);
+        assert_eq!(select("data.meta[\"total\"]"), vec![json!(3)]);
+        assert!(select("data.users[7]").is_empty());
+        assert!(select("data.missing").is_empty());
+    }
+
+    #[test]
+    fn selects_wildcards() {
+        assert_eq!(
+            select("data.users[*].id"),
+            vec![json!(1), json!(2), json!(3)]
+        );
+        assert_eq!(
+            select("data.users.*.name"),
+            vec![json!("ada"), json!("bob"), json!("cy")]
+        );
+        assert_eq!(select("data.meta.*").len(), 2);
+    }
+
+    #[test]
+    fn selects_slices() {
+        assert_eq!(select("data.users[1:].id"), vec![json!(2), json!(3)]);
+        assert_eq!(select("data.users[:2].id"), vec![json!(1), json!(2)]);
+        assert_eq!(select("data.users[::2].id"), vec![json!(1), json!(3)]);
+        assert_eq!(select("data.users[-2:].id"), vec![json!(2), json!(3)]);
+        assert_eq!(
+            select("data.users[::-1].id"),
+            vec![json!(3), json!(2), json!(1)]
+        );
+        assert!(select("data.users[::0]").is_empty());
+    }
+
+    #[test]
+    fn selects_recursive_descent() {
+        assert_eq!(
+            select("$..name"),
+            vec![json!("ada"), json!("bob"), json!("cy")]
+        );
+        assert_eq!(select("$..total"), vec![json!(3)]);
+        assert_eq!(select("data..[0].id"), vec![json!(1)]);
+    }
+
+    #[test]
+    fn selects_with_filters() {
+        assert_eq!(
+            select("data.users[?(@.id > 1)].name"),
+            vec![json!("bob"), json!("cy")]
+        );
+        assert_eq!(select("data.users[?(@.name == 'bob')].id"), vec![json!(2)]);
+        assert_eq!(
+            select("data.users[?(@.id <= 2 && @.admin == false)].name"),
+            vec![json!("bob")]
+        );
+        assert_eq!(
+            select("data.users[?(@.id == 1 || @.id == 3)].name"),
+            vec![json!("ada"), json!("cy")]
+        );
+        assert_eq!(
+            select("data.users[?(@.email)].id"),
+            vec![json!(1), json!(2)]
+        );
+        assert_eq!(select("data.users[?(@.admin)].name"), vec![json!("ada")]);
+        assert_eq!(
+            select("data.users[?(@.name != 'ada')].id"),
+            vec![json!(2), json!(3)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for path in [
+            "data.users[0",
+            "data..",
+            "data.users[?(id > 1)]",
+            "data.users[?(@.id > nope)]",
+            "data.users[1:2:3:4]",
+            "data.users[a:b]",
+            "data.users[0]x",
+        ] {
+            assert!(
+                matches!(JsonPath::parse(path), Err(JsonPathError::Parse(..))),
+                "expected `{}` to be rejected",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn formats_matches() {
+        let (a, b) = (json!("x"), json!({"k": 1}));
+        assert_eq!(format_matches(&[], None), None);
+        assert_eq!(format_matches(&[&a], None), Some("x".to_string()));
+        assert_eq!(format_matches(&[&b], None), Some(r#"{"k":1}"#.to_string()));
+        assert_eq!(
+            format_matches(&[&a, &b], Some(",")),
+            Some(r#"x,{"k":1}"#.to_string())
+        );
+        assert_eq!(
+            format_matches(&[&a, &b], None),
+            Some(r#"["x",{"k":1}]"#.to_string())
+        );
+    }
+}
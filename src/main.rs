@@ -1,6 +1,8 @@
 use app::App;
 use clap::Parser;
 use cli::Cli;
+use cli::command::CliCommandError;
+use config::Config;
 use flowlet_context::FlowletContext;
 use printer::{Icon, Printer};
 use util::FlowletResult;
@@ -8,20 +10,45 @@ use util::FlowletResult;
 pub mod api_client;
 pub mod app;
 pub mod cli;
+pub mod config;
+pub mod discover;
+pub mod extract;
 pub mod flowlet_context;
 pub mod flowlet_db;
+pub mod json_path;
 pub mod printer;
+pub mod project_file;
+pub mod runner;
+pub mod secrets;
+pub mod shell;
+pub mod sync;
+pub mod tui;
 pub mod util;
 
 #[tokio::main]
 async fn main() -> FlowletResult<()> {
     pretty_env_logger::init();
-    let ctx = FlowletContext::new().await?;
+    let mut cli = Cli::parse();
+
+    // Flags win over `FLOWLET_*` variables, which win over the config file
+    let global = std::mem::take(&mut cli.global);
+    let config = Config::load()?
+        .merge(Config::from_env().validated()?)
+        .merge(global.into_config().validated()?);
+    config.apply_color();
+    config.apply_output();
+
+    let ctx = FlowletContext::new(config).await?;
     let app = App { ctx: &ctx };
-    let cli = Cli::parse();
 
     if let Err(e) = app.run(cli).await {
         Printer::error(Icon::Error, "Error", &e.to_string());
+
+        // Mirror the exit code of a failed command so scripts can rely on it
+        let code = e
+            .downcast_ref::<CliCommandError>()
+            .map_or(1, CliCommandError::exit_code);
+        std::process::exit(code);
     }
 
     Ok(())
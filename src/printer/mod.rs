@@ -1,8 +1,45 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use colored::*;
 use prettytable::{Cell, Row, Table};
 use regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::util::FlowletResult;
+use similar::{ChangeTag, TextDiff};
 use unicode_width::UnicodeWidthStr;
 
+/// How results are written to stdout, set by `--output`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Tables and colored status lines.
+    #[default]
+    Table,
+    /// Results as JSON, status lines on stderr.
+    Json,
+    /// Results as YAML, status lines on stderr.
+    Yaml,
+    /// Tab separated rows without headers, status lines on stderr.
+    Plain,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "table" => Some(OutputFormat::Table),
+            "json" => Some(OutputFormat::Json),
+            "yaml" => Some(OutputFormat::Yaml),
+            "plain" => Some(OutputFormat::Plain),
+            _ => None,
+        }
+    }
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+static QUIET: AtomicBool = AtomicBool::new(false);
+
 pub enum Icon {
     Trash,
     Cloud,
@@ -16,6 +53,7 @@ pub enum Icon {
     Rocket,
     Project,
     Task,
+    Workflow,
 }
 
 impl Icon {
@@ -33,6 +71,7 @@ impl Icon {
             Icon::Rocket => "🚀",
             Icon::Project => "📁",
             Icon::Task => "📌",
+            Icon::Workflow => "🔗",
         }
     }
 
@@ -49,6 +88,7 @@ impl Icon {
             Icon::Auth => Color::Magenta,
             Icon::Project => Color::Blue,
             Icon::Task => Color::BrightGreen,
+            Icon::Workflow => Color::BrightCyan,
         }
     }
 
@@ -60,7 +100,8 @@ impl Icon {
             | Icon::Auth
             | Icon::Rocket
             | Icon::Project
-            | Icon::Task => {
+            | Icon::Task
+            | Icon::Workflow => {
                 format!("{}  ", self.symbol())
             } // 2 spaces
             Icon::Warning | Icon::Failure | Icon::Error => format!("{}  ", self.symbol()),
@@ -72,16 +113,69 @@ impl Icon {
 pub struct Printer;
 
 impl Printer {
+    /// Sets the output format for the rest of the process. Only the first call counts.
+    pub fn set_format(format: OutputFormat) {
+        let _ = FORMAT.set(format);
+    }
+
+    pub fn format() -> OutputFormat {
+        FORMAT.get().copied().unwrap_or_default()
+    }
+
+    /// Writes `value` to stdout when the output format is JSON or YAML, returning whether
+    /// it did. Callers fall back to their table or detail view otherwise.
+    pub fn serialized<T: Serialize + ?Sized>(value: &T) -> FlowletResult<bool> {
+        match Self::format() {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+            OutputFormat::Table | OutputFormat::Plain => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Silences status and error lines, for while the TUI owns the terminal.
+    pub fn set_quiet(quiet: bool) {
+        QUIET.store(quiet, Ordering::Relaxed);
+    }
+
+    fn is_quiet() -> bool {
+        QUIET.load(Ordering::Relaxed)
+    }
+
+    /// Status lines go to stderr unless output is meant for people, so stdout stays
+    /// parseable.
+    fn chatter(line: &str) {
+        if Self::is_quiet() {
+            return;
+        }
+
+        Self::human(line);
+    }
+
+    /// Like [`Printer::chatter`], but also shown when quiet, for output a prompt needs.
+    fn human(line: &str) {
+        if Self::format() == OutputFormat::Table {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+
     pub fn success(icon: Icon, label: &str, message: &str) {
-        println!(
+        Self::chatter(&format!(
             "{}{} {}",
             icon.formatted(),
             pad_colored(label.color(icon.color()).bold(), 12),
             message
-        );
+        ));
     }
 
     pub fn error(icon: Icon, label: &str, message: &str) {
+        if Self::is_quiet() {
+            return;
+        }
+
         eprintln!(
             "{}{} {}",
             icon.formatted(),
@@ -91,35 +185,60 @@ impl Printer {
     }
 
     pub fn warning(icon: Icon, label: &str, message: &str) {
-        println!(
+        Self::chatter(&format!(
             "{}{} {}",
             icon.formatted(),
             pad_colored(label.color(icon.color()).bold(), 12),
             message
-        );
+        ));
     }
 
     pub fn info(icon: Icon, label: &str, message: &str) {
-        println!(
+        Self::chatter(&format!(
             "{}{} {}",
             icon.formatted(),
             pad_colored(label.color(icon.color()).bold(), 12),
             message
-        );
+        ));
     }
 
     pub fn field(icon: Icon, label: &str, value: &str) {
         let pad = 14;
-        println!(
+        Self::chatter(&format!(
             "{}{}{}{}",
             icon.formatted(),
             label.bold(),
             ":".bold(),
             format!("{:>width$}", value, width = pad - label.len())
-        );
+        ));
     }
 
     pub fn table(headers: Vec<&str>, rows: Vec<Vec<String>>) {
+        match Self::format() {
+            OutputFormat::Table => {}
+            OutputFormat::Plain => {
+                for row in rows {
+                    println!("{}", row.join("\t"));
+                }
+                return;
+            }
+            // Views without a model behind them are written as one object per row
+            OutputFormat::Json | OutputFormat::Yaml => {
+                let objects: Vec<Map<String, Value>> = rows
+                    .into_iter()
+                    .map(|row| {
+                        headers
+                            .iter()
+                            .map(|h| h.to_lowercase().replace(' ', "_"))
+                            .zip(row.into_iter().map(Value::String))
+                            .collect()
+                    })
+                    .collect();
+                let _ = Self::serialized(&objects);
+                return;
+            }
+        }
+
         let mut table = Table::new();
 
         // Headers
@@ -141,6 +260,14 @@ impl Printer {
     }
 
     pub fn multi_line_info(label: &str, lines: Vec<&str>) {
+        if Self::format() != OutputFormat::Table {
+            Self::human(label);
+            for line in lines {
+                Self::human(line);
+            }
+            return;
+        }
+
         println!("\n{}", label.bold());
         for line in lines {
             println!("{}", line.bright_white().bold());
@@ -149,6 +276,10 @@ impl Printer {
     }
 
     pub fn multi_line_info_with_icon(icon: Icon, label: &str, lines: Vec<&str>) {
+        if Self::format() != OutputFormat::Table {
+            return Self::multi_line_info(label, lines);
+        }
+
         println!(
             "\n{} {}\n",
             icon.formatted(),
@@ -160,11 +291,49 @@ impl Printer {
         println!();
     }
 
+    /// Prints a line diff from `old` to `new`, removed lines in red and added in green.
+    pub fn diff(old_label: &str, new_label: &str, old: &str, new: &str) {
+        Self::human(&format!("--- {}", old_label).red().bold().to_string());
+        Self::human(&format!("+++ {}", new_label).green().bold().to_string());
+
+        for change in TextDiff::from_lines(old, new).iter_all_changes() {
+            let line = change.to_string_lossy();
+            let line = line.trim_end_matches('\n');
+            match change.tag() {
+                ChangeTag::Delete => Self::human(&format!("-{}", line).red().to_string()),
+                ChangeTag::Insert => Self::human(&format!("+{}", line).green().to_string()),
+                ChangeTag::Equal => Self::human(&format!(" {}", line)),
+            }
+        }
+        Self::human("");
+    }
+
     pub fn block_kv(title: &str, kvs: &[(&str, String)]) {
-        use colored::Colorize;
-        use unicode_width::UnicodeWidthStr;
+        match Self::format() {
+            OutputFormat::Table => {}
+            OutputFormat::Plain => {
+                for (key, value) in kvs {
+                    println!("{}\t{}", key, value);
+                }
+                return;
+            }
+            OutputFormat::Json | OutputFormat::Yaml => {
+                let object: Map<String, Value> = kvs
+                    .iter()
+                    .map(|(k, v)| (k.to_lowercase().replace(' ', "_"), Value::String(v.clone())))
+                    .collect();
+                let _ = Self::serialized(&object);
+                return;
+            }
+        }
 
-        println!("\n{}", title.bold().underline());
+        Self::details_kv(title, kvs);
+    }
+
+    /// Key-value details that are not the output of a command, such as a plan to confirm.
+    /// Shown like [`Printer::block_kv`] in table mode and on stderr otherwise.
+    pub fn details_kv(title: &str, kvs: &[(&str, String)]) {
+        Self::human(&format!("\n{}", title.bold().underline()));
 
         let max_key_width = kvs
             .iter()
@@ -174,14 +343,14 @@ impl Printer {
 
         for (key, value) in kvs {
             let padded_key = format!("{:width$}", key, width = max_key_width);
-            println!(
+            Self::human(&format!(
                 "{} {}",
                 padded_key.bright_blue().bold(),
                 value.bright_white()
-            );
+            ));
         }
 
-        println!();
+        Self::human("");
     }
 }
 
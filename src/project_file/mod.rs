@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+use crate::{
+    flowlet_db::{
+        metadata::Metadata,
+        models::{
+            command::{Command, CommandOptions},
+            workflow::{StepRun, Workflow, WorkflowStep},
+        },
+    },
+    util::{FlowletResult, find_project_file},
+};
+
+#[derive(Debug, Error)]
+pub enum ProjectFileError {
+    #[error("Failed to parse `{0}`: {1}")]
+    ParseFailed(String, String),
+
+    #[error("Step {1} of workflow `{0}` needs exactly one of `command` or `shell`.")]
+    InvalidStep(String, usize),
+
+    #[error("Variable `{0}` in `flowlet.toml` must be a string, number or boolean.")]
+    InvalidVariable(String),
+}
+
+/// Commands, variables and workflows declared in `flowlet.toml`, so they can be checked
+/// in with the project.
+///
+/// ```toml
+/// [commands.test]
+/// cmd = "cargo test ${filter:-}"
+/// description = "Run the test suite"
+///
+/// [vars]
+/// region = "eu-west-1"
+///
+/// [[workflows.ci.steps]]
+/// command = "test"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct ProjectFile {
+    /// Where the file was read from.
+    #[serde(skip)]
+    pub path: PathBuf,
+
+    #[serde(default)]
+    pub project: Option<ProjectSection>,
+
+    #[serde(default)]
+    pub commands: BTreeMap<String, CommandSpec>,
+
+    /// Values used for placeholders with no project scoped variable saved.
+    #[serde(default, deserialize_with = "scalars")]
+    pub vars: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub workflows: BTreeMap<String, WorkflowSpec>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ProjectSection {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A `[commands.<name>]` table.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct CommandSpec {
+    pub cmd: String,
+
+    #[serde(flatten)]
+    pub options: CommandOptions,
+}
+
+/// A `[workflows.<name>]` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkflowSpec {
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub steps: Vec<StepSpec>,
+}
+
+/// A `[[workflows.<name>.steps]]` entry, running either a command or a shell snippet.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StepSpec {
+    /// Defaults to the command name, or `step <n>` for shell steps.
+    pub name: Option<String>,
+    pub command: Option<String>,
+    pub shell: Option<String>,
+    pub save_var: Option<String>,
+    pub json_path: Option<String>,
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+impl ProjectFile {
+    /// Reads the nearest `flowlet.toml`, if there is one.
+    pub fn load() -> FlowletResult<Option<Self>> {
+        let path = match find_project_file()? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let contents = fs::read_to_string(&path)?;
+
+        let mut file: ProjectFile = toml::from_str(&contents).map_err(|e| {
+            ProjectFileError::ParseFailed(path.display().to_string(), e.message().to_string())
+        })?;
+        file.path = path;
+
+        Ok(Some(file))
+    }
+
+    pub fn project_name(&self) -> Option<String> {
+        self.project.as_ref().and_then(|p| p.name.clone())
+    }
+
+    /// The declared command as a record, which is not saved anywhere.
+    pub fn command(&self, name: &str, author: Option<&str>) -> Option<Command> {
+        self.commands
+            .get(name)
+            .map(|spec| spec.to_command(name, self.project_name(), author))
+    }
+
+    /// The declared workflow as a record, which is not saved anywhere.
+    pub fn workflow(&self, name: &str, author: Option<&str>) -> FlowletResult<Option<Workflow>> {
+        let Some(spec) = self.workflows.get(name) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Workflow {
+            _id: ulid::Ulid::new(),
+            name: name.to_string(),
+            description: spec.description.clone(),
+            project: self.project_name(),
+            steps: spec.steps(name)?,
+            metadata: Metadata::new(author),
+        }))
+    }
+}
+
+impl CommandSpec {
+    pub fn to_command(&self, name: &str, project: Option<String>, author: Option<&str>) -> Command {
+        Command {
+            _id: ulid::Ulid::new(),
+            name: name.to_string(),
+            cmd: self.cmd.clone(),
+            project,
+            options: self.options.clone(),
+            metadata: Metadata::new(author),
+        }
+    }
+
+    /// Whether `command` already matches this declaration.
+    pub fn matches(&self, command: &Command) -> bool {
+        self.cmd == command.cmd && self.options == command.options
+    }
+}
+
+impl WorkflowSpec {
+    pub fn steps(&self, workflow: &str) -> FlowletResult<Vec<WorkflowStep>> {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let run = match (&step.command, &step.shell) {
+                    (Some(command), None) => StepRun::Command(command.clone()),
+                    (None, Some(shell)) => StepRun::Shell(shell.clone()),
+                    _ => {
+                        return Err(Box::new(ProjectFileError::InvalidStep(
+                            workflow.to_string(),
+                            i + 1,
+                        )) as Box<dyn std::error::Error>);
+                    }
+                };
+
+                let name = step.name.clone().unwrap_or_else(|| match &run {
+                    StepRun::Command(command) => command.clone(),
+                    StepRun::Shell(_) => format!("step {}", i + 1),
+                });
+
+                Ok(WorkflowStep {
+                    name,
+                    run,
+                    save_var: step.save_var.clone(),
+                    json_path: step.json_path.clone(),
+                    continue_on_error: step.continue_on_error,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `[vars]` values may be written as numbers or booleans, they are used as text.
+fn scalars<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = BTreeMap::<String, toml::Value>::deserialize(deserializer)?;
+
+    raw.into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => {
+                    return Err(serde::de::Error::custom(ProjectFileError::InvalidVariable(
+                        name,
+                    )));
+                }
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
@@ -0,0 +1,382 @@
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use tempfile::TempPath;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::{secrets::mask_secrets, util::FlowletResult};
+
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("Failed to spawn process.")]
+    SpawnFailed,
+
+    #[error("Failed to wait for process.")]
+    WaitFailed,
+
+    #[error("Interpreter `{0}` not found.")]
+    InterpreterNotFound(String),
+}
+
+/// The collected result of a streamed process.
+pub struct RunOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+    /// Whether the process was killed for running past its timeout.
+    pub timed_out: bool,
+}
+
+impl RunOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// Where the output of a running process is forwarded while it is collected.
+#[derive(Clone, Default)]
+pub enum OutputSink {
+    /// The terminal Flowlet runs in, with stdin inherited.
+    #[default]
+    Terminal,
+
+    /// A channel receiving each line of both streams, for callers that own the terminal
+    /// such as the TUI. Stdin is closed and the process is killed if the run is dropped.
+    Channel(UnboundedSender<String>),
+}
+
+/// Which stream of the child a line was read from.
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Exit code reported for a process killed by its timeout, as with `timeout(1)`.
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// How long a timed out process has to exit after `SIGTERM` before it is killed.
+const KILL_GRACE: Duration = Duration::from_secs(2);
+
+/// What woke up a run waiting for its process.
+enum Wake {
+    Exited(std::io::Result<ExitStatus>),
+    Expired,
+    Interrupted,
+}
+
+/// Spawns the process, forwarding stdout/stderr to the terminal line by line while
+/// also collecting both streams so callers can inspect the output afterwards.
+///
+/// Stdin is inherited so interactive commands keep working. Ctrl-C is delivered by the
+/// terminal to the whole foreground process group, so Flowlet only swallows the signal
+/// itself and keeps waiting for the child to decide how to exit.
+///
+/// With a `timeout`, the process leads its own process group so everything it started
+/// can be stopped: the group gets `SIGTERM` when the time is up and `SIGKILL` if it is
+/// still running shortly after. Such a group no longer gets Ctrl-C from the terminal, so
+/// it is forwarded. A group outside the foreground cannot read the terminal either, so
+/// stdin is only closed when it is one; piped input still reaches the process.
+///
+/// Any of `secrets` appearing in the output is masked on the terminal. The collected
+/// output is left as is so values can still be extracted from it.
+pub async fn run_streaming(
+    command: &mut tokio::process::Command,
+    secrets: &[String],
+    sink: OutputSink,
+    timeout: Option<Duration>,
+) -> FlowletResult<RunOutput> {
+    let stdin = match (&sink, timeout) {
+        (OutputSink::Channel(_), _) => {
+            command.kill_on_drop(true);
+            Stdio::null()
+        }
+        // Reading the terminal would stop the group with `SIGTTIN` until the timeout
+        (OutputSink::Terminal, Some(_)) if std::io::stdin().is_terminal() => Stdio::null(),
+        (OutputSink::Terminal, Some(_)) => Stdio::inherit(),
+        (OutputSink::Terminal, None) => Stdio::inherit(),
+    };
+
+    #[cfg(unix)]
+    if timeout.is_some() {
+        command.process_group(0);
+    }
+
+    command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| {
+        log::error!("Failed to spawn process: {:?}", e);
+        RunnerError::SpawnFailed
+    })?;
+
+    // The group keeps the id of its leader after the leader exits
+    let group = timeout.and(child.id());
+
+    let stdout = child.stdout.take().ok_or(RunnerError::SpawnFailed)?;
+    let stderr = child.stderr.take().ok_or(RunnerError::SpawnFailed)?;
+
+    let stdout_task = tee(stdout, Stream::Stdout, sink.clone(), secrets.to_vec());
+    let stderr_task = tee(stderr, Stream::Stderr, sink, secrets.to_vec());
+
+    let mut deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut timed_out = false;
+
+    let status = loop {
+        let expired = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let wake = tokio::select! {
+            status = child.wait() => Wake::Exited(status),
+            _ = expired => Wake::Expired,
+            _ = tokio::signal::ctrl_c() => Wake::Interrupted,
+        };
+
+        match wake {
+            Wake::Exited(status) => {
+                break status.map_err(|e| {
+                    log::error!("Failed to wait for process: {:?}", e);
+                    RunnerError::WaitFailed
+                })?;
+            }
+            Wake::Expired if !timed_out => {
+                log::debug!("Timed out, stopping the process group.");
+                timed_out = true;
+
+                // Without process groups only the process itself can be stopped
+                #[cfg(unix)]
+                signal_group(group, libc::SIGTERM);
+                #[cfg(not(unix))]
+                let _ = child.start_kill();
+
+                deadline = Some(Instant::now() + KILL_GRACE);
+            }
+            Wake::Expired => {
+                kill(&mut child, group);
+                deadline = None;
+            }
+            Wake::Interrupted => {
+                log::debug!("Received Ctrl-C, waiting for child to exit.");
+                #[cfg(unix)]
+                signal_group(group, libc::SIGINT);
+            }
+        }
+    };
+
+    // Processes left in the group would keep the output pipes open
+    if timed_out {
+        kill(&mut child, group);
+    }
+
+    let stdout = stdout_task.await.unwrap_or_default();
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    Ok(RunOutput {
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        exit_code: if timed_out {
+            TIMEOUT_EXIT_CODE
+        } else {
+            exit_code(&status)
+        },
+        timed_out,
+    })
+}
+
+/// Kills a process and everything it started.
+fn kill(child: &mut tokio::process::Child, group: Option<u32>) {
+    #[cfg(unix)]
+    signal_group(group, libc::SIGKILL);
+    #[cfg(not(unix))]
+    let _ = group;
+
+    let _ = child.start_kill();
+}
+
+/// Sends `signal` to every process in the group led by `group`.
+#[cfg(unix)]
+fn signal_group(group: Option<u32>, signal: libc::c_int) {
+    let Some(group) = group else {
+        return;
+    };
+
+    // SAFETY: `kill` only sends a signal, a group that is gone makes it fail with ESRCH
+    unsafe {
+        libc::kill(-(group as libc::pid_t), signal);
+    }
+}
+
+/// Copies a child stream to `sink` one line at a time, returning everything read.
+fn tee<R>(reader: R, stream: Stream, sink: OutputSink, secrets: Vec<String>) -> JoinHandle<Vec<u8>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        let mut collected = Vec::new();
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) => break,
+                Ok(_) => {
+                    let forwarded = if secrets.is_empty() {
+                        line.clone()
+                    } else {
+                        mask_secrets(&String::from_utf8_lossy(&line), &secrets).into_bytes()
+                    };
+                    forward(&forwarded, stream, &sink).await;
+                    collected.extend_from_slice(&line);
+                }
+                Err(e) => {
+                    log::error!("Failed to read process output: {:?}", e);
+                    break;
+                }
+            }
+        }
+
+        collected
+    })
+}
+
+async fn forward(line: &[u8], stream: Stream, sink: &OutputSink) {
+    match (sink, stream) {
+        (OutputSink::Terminal, Stream::Stdout) => {
+            let mut stdout = tokio::io::stdout();
+            let _ = stdout.write_all(line).await;
+            let _ = stdout.flush().await;
+        }
+        (OutputSink::Terminal, Stream::Stderr) => {
+            let mut stderr = tokio::io::stderr();
+            let _ = stderr.write_all(line).await;
+            let _ = stderr.flush().await;
+        }
+        (OutputSink::Channel(sender), _) => {
+            let line = String::from_utf8_lossy(line);
+            let _ = sender.send(line.trim_end_matches(['\r', '\n']).to_string());
+        }
+    }
+}
+
+/// A program running the text of saved commands, such as `sh`, `python3` or `node`.
+pub struct Interpreter {
+    pub program: PathBuf,
+}
+
+impl Interpreter {
+    /// Looks `name` up on the `PATH`, or checks it when it is a path.
+    pub fn find(name: &str) -> FlowletResult<Self> {
+        let program =
+            which::which(name).map_err(|_| RunnerError::InterpreterNotFound(name.to_string()))?;
+
+        Ok(Self { program })
+    }
+
+    /// The flag taking code to run inline, and whether the arguments following the code
+    /// start with `$0` as in POSIX shells. `None` when the interpreter is not known to
+    /// take inline code.
+    fn inline_flag(&self) -> Option<(&'static str, bool)> {
+        // `python3.12` and `python3` are both `python`
+        let name = self.program.file_stem()?.to_str()?;
+
+        match name.trim_end_matches(|c: char| c.is_ascii_digit()) {
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "mksh" | "ash" => Some(("-c", true)),
+            "fish" | "python" | "pypy" => Some(("-c", false)),
+            "node" | "bun" | "deno" | "ruby" | "perl" => Some(("-e", false)),
+            "php" => Some(("-r", false)),
+            "pwsh" | "powershell" => Some(("-Command", false)),
+            _ => None,
+        }
+    }
+}
+
+/// A process running `code`, the text of the command saved as `name`, along with the
+/// temp file it runs from if any. The file is removed when the path is dropped, so it has
+/// to be kept until the process exits.
+///
+/// Code starting with a `#!` line always runs from a file, executed as is. Scripts and
+/// code for interpreters without an inline flag are given a `#!` line running
+/// `interpreter`.
+pub fn prepare(
+    code: &str,
+    name: &str,
+    interpreter: &str,
+    script: bool,
+) -> FlowletResult<(tokio::process::Command, Option<TempPath>)> {
+    let code = code.trim_start();
+
+    if code.starts_with("#!") {
+        let path = write_script(code)?;
+        return Ok((tokio::process::Command::new(&path), Some(path)));
+    }
+
+    let interpreter = Interpreter::find(interpreter)?;
+
+    match interpreter.inline_flag().filter(|_| !script) {
+        Some((flag, takes_name)) => {
+            let mut process = tokio::process::Command::new(&interpreter.program);
+            process.arg(flag).arg(code);
+            if takes_name {
+                process.arg(name);
+            }
+            Ok((process, None))
+        }
+        None => {
+            let body = format!("#!{}\n{}", interpreter.program.display(), code);
+            let path = write_script(&body)?;
+            Ok((tokio::process::Command::new(&path), Some(path)))
+        }
+    }
+}
+
+/// Writes `body` to an executable temp file, removed when the returned path is dropped.
+fn write_script(body: &str) -> FlowletResult<TempPath> {
+    let mut file = tempfile::Builder::new().prefix("flowlet-").tempfile()?;
+
+    file.write_all(body.as_bytes())?;
+    if !body.ends_with('\n') {
+        writeln!(file)?;
+    }
+
+    // The file has to be closed before it can be executed
+    let path = file.into_temp_path();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o700))?;
+    }
+
+    Ok(path)
+}
+
+/// Maps an exit status to a shell style exit code, using `128 + signal` for signals.
+fn exit_code(status: &ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    1
+}
@@ -0,0 +1,289 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use dialoguer::Password;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::util::{FlowletResult, config_dir};
+
+/// Replaces secret values in echoed output and messages.
+pub const MASK: &str = "••••••";
+
+const VERSION: &str = "v1";
+const KEY_FILE: &str = "secret.key";
+const PASSPHRASE_ENV: &str = "FLOWLET_PASSPHRASE";
+
+/// Passphrase entered during this invocation, so each run prompts at most once.
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Failed to encrypt secret.")]
+    EncryptFailed,
+
+    #[error("Failed to decrypt secret. Check the key file or passphrase.")]
+    DecryptFailed,
+
+    #[error("Secret value is not in a recognised format.")]
+    InvalidFormat,
+
+    #[error("Failed to derive key from passphrase.")]
+    KeyDerivationFailed,
+
+    #[error("Key file `{0}` is invalid.")]
+    InvalidKeyFile(String),
+}
+
+/// Where the encryption key of a secret comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    /// A random key stored under the config dir, created on first use.
+    KeyFile,
+    /// A key derived from a passphrase with Argon2. Read from `FLOWLET_PASSPHRASE` or prompted.
+    Passphrase,
+}
+
+/// Encrypts a value into `v1:key:<nonce>:<ciphertext>` or
+/// `v1:pass:<salt>:<nonce>:<ciphertext>`, all parts base64 encoded.
+pub fn encrypt(plaintext: &str, source: KeySource) -> FlowletResult<String> {
+    let (key, header) = match source {
+        KeySource::KeyFile => (load_or_create_key_file()?, "key".to_string()),
+        KeySource::Passphrase => {
+            let salt = ChaCha20Poly1305::generate_key(&mut OsRng)[..16].to_vec();
+            let key = derive_key(&passphrase(true)?, &salt)?;
+            (key, format!("pass:{}", STANDARD.encode(&salt)))
+        }
+    };
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| SecretError::EncryptFailed)?;
+
+    Ok(format!(
+        "{}:{}:{}:{}",
+        VERSION,
+        header,
+        STANDARD.encode(nonce),
+        STANDARD.encode(ciphertext)
+    ))
+}
+
+pub fn decrypt(encrypted: &str) -> FlowletResult<String> {
+    let parts: Vec<&str> = encrypted.split(':').collect();
+
+    let (key, nonce, ciphertext) = match parts.as_slice() {
+        [VERSION, "key", nonce, ciphertext] => (load_key_file()?, nonce, ciphertext),
+        [VERSION, "pass", salt, nonce, ciphertext] => {
+            let salt = decode(salt)?;
+            (derive_key(&passphrase(false)?, &salt)?, nonce, ciphertext)
+        }
+        _ => return Err(Box::new(SecretError::InvalidFormat)),
+    };
+
+    let nonce = decode(nonce)?;
+    if nonce.len() != 12 {
+        return Err(Box::new(SecretError::InvalidFormat));
+    }
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), decode(ciphertext)?.as_ref())
+        .map_err(|_| SecretError::DecryptFailed)?;
+
+    Ok(String::from_utf8(plaintext).map_err(|_| SecretError::DecryptFailed)?)
+}
+
+/// Whether decrypting `encrypted` needs the passphrase.
+pub fn needs_passphrase(encrypted: &str) -> bool {
+    encrypted.starts_with(&format!("{}:pass:", VERSION))
+}
+
+/// Reads the passphrase ahead of time, prompting unless `FLOWLET_PASSPHRASE` is set, so
+/// later calls to [`decrypt`] do not prompt.
+pub fn unlock() -> FlowletResult<()> {
+    passphrase(false).map(|_| ())
+}
+
+/// Replaces every occurrence of the given secret values with [`MASK`].
+pub fn mask_secrets(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|s| !s.is_empty())
+        .fold(text.to_string(), |masked, secret| {
+            masked.replace(secret.as_str(), MASK)
+        })
+}
+
+/// Names of flags and variables that usually hold credentials.
+const SECRET_KEYS: &str = r"[a-z0-9_-]*(?:password|passwd|pwd|token|secret|api[_-]?key|access[_-]?key|private[_-]?key|auth)";
+
+/// A value in a command that looks like a credential.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretCandidate {
+    pub value: String,
+    /// Variable name suggested for the value.
+    pub name: String,
+}
+
+/// Finds likely credentials in a command: `--token x` and `PASSWORD=x` style arguments,
+/// `Authorization` headers, passwords in URLs and well known token formats.
+///
+/// Values that are already placeholders or variable references are skipped.
+pub fn find_secrets(command: &str) -> Vec<SecretCandidate> {
+    let value = r#"(?P<value>"[^"]+"|'[^']+'|[^\s'"]+)"#;
+    // Each pattern captures `value`, and `flag` or `var` when the value is named
+    let patterns = [
+        (
+            format!(
+                r"(?i)(?:--?(?P<flag>{keys})(?:=|\s+)|\b(?P<var>{keys})=){value}",
+                keys = SECRET_KEYS,
+            ),
+            "secret",
+        ),
+        (
+            r#"(?i)authorization:\s*(?:bearer|basic|token)\s+(?P<value>[^\s'"]+)"#.to_string(),
+            "token",
+        ),
+        (
+            r"://[^\s:/@]+:(?P<value>[^\s@/]+)@".to_string(),
+            "password",
+        ),
+        (
+            r"\b(?P<value>(?:ghp|gho|ghu|ghs|github_pat)_[A-Za-z0-9_]{20,}|sk-[A-Za-z0-9_-]{20,}|AKIA[0-9A-Z]{16}|xox[abprs]-[A-Za-z0-9-]{10,}|eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+)".to_string(),
+            "token",
+        ),
+    ];
+
+    let mut candidates: Vec<SecretCandidate> = Vec::new();
+
+    for (pattern, default_name) in &patterns {
+        let regex = Regex::new(pattern).unwrap();
+
+        for caps in regex.captures_iter(command) {
+            let value = caps["value"]
+                .trim_matches(|c| c == '"' || c == '\'')
+                .to_string();
+
+            if value.is_empty()
+                || value.starts_with('$')
+                || candidates.iter().any(|c| c.value == value)
+            {
+                continue;
+            }
+
+            let name = caps
+                .name("flag")
+                .or_else(|| caps.name("var"))
+                .map(|key| key.as_str().to_lowercase().replace('-', "_"))
+                .unwrap_or_else(|| default_name.to_string());
+
+            candidates.push(SecretCandidate { value, name });
+        }
+    }
+
+    candidates
+}
+
+fn decode(part: &str) -> FlowletResult<Vec<u8>> {
+    Ok(STANDARD
+        .decode(part)
+        .map_err(|_| SecretError::InvalidFormat)?)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> FlowletResult<Vec<u8>> {
+    let mut key = vec![0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| {
+            log::error!("{:?}", e);
+            SecretError::KeyDerivationFailed
+        })?;
+    Ok(key)
+}
+
+fn passphrase(confirm: bool) -> FlowletResult<String> {
+    let mut cached = PASSPHRASE.lock().unwrap();
+
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+
+    let passphrase = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) if confirm => Password::new()
+            .with_prompt("Secret passphrase")
+            .with_confirmation("Confirm passphrase", "Passphrases do not match")
+            .interact()?,
+        Err(_) => Password::new()
+            .with_prompt("Secret passphrase")
+            .interact()?,
+    };
+
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+fn key_file_path() -> FlowletResult<PathBuf> {
+    Ok(config_dir()?.join(KEY_FILE))
+}
+
+fn load_key_file() -> FlowletResult<Vec<u8>> {
+    let path = key_file_path()?;
+    let encoded = fs::read_to_string(&path)?;
+    let key = STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| SecretError::InvalidKeyFile(path.display().to_string()))?;
+
+    if key.len() != 32 {
+        return Err(Box::new(SecretError::InvalidKeyFile(
+            path.display().to_string(),
+        )));
+    }
+
+    Ok(key)
+}
+
+fn load_or_create_key_file() -> FlowletResult<Vec<u8>> {
+    let path = key_file_path()?;
+
+    if path.exists() {
+        return load_key_file();
+    }
+
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng).to_vec();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Only the owner should be able to read the key, from the moment it exists
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = match options.open(&path) {
+        Ok(file) => file,
+        // Another flowlet process created the key first, use that one
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return load_key_file(),
+        Err(e) => return Err(Box::new(e)),
+    };
+    file.write_all(STANDARD.encode(&key).as_bytes())?;
+
+    log::info!("Created secret key file at {}", path.display());
+    Ok(key)
+}
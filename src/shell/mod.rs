@@ -0,0 +1,261 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::util::{FlowletResult, config_dir};
+
+/// File the `flowlet shell-init` hook appends commands to, under the config dir.
+const HOOK_LOG: &str = "shell_history";
+
+#[derive(Debug, Error)]
+pub enum ShellError {
+    #[error("Could not detect your shell from $SHELL. Pass one of `bash`, `zsh` or `fish`.")]
+    UnknownShell,
+
+    #[error(
+        "No shell history found. Add `flowlet shell-init` to your shell config to record commands."
+    )]
+    HistoryNotFound,
+}
+
+/// A shell whose history Flowlet can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The login shell named by `$SHELL`.
+    pub fn detect() -> FlowletResult<Self> {
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        let name = Path::new(&shell)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        match name {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(Box::new(ShellError::UnknownShell)),
+        }
+    }
+
+    /// The history file the shell writes, honouring `$HISTFILE` for bash and zsh.
+    fn history_file(&self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        let histfile = std::env::var("HISTFILE").ok().map(PathBuf::from);
+
+        match self {
+            Shell::Bash => Some(histfile.unwrap_or_else(|| home.join(".bash_history"))),
+            Shell::Zsh => Some(histfile.unwrap_or_else(|| home.join(".zsh_history"))),
+            Shell::Fish => Some(
+                dirs::data_dir()
+                    .unwrap_or_else(|| home.join(".local").join("share"))
+                    .join("fish")
+                    .join("fish_history"),
+            ),
+        }
+    }
+
+    fn parse(&self, contents: &str) -> Vec<String> {
+        match self {
+            Shell::Bash => parse_bash(contents),
+            Shell::Zsh => parse_zsh(contents),
+            Shell::Fish => parse_fish(contents),
+        }
+    }
+
+    /// A hook recording each command as it runs, to be evaluated by the shell.
+    ///
+    /// Shells only write their history file on exit by default, so without the hook
+    /// `--last` cannot see commands from the current session.
+    pub fn init_script(&self) -> FlowletResult<String> {
+        let log = hook_log_path()?;
+        if let Some(dir) = log.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let log = log.to_string_lossy().to_string();
+
+        let script = match self {
+            Shell::Bash => format!(
+                r#"# Added by `flowlet shell-init bash`
+__flowlet_record() {{
+  local line num cmd bs='\' nl=$'\n'
+  line=$(HISTTIMEFORMAT= builtin history 1)
+  [[ $line =~ ^\ *([0-9]+)\*?\ +(.*)$ ]] || return
+  num=${{BASH_REMATCH[1]}} cmd=${{BASH_REMATCH[2]}}
+  [[ $num == "$__flowlet_last" ]] && return
+  __flowlet_last=$num
+  cmd=${{cmd//"$bs"/"$bs$bs"}}
+  printf '%s\n' "${{cmd//"$nl"/"$bs"n}}" >> {log}
+}}
+PROMPT_COMMAND="__flowlet_record${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+"#,
+                log = quote_posix(&log)
+            ),
+            Shell::Zsh => format!(
+                r#"# Added by `flowlet shell-init zsh`
+__flowlet_record() {{
+  local bs='\' nl=$'\n' cmd=$1
+  cmd=${{cmd//"$bs"/"$bs$bs"}}
+  printf '%s\n' "${{cmd//"$nl"/"$bs"n}}" >> {log}
+}}
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec __flowlet_record
+"#,
+                log = quote_posix(&log)
+            ),
+            Shell::Fish => format!(
+                r#"# Added by `flowlet shell-init fish`
+function __flowlet_record --on-event fish_preexec
+    set -l cmd (string replace -a -- '\\' '\\\\' $argv[1])
+    string join -- '\n' $cmd >> {log}
+end
+"#,
+                log = quote_fish(&log)
+            ),
+        };
+
+        Ok(script)
+    }
+}
+
+/// Recent commands, most recent first, without repeats or Flowlet's own invocations.
+///
+/// Commands recorded by the `shell-init` hook are preferred over the shell's history file.
+pub fn recent_commands(limit: usize) -> FlowletResult<Vec<String>> {
+    let hook_log = hook_log_path()?;
+
+    let commands = match read_lossy(&hook_log) {
+        Some(contents) if !contents.trim().is_empty() => parse_hook_log(&contents),
+        _ => {
+            let shell = Shell::detect()?;
+            let contents = shell
+                .history_file()
+                .and_then(|path| read_lossy(&path))
+                .ok_or(ShellError::HistoryNotFound)?;
+            shell.parse(&contents)
+        }
+    };
+
+    let mut recent: Vec<String> = Vec::new();
+
+    for command in commands.into_iter().rev() {
+        let command = command.trim().to_string();
+
+        if command.is_empty() || is_flowlet(&command) || recent.contains(&command) {
+            continue;
+        }
+
+        recent.push(command);
+
+        if recent.len() == limit {
+            break;
+        }
+    }
+
+    if recent.is_empty() {
+        return Err(Box::new(ShellError::HistoryNotFound));
+    }
+
+    Ok(recent)
+}
+
+pub fn hook_log_path() -> FlowletResult<PathBuf> {
+    Ok(config_dir()?.join(HOOK_LOG))
+}
+
+/// History files may hold bytes that are not valid UTF-8, zsh in particular.
+fn read_lossy(path: &Path) -> Option<String> {
+    fs::read(path)
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn is_flowlet(command: &str) -> bool {
+    command == "flowlet" || command.starts_with("flowlet ")
+}
+
+/// One command per line, skipping `#<epoch>` lines written with `HISTTIMEFORMAT`.
+fn parse_bash(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter(|line| !(line.starts_with('#') && line[1..].chars().all(|c| c.is_ascii_digit())))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Plain or `: <start>:<elapsed>;<command>` lines. A trailing backslash continues a
+/// command on the next line.
+fn parse_zsh(contents: &str) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+
+    for line in contents.lines() {
+        if continued && let Some(last) = commands.last_mut() {
+            last.pop();
+            last.push('\n');
+            last.push_str(line);
+        } else {
+            let command = match line.strip_prefix(": ").and_then(|l| l.split_once(';')) {
+                Some((_, command)) => command,
+                None => line,
+            };
+            commands.push(command.to_string());
+        }
+
+        continued = line.ends_with('\\');
+    }
+
+    commands
+}
+
+/// `- cmd: <command>` entries with `\n` and `\\` escaped.
+fn parse_fish(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("- cmd: "))
+        .map(unescape)
+        .collect()
+}
+
+/// Lines written by the hook, escaped the same way as fish history.
+fn parse_hook_log(contents: &str) -> Vec<String> {
+    contents.lines().map(unescape).collect()
+}
+
+fn unescape(escaped: &str) -> String {
+    let mut result = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+fn quote_posix(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn quote_fish(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
@@ -0,0 +1,494 @@
+use std::collections::{BTreeSet, HashMap};
+
+use deeb::Query;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use thiserror::Error;
+
+use crate::{
+    flowlet_context::FlowletContext,
+    flowlet_db::{
+        metadata::{Metadata, Tracked},
+        models::{
+            Api,
+            outbox::{Delivery, Outbox},
+            sync_state::{
+                CreateSyncStateInput, ListSyncStateInput, RemoveSyncStateInput, SyncState,
+            },
+        },
+    },
+    util::FlowletResult,
+};
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("Edited {0} is not valid: {1}")]
+    InvalidEdit(String, String),
+}
+
+/// A model that `flowlet sync` reconciles between the local store and the server.
+///
+/// Records are matched across devices by [`Syncable::sync_key`] rather than `_id`, the
+/// same way the rest of the CLI addresses them.
+pub trait Syncable: Tracked + Serialize + DeserializeOwned + Clone {
+    #![allow(async_fn_in_trait)]
+
+    /// Collection name on the server, as in `/find-many/<entity>`.
+    const ENTITY: &'static str;
+
+    fn id(&self) -> ulid::Ulid;
+
+    /// What the CLI addresses the record by, such as a command name or a task `_id`.
+    fn key(&self) -> String;
+
+    /// The project the record belongs to. Records with the same key in different
+    /// projects are different records.
+    fn project(&self) -> Option<&str> {
+        None
+    }
+
+    /// The key and project of the record, such as `api/deploy`.
+    fn sync_key(&self) -> String {
+        match self.project() {
+            Some(project) => format!("{}/{}", project, self.key()),
+            None => self.key(),
+        }
+    }
+
+    fn set_metadata(&mut self, metadata: Metadata);
+
+    async fn find_all_local(ctx: &FlowletContext) -> FlowletResult<Vec<Self>>;
+
+    async fn insert_local(ctx: &FlowletContext, record: Self) -> FlowletResult<()>;
+
+    async fn delete_local(ctx: &FlowletContext, id: ulid::Ulid) -> FlowletResult<()>;
+}
+
+/// What syncing a record does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Both sides match.
+    Unchanged,
+    /// Send the local record to the server.
+    Push,
+    /// Overwrite the local record with the remote one.
+    Pull,
+    /// The record was removed on the server.
+    DeleteLocal,
+    /// The record was removed locally.
+    DeleteRemote,
+    /// Removed on both sides, only the sync state is left.
+    Forget,
+    /// Both sides changed since the last sync.
+    Conflict,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Action::Unchanged => "unchanged",
+            Action::Push => "push",
+            Action::Pull => "pull",
+            Action::DeleteLocal => "delete local",
+            Action::DeleteRemote => "delete remote",
+            Action::Forget => "forget",
+            Action::Conflict => "conflict",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// How a conflict is settled.
+pub enum Resolution<T> {
+    KeepLocal,
+    KeepRemote,
+    /// A merged record, written to both sides.
+    Merged(T),
+}
+
+pub struct PlanItem<T> {
+    pub key: String,
+    pub local: Option<T>,
+    pub remote: Option<T>,
+    /// The state recorded by the last sync of this record.
+    pub synced: Option<SyncState>,
+    pub action: Action,
+}
+
+impl<T: Syncable> PlanItem<T> {
+    pub fn local_revision(&self) -> Option<u64> {
+        self.local.as_ref().map(|r| r.metadata().revision)
+    }
+
+    pub fn remote_revision(&self) -> Option<u64> {
+        self.remote.as_ref().map(|r| r.metadata().revision)
+    }
+}
+
+/// Compares local and remote records against the revisions seen at the last sync.
+pub async fn plan<T: Syncable>(ctx: &FlowletContext) -> FlowletResult<Vec<PlanItem<T>>> {
+    let mut local = by_key(T::find_all_local(ctx).await?);
+    let mut remote = by_key(fetch_remote::<T>(ctx).await?);
+    let mut states: HashMap<String, SyncState> = SyncState::list(
+        ctx,
+        ListSyncStateInput {
+            entity: T::ENTITY.to_string(),
+        },
+    )
+    .await?
+    .into_iter()
+    .map(|state| (state.key.clone(), state))
+    .collect();
+
+    let keys: BTreeSet<String> = local
+        .keys()
+        .chain(remote.keys())
+        .chain(states.keys())
+        .cloned()
+        .collect();
+
+    let plan = keys
+        .into_iter()
+        .map(|key| {
+            let local = local.remove(&key);
+            let remote = remote.remove(&key);
+            let state = states.remove(&key);
+            let action = decide(local.as_ref(), remote.as_ref(), state.as_ref());
+
+            PlanItem {
+                key,
+                local,
+                remote,
+                synced: state,
+                action,
+            }
+        })
+        .collect();
+
+    Ok(plan)
+}
+
+fn decide<T: Syncable>(local: Option<&T>, remote: Option<&T>, state: Option<&SyncState>) -> Action {
+    let local_changed =
+        |record: &T| state.is_none_or(|state| record.metadata().revision != state.local_revision);
+    let remote_changed =
+        |record: &T| state.is_none_or(|state| record.metadata().revision != state.remote_revision);
+
+    match (local, remote, state) {
+        (None, None, _) => Action::Forget,
+        (Some(_), None, None) => Action::Push,
+        (None, Some(_), None) => Action::Pull,
+        (Some(local), None, Some(_)) if local_changed(local) => Action::Conflict,
+        (Some(_), None, Some(_)) => Action::DeleteLocal,
+        (None, Some(remote), Some(_)) if remote_changed(remote) => Action::Conflict,
+        (None, Some(_), Some(_)) => Action::DeleteRemote,
+        (Some(local), Some(remote), _) => {
+            if content(local) == content(remote) {
+                return Action::Unchanged;
+            }
+
+            match (local_changed(local), remote_changed(remote)) {
+                (true, false) => Action::Push,
+                (false, true) => Action::Pull,
+                _ => Action::Conflict,
+            }
+        }
+    }
+}
+
+/// Applies a non conflicting plan item.
+pub async fn apply<T: Syncable>(ctx: &FlowletContext, item: &PlanItem<T>) -> FlowletResult<()> {
+    match (item.action, &item.local, &item.remote) {
+        (Action::Push, Some(local), remote) => {
+            if !push_remote(ctx, local, remote.as_ref())
+                .await?
+                .reaches_remote()
+            {
+                return Ok(());
+            }
+            record::<T>(
+                ctx,
+                &item.key,
+                local.metadata().revision,
+                local.metadata().revision,
+            )
+            .await
+        }
+        (Action::Pull, local, Some(remote)) => {
+            replace_local(ctx, local.as_ref(), remote.clone()).await?;
+            record::<T>(
+                ctx,
+                &item.key,
+                remote.metadata().revision,
+                remote.metadata().revision,
+            )
+            .await
+        }
+        (Action::Unchanged, Some(local), Some(remote)) => {
+            let (local_revision, remote_revision) =
+                (local.metadata().revision, remote.metadata().revision);
+
+            let recorded = item.synced.as_ref().is_some_and(|state| {
+                state.local_revision == local_revision && state.remote_revision == remote_revision
+            });
+
+            if recorded {
+                return Ok(());
+            }
+            record::<T>(ctx, &item.key, local_revision, remote_revision).await
+        }
+        (Action::DeleteLocal, Some(local), _) => {
+            T::delete_local(ctx, local.id()).await?;
+            forget::<T>(ctx, &item.key).await
+        }
+        (Action::DeleteRemote, _, Some(remote)) => {
+            if !delete_remote(ctx, remote).await?.reaches_remote() {
+                return Ok(());
+            }
+            forget::<T>(ctx, &item.key).await
+        }
+        (Action::Forget, _, _) => forget::<T>(ctx, &item.key).await,
+        _ => Ok(()),
+    }
+}
+
+/// Settles a conflict. A side that is missing counts as a deletion.
+pub async fn resolve<T: Syncable>(
+    ctx: &FlowletContext,
+    item: &PlanItem<T>,
+    resolution: Resolution<T>,
+) -> FlowletResult<()> {
+    let winner = match resolution {
+        Resolution::KeepLocal => match (&item.local, &item.remote) {
+            (Some(local), _) => local.clone(),
+            (None, Some(remote)) => {
+                if !delete_remote(ctx, remote).await?.reaches_remote() {
+                    return Ok(());
+                }
+                return forget::<T>(ctx, &item.key).await;
+            }
+            (None, None) => return forget::<T>(ctx, &item.key).await,
+        },
+        Resolution::KeepRemote => match (&item.remote, &item.local) {
+            (Some(remote), _) => remote.clone(),
+            (None, Some(local)) => {
+                T::delete_local(ctx, local.id()).await?;
+                return forget::<T>(ctx, &item.key).await;
+            }
+            (None, None) => return forget::<T>(ctx, &item.key).await,
+        },
+        Resolution::Merged(merged) => merged,
+    };
+
+    // A revision past both sides, so neither looks unchanged against the new state
+    let latest = item
+        .local_revision()
+        .max(item.remote_revision())
+        .unwrap_or_default();
+    let mut winner = winner;
+    let mut metadata = winner.metadata().clone();
+    metadata.revision = latest;
+    winner.set_metadata(metadata.next(ctx.author()));
+
+    let revision = winner.metadata().revision;
+    let delivery = push_remote(ctx, &winner, item.remote.as_ref()).await?;
+    replace_local(ctx, item.local.as_ref(), winner).await?;
+
+    // Leave the conflict recorded as it was, the next sync shows it again
+    if !delivery.reaches_remote() {
+        return Ok(());
+    }
+    record::<T>(ctx, &item.key, revision, revision).await
+}
+
+/// The record as edited text, without the fields sync manages itself.
+pub fn to_editable<T: Syncable>(record: &T) -> FlowletResult<String> {
+    Ok(serde_json::to_string_pretty(&content(record))?)
+}
+
+/// Reads back an edited record, keeping the identity and metadata of `base`.
+pub fn from_editable<T: Syncable>(text: &str, base: &T) -> FlowletResult<T> {
+    let invalid =
+        |e: serde_json::Error| SyncError::InvalidEdit(T::ENTITY.to_string(), e.to_string());
+
+    let mut edited: Value = serde_json::from_str(text).map_err(invalid)?;
+    let original = serde_json::to_value(base)?;
+
+    if let (Some(edited), Some(original)) = (edited.as_object_mut(), original.as_object()) {
+        for field in managed_fields() {
+            if let Some(value) = original.get(&field) {
+                edited.insert(field, value.clone());
+            }
+        }
+    }
+
+    Ok(serde_json::from_value(edited).map_err(invalid)?)
+}
+
+/// The record without `_id` and metadata, for comparing and showing records.
+pub fn content<T: Serialize>(record: &T) -> Value {
+    let mut value = serde_json::to_value(record).unwrap_or(Value::Null);
+
+    if let Some(object) = value.as_object_mut() {
+        for field in managed_fields() {
+            object.remove(&field);
+        }
+    }
+
+    value
+}
+
+fn managed_fields() -> Vec<String> {
+    let metadata = serde_json::to_value(Metadata::default()).unwrap_or(Value::Null);
+
+    std::iter::once("_id".to_string())
+        .chain(
+            metadata
+                .as_object()
+                .map(|object| object.keys().cloned().collect::<Vec<_>>())
+                .unwrap_or_default(),
+        )
+        .collect()
+}
+
+fn by_key<T: Syncable>(records: Vec<T>) -> HashMap<String, T> {
+    records
+        .into_iter()
+        .map(|record| (record.sync_key(), record))
+        .collect()
+}
+
+async fn fetch_remote<T: Syncable>(ctx: &FlowletContext) -> FlowletResult<Vec<T>> {
+    let res = ctx
+        .api_client
+        .post::<_, Vec<T>>(
+            &format!("/find-many/{}", T::ENTITY),
+            &json!({ "query": Query::All }),
+        )
+        .await?;
+
+    Ok(res.data.unwrap_or_default())
+}
+
+/// Sends the record to the server, updating `remote` when the server already has it.
+async fn push_remote<T: Syncable>(
+    ctx: &FlowletContext,
+    record: &T,
+    remote: Option<&T>,
+) -> FlowletResult<Delivery> {
+    let label = format!("{} `{}`", T::ENTITY, record.sync_key());
+
+    match remote {
+        Some(remote) => {
+            let query = Query::eq("_id", remote.id().to_string());
+            Outbox::send(
+                ctx,
+                &format!("/update-one/{}", T::ENTITY),
+                &json!({ "query": query, "document": record }),
+                &label,
+            )
+            .await
+        }
+        None => Outbox::send(ctx, &format!("/insert-one/{}", T::ENTITY), record, &label).await,
+    }
+}
+
+async fn delete_remote<T: Syncable>(ctx: &FlowletContext, remote: &T) -> FlowletResult<Delivery> {
+    let query = Query::eq("_id", remote.id().to_string());
+
+    Outbox::send(
+        ctx,
+        &format!("/delete-one/{}", T::ENTITY),
+        &json!({ "query": query }),
+        &format!("{} `{}`", T::ENTITY, remote.sync_key()),
+    )
+    .await
+}
+
+/// Writes `record` locally in place of `current`, the local record with the same key.
+async fn replace_local<T: Syncable>(
+    ctx: &FlowletContext,
+    current: Option<&T>,
+    record: T,
+) -> FlowletResult<()> {
+    if let Some(current) = current {
+        T::delete_local(ctx, current.id()).await?;
+    }
+
+    T::insert_local(ctx, record).await
+}
+
+async fn record<T: Syncable>(
+    ctx: &FlowletContext,
+    key: &str,
+    local_revision: u64,
+    remote_revision: u64,
+) -> FlowletResult<()> {
+    SyncState::create(
+        ctx,
+        CreateSyncStateInput {
+            entity: T::ENTITY.to_string(),
+            key: key.to_string(),
+            local_revision,
+            remote_revision,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn forget<T: Syncable>(ctx: &FlowletContext, key: &str) -> FlowletResult<()> {
+    SyncState::remove(
+        ctx,
+        RemoveSyncStateInput {
+            entity: T::ENTITY.to_string(),
+            key: key.to_string(),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flowlet_db::models::task::{Task, TaskStatus};
+
+    fn task(title: &str) -> Task {
+        Task {
+            _id: ulid::Ulid::new(),
+            title: title.to_string(),
+            description: None,
+            status: TaskStatus::Todo,
+            project: Some("api".to_string()),
+            due_date: None,
+            tags: Vec::new(),
+            milestones: Vec::new(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    #[test]
+    fn tasks_with_the_same_title_stay_separate() {
+        let (first, second) = (task("Write docs"), task("Write docs"));
+        let (first_id, second_id) = (first._id.to_string(), second._id.to_string());
+
+        let local = by_key(vec![first.clone(), second.clone()]);
+        assert_eq!(local.len(), 2);
+        assert_eq!(local[&first_id].title, "Write docs");
+        assert_eq!(local[&second_id]._id, second._id);
+
+        // Only the second task exists on the server, the first has never been synced
+        let remote = by_key(vec![second.clone()]);
+        assert_eq!(
+            decide(local.get(&first_id), remote.get(&first_id), None),
+            Action::Push
+        );
+        assert_eq!(
+            decide(local.get(&second_id), remote.get(&second_id), None),
+            Action::Unchanged
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::future::Future;
+use std::io::IsTerminal;
+use std::pin::Pin;
+use std::time::Duration;
+
+use ratatui::{
+    DefaultTerminal,
+    crossterm::{
+        event::{self, Event, KeyEventKind},
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    },
+};
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::{
+    cli::command::{Command, Execution},
+    flowlet_context::WithContext,
+    flowlet_db::models::{
+        Api,
+        command::{self as command_model, UpdateCommandInput},
+        project::{Project, UpdateProjectInput},
+        task::{Task, UpdateTaskInput},
+        variable::{UpdateVariableInput, Variable},
+    },
+    printer::Printer,
+    runner::OutputSink,
+    secrets::{needs_passphrase, unlock},
+    util::{
+        CommandArgs, FlowletResult, MissingVariables, launch_editor, parse_placeholders,
+        preview_variables,
+    },
+};
+
+pub mod state;
+pub mod view;
+
+use state::{Action, Pane, Records, State, next_status};
+
+/// How long the event loop waits for a key before redrawing.
+const TICK: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Error)]
+pub enum TuiError {
+    #[error("The TUI needs an interactive terminal.")]
+    NotATerminal,
+}
+
+/// A command started from the TUI, polled by the event loop while keys are handled.
+type Running<'a> = Pin<Box<dyn Future<Output = FlowletResult<Execution>> + 'a>>;
+
+/// A record opened in the editor.
+enum EditTarget {
+    Command(command_model::Command),
+    Variable(Variable),
+    Project(Project),
+    Task(Task),
+}
+
+pub struct Tui;
+
+impl Tui {
+    /// Opens the full screen browser until the user quits.
+    pub async fn run(ctx: &impl WithContext, remote: bool) -> FlowletResult<()> {
+        if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+            return Err(Box::new(TuiError::NotATerminal));
+        }
+
+        // Models report progress through the printer, which would draw over the screen
+        Printer::set_quiet(true);
+
+        let mut state = State::new(remote);
+        Self::reload(ctx, &mut state).await;
+
+        let mut terminal = ratatui::init();
+
+        let result = Self::event_loop(ctx, &mut terminal, &mut state).await;
+
+        ratatui::restore();
+        Printer::set_quiet(false);
+
+        result
+    }
+
+    async fn event_loop(
+        ctx: &impl WithContext,
+        terminal: &mut DefaultTerminal,
+        state: &mut State,
+    ) -> FlowletResult<()> {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+        let mut running: Option<Running> = None;
+
+        loop {
+            while let Ok(line) = receiver.try_recv() {
+                state.push_output(line);
+            }
+
+            Self::refresh_preview(ctx, state).await;
+            terminal.draw(|frame| view::draw(frame, state))?;
+
+            if let Some(execution) = running.as_mut() {
+                tokio::select! {
+                    result = execution => {
+                        running = None;
+                        Self::finish_run(ctx, state, result).await;
+                        continue;
+                    }
+                    _ = tokio::time::sleep(TICK) => {}
+                }
+            }
+
+            let wait = if running.is_some() {
+                Duration::ZERO
+            } else {
+                TICK
+            };
+
+            if !event::poll(wait)? {
+                continue;
+            }
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            state.status = None;
+
+            match state.handle_key(key) {
+                Action::None => {}
+                // Dropping the run kills the process, quitting takes a second press
+                Action::Quit if running.is_some() => {
+                    running = None;
+                    let name = state.running.take().unwrap_or_default();
+                    state.fail(format!("Stopped `{}`.", name));
+                }
+                Action::Quit => return Ok(()),
+                Action::Reload => {
+                    Self::reload(ctx, state).await;
+                    state.info("Reloaded.");
+                }
+                Action::ToggleRemote => {
+                    state.remote = !state.remote;
+                    Self::reload(ctx, state).await;
+                }
+                Action::Run if running.is_some() => {
+                    state.fail("A command is already running.");
+                }
+                Action::Run => {
+                    let Some(command) = state.selected_command().cloned() else {
+                        continue;
+                    };
+
+                    if Command::needs_confirmation(&command.options) {
+                        state.fail(format!(
+                            "`{}` needs confirmation. Run it with `flowlet command run {}`.",
+                            command.name, command.name
+                        ));
+                        continue;
+                    }
+
+                    if Self::needs_unlock(state, &command.cmd) {
+                        Self::suspend()?;
+                        let unlocked = unlock();
+                        Self::resume(terminal)?;
+
+                        if let Err(e) = unlocked {
+                            state.fail(e.to_string());
+                            continue;
+                        }
+                    }
+
+                    state.output.clear();
+                    state.running = Some(command.name.clone());
+
+                    let sink = OutputSink::Channel(sender.clone());
+                    running = Some(Box::pin(async move {
+                        Command::execute_to(
+                            ctx,
+                            &command.name,
+                            &command.cmd,
+                            &command.options,
+                            &CommandArgs::default(),
+                            MissingVariables::Strict,
+                            sink,
+                        )
+                        .await
+                    }));
+                }
+                Action::Edit if running.is_some() => {
+                    state.fail("Wait for the running command to finish before editing.");
+                }
+                Action::Edit => Self::edit(ctx, terminal, state).await?,
+                Action::ToggleStatus => Self::toggle_status(ctx, state).await,
+            }
+        }
+    }
+
+    async fn reload(ctx: &impl WithContext, state: &mut State) {
+        match Records::load(ctx.get(), state.remote).await {
+            Ok(records) => state.set_records(records),
+            Err(e) => state.fail(e.to_string()),
+        }
+    }
+
+    /// Fills in the variables of the selected command when the selection changed.
+    async fn refresh_preview(ctx: &impl WithContext, state: &mut State) {
+        let Some(command) = state.selected_command() else {
+            return;
+        };
+
+        if state
+            .preview
+            .as_ref()
+            .is_some_and(|(_id, _)| *_id == command._id)
+        {
+            return;
+        }
+
+        let _id = command._id;
+        let preview = match preview_variables(ctx, &command.cmd).await {
+            Ok(preview) => preview,
+            Err(e) => e.to_string(),
+        };
+
+        state.preview = Some((_id, preview));
+    }
+
+    /// Whether running `cmd` decrypts a secret protected by a passphrase, which has to be
+    /// asked for outside of the TUI.
+    fn needs_unlock(state: &State, cmd: &str) -> bool {
+        let names: Vec<String> = parse_placeholders(cmd)
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+
+        state
+            .records
+            .variables
+            .iter()
+            .any(|v| v.secret && needs_passphrase(&v.value) && names.contains(&v.name))
+    }
+
+    async fn finish_run(
+        ctx: &impl WithContext,
+        state: &mut State,
+        result: FlowletResult<Execution>,
+    ) {
+        let name = state.running.take().unwrap_or_default();
+
+        let execution = match result {
+            Ok(execution) => execution,
+            Err(e) => return state.fail(e.to_string()),
+        };
+
+        // History is best effort, same as `flowlet command run`
+        if let Err(e) = Command::record_run(ctx, &name, &[], &execution).await {
+            log::warn!("Failed to record run: {:?}", e);
+        }
+
+        let code = execution.output.exit_code;
+        let elapsed = format!("{:.1}s", execution.duration.as_secs_f64());
+
+        if execution.output.success() {
+            state.info(format!("`{}` finished in {}.", name, elapsed));
+        } else {
+            state.fail(format!(
+                "`{}` exited with code {} after {}.",
+                name, code, elapsed
+            ));
+        }
+    }
+
+    /// Opens the selected record in the editor and saves the result through the `Api`.
+    async fn edit(
+        ctx: &impl WithContext,
+        terminal: &mut DefaultTerminal,
+        state: &mut State,
+    ) -> FlowletResult<()> {
+        if state.remote {
+            state.fail("Switch to the local view with `R` to edit.");
+            return Ok(());
+        }
+
+        let target = match state.pane {
+            Pane::Commands => state.selected_command().cloned().map(EditTarget::Command),
+            Pane::Variables => state.selected_variable().cloned().map(EditTarget::Variable),
+            Pane::Projects => state.selected_project().cloned().map(EditTarget::Project),
+            Pane::Tasks => state.selected_task().cloned().map(EditTarget::Task),
+        };
+
+        let Some(target) = target else {
+            return Ok(());
+        };
+
+        let initial = match &target {
+            EditTarget::Variable(var) if var.secret => {
+                state.fail("Secrets are not opened in an editor. Use `flowlet vars set --secret`.");
+                return Ok(());
+            }
+            EditTarget::Command(command) => command.cmd.clone(),
+            EditTarget::Variable(var) => var.value.clone(),
+            EditTarget::Project(project) => project.description.clone().unwrap_or_default(),
+            EditTarget::Task(task) => task.description.clone().unwrap_or_default(),
+        };
+
+        Self::suspend()?;
+        let edited = launch_editor(&initial, ctx.get().config.editor());
+        Self::resume(terminal)?;
+
+        let text = match edited {
+            Ok(text) => text.trim_end_matches('\n').to_string(),
+            Err(e) => {
+                state.fail(e.to_string());
+                return Ok(());
+            }
+        };
+
+        if text == initial {
+            state.info("No changes.");
+            return Ok(());
+        }
+
+        match Self::save(ctx, target, text).await {
+            Ok(saved) => state.info(format!("Saved {}.", saved)),
+            Err(e) => state.fail(e.to_string()),
+        }
+
+        Self::reload(ctx, state).await;
+        Ok(())
+    }
+
+    async fn save(
+        ctx: &impl WithContext,
+        target: EditTarget,
+        text: String,
+    ) -> FlowletResult<String> {
+        let ctx = ctx.get();
+        let optional = |text: String| (!text.trim().is_empty()).then_some(text);
+
+        let saved = match target {
+            EditTarget::Command(command) => {
+                command_model::Command::update(
+                    ctx,
+                    UpdateCommandInput {
+                        name: command.name.clone(),
+                        cmd: text,
+                        options: None,
+                    },
+                )
+                .await?;
+                format!("command `{}`", command.name)
+            }
+            EditTarget::Variable(var) => {
+                Variable::update(
+                    ctx,
+                    UpdateVariableInput {
+                        name: var.name.clone(),
+                        value: text,
+                        project: var.project,
+                        environment: var.environment,
+                        secret: false,
+                    },
+                )
+                .await?;
+                format!("variable `{}`", var.name)
+            }
+            EditTarget::Project(project) => {
+                Project::update(
+                    ctx,
+                    UpdateProjectInput {
+                        name: project.name.clone(),
+                        description: optional(text),
+                    },
+                )
+                .await?;
+                format!("project `{}`", project.name)
+            }
+            EditTarget::Task(task) => {
+                let title = task.title.clone();
+                Task::update(
+                    ctx,
+                    Self::task_update(task, |t| t.description = optional(text)),
+                )
+                .await?;
+                format!("task `{}`", title)
+            }
+        };
+
+        Ok(saved)
+    }
+
+    async fn toggle_status(ctx: &impl WithContext, state: &mut State) {
+        if state.remote {
+            return state.fail("Switch to the local view with `R` to change tasks.");
+        }
+
+        let Some(task) = state.selected_task().cloned() else {
+            return;
+        };
+
+        let update = Self::task_update(task, |t| t.status = next_status(&t.status));
+
+        match Task::update(ctx.get(), update).await {
+            Ok(task) => state.info(format!("`{}` is now {}.", task.title, task.status)),
+            Err(e) => state.fail(e.to_string()),
+        }
+
+        Self::reload(ctx, state).await;
+    }
+
+    /// An update writing back every field of `task` after `change`, since unset fields
+    /// would clear the stored ones.
+    fn task_update(mut task: Task, change: impl FnOnce(&mut Task)) -> UpdateTaskInput {
+        change(&mut task);
+
+        UpdateTaskInput {
+            title: task.title,
+            status: Some(task.status),
+            description: task.description,
+            due_date: task.due_date,
+            tags: Some(task.tags),
+            milestones: Some(task.milestones),
+        }
+    }
+
+    /// Hands the terminal back for the editor or a passphrase prompt.
+    fn suspend() -> FlowletResult<()> {
+        disable_raw_mode()?;
+        execute!(std::io::stdout(), LeaveAlternateScreen)?;
+        Ok(())
+    }
+
+    fn resume(terminal: &mut DefaultTerminal) -> FlowletResult<()> {
+        enable_raw_mode()?;
+        execute!(std::io::stdout(), EnterAlternateScreen)?;
+        terminal.clear()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,360 @@
+use deeb::Query;
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    flowlet_context::FlowletContext,
+    flowlet_db::models::{
+        Api,
+        command::{Command, ListCommandInput},
+        project::{ListProjectInput, Project},
+        task::{ListTaskInput, Task, TaskStatus},
+        variable::{ListVariableInput, Variable},
+    },
+    util::FlowletResult,
+};
+
+/// Lines of output kept for the output pane.
+const OUTPUT_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Commands,
+    Variables,
+    Projects,
+    Tasks,
+}
+
+impl Pane {
+    pub const ALL: [Pane; 4] = [Pane::Commands, Pane::Variables, Pane::Projects, Pane::Tasks];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Pane::Commands => "Commands",
+            Pane::Variables => "Variables",
+            Pane::Projects => "Projects",
+            Pane::Tasks => "Tasks",
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Pane::ALL.iter().position(|p| p == self).unwrap_or_default()
+    }
+
+    fn offset(&self, by: isize) -> Self {
+        let len = Pane::ALL.len() as isize;
+        Pane::ALL[(self.index() as isize + by).rem_euclid(len) as usize]
+    }
+}
+
+/// Something the event loop has to do in response to a key, beyond moving around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+    Reload,
+    ToggleRemote,
+    Run,
+    Edit,
+    ToggleStatus,
+}
+
+/// The records shown in the panes, as returned by the `Api` list methods.
+#[derive(Default)]
+pub struct Records {
+    pub commands: Vec<Command>,
+    pub variables: Vec<Variable>,
+    pub projects: Vec<Project>,
+    pub tasks: Vec<Task>,
+}
+
+impl Records {
+    /// Variables are only stored locally, so `remote` applies to the other panes.
+    pub async fn load(ctx: &FlowletContext, remote: bool) -> FlowletResult<Self> {
+        let mut commands = Command::list(
+            ctx,
+            ListCommandInput {
+                query: Query::All,
+                remote,
+            },
+        )
+        .await?;
+        let mut variables = Variable::list(ctx, ListVariableInput { query: Query::All }).await?;
+        let mut projects = Project::list(
+            ctx,
+            ListProjectInput {
+                query: Query::All,
+                remote,
+            },
+        )
+        .await?;
+        let mut tasks = Task::list(
+            ctx,
+            ListTaskInput {
+                query: Query::All,
+                remote,
+            },
+        )
+        .await?;
+
+        commands.sort_by(|a, b| a.name.cmp(&b.name));
+        variables.sort_by(|a, b| a.name.cmp(&b.name).then(a.scope().cmp(&b.scope())));
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+        tasks.sort_by(|a, b| a.title.cmp(&b.title));
+
+        Ok(Self {
+            commands,
+            variables,
+            projects,
+            tasks,
+        })
+    }
+}
+
+/// A message for the status line.
+pub struct Status {
+    pub message: String,
+    pub error: bool,
+}
+
+pub struct State {
+    pub pane: Pane,
+    pub records: Records,
+    pub remote: bool,
+    pub filter: String,
+    /// Keys go to the filter instead of the panes.
+    pub filtering: bool,
+    /// Position in the filtered rows of each pane.
+    selected: [usize; 4],
+    /// The selected command with its variables filled in, by command `_id`.
+    pub preview: Option<(ulid::Ulid, String)>,
+    pub output: Vec<String>,
+    /// Name of the command currently running.
+    pub running: Option<String>,
+    pub status: Option<Status>,
+    matcher: SkimMatcherV2,
+}
+
+impl State {
+    pub fn new(remote: bool) -> Self {
+        Self {
+            pane: Pane::Commands,
+            records: Records::default(),
+            remote,
+            filter: String::new(),
+            filtering: false,
+            selected: [0; 4],
+            preview: None,
+            output: Vec::new(),
+            running: None,
+            status: None,
+            matcher: SkimMatcherV2::default(),
+        }
+    }
+
+    /// The label of each record of `pane`, which is also what the filter matches.
+    pub fn labels(&self, pane: Pane) -> Vec<String> {
+        match pane {
+            Pane::Commands => self
+                .records
+                .commands
+                .iter()
+                .map(|c| c.name.clone())
+                .collect(),
+            Pane::Variables => self
+                .records
+                .variables
+                .iter()
+                .map(|v| format!("{} ({})", v.name, v.scope()))
+                .collect(),
+            Pane::Projects => self
+                .records
+                .projects
+                .iter()
+                .map(|p| p.name.clone())
+                .collect(),
+            Pane::Tasks => self
+                .records
+                .tasks
+                .iter()
+                .map(|t| format!("{} {}", status_marker(&t.status), t.title))
+                .collect(),
+        }
+    }
+
+    /// Indices into the records of `pane` matching the filter, best match first.
+    pub fn visible(&self, pane: Pane) -> Vec<usize> {
+        let labels = self.labels(pane);
+
+        if self.filter.is_empty() {
+            return (0..labels.len()).collect();
+        }
+
+        let mut scored: Vec<(i64, usize)> = labels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, label)| {
+                self.matcher
+                    .fuzzy_match(label, &self.filter)
+                    .map(|score| (score, i))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        scored.into_iter().map(|(_, i)| i).collect()
+    }
+
+    /// Position of the selection in the filtered rows of the current pane.
+    pub fn selected_row(&self) -> usize {
+        self.selected[self.pane.index()]
+    }
+
+    /// Index into the records of the current pane of the selected row.
+    fn selected_index(&self) -> Option<usize> {
+        self.visible(self.pane).get(self.selected_row()).copied()
+    }
+
+    pub fn selected_command(&self) -> Option<&Command> {
+        match self.pane {
+            Pane::Commands => self.selected_index().map(|i| &self.records.commands[i]),
+            _ => None,
+        }
+    }
+
+    pub fn selected_variable(&self) -> Option<&Variable> {
+        match self.pane {
+            Pane::Variables => self.selected_index().map(|i| &self.records.variables[i]),
+            _ => None,
+        }
+    }
+
+    pub fn selected_project(&self) -> Option<&Project> {
+        match self.pane {
+            Pane::Projects => self.selected_index().map(|i| &self.records.projects[i]),
+            _ => None,
+        }
+    }
+
+    pub fn selected_task(&self) -> Option<&Task> {
+        match self.pane {
+            Pane::Tasks => self.selected_index().map(|i| &self.records.tasks[i]),
+            _ => None,
+        }
+    }
+
+    pub fn set_records(&mut self, records: Records) {
+        self.records = records;
+        self.preview = None;
+        self.clamp_selection();
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.status = Some(Status {
+            message: message.into(),
+            error: false,
+        });
+    }
+
+    pub fn fail(&mut self, message: impl Into<String>) {
+        self.status = Some(Status {
+            message: message.into(),
+            error: true,
+        });
+    }
+
+    pub fn push_output(&mut self, line: String) {
+        self.output.push(line);
+
+        if self.output.len() > OUTPUT_LIMIT {
+            let excess = self.output.len() - OUTPUT_LIMIT;
+            self.output.drain(..excess);
+        }
+    }
+
+    /// Moves around and edits the filter, returning anything the event loop has to do.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        if self.filtering {
+            match key.code {
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.filtering = false;
+                }
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Up => self.move_selection(-1),
+                KeyCode::Down => self.move_selection(1),
+                _ => {}
+            }
+            self.clamp_selection();
+            return Action::None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if self.filter.is_empty() => return Action::Quit,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.clamp_selection();
+            }
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Tab | KeyCode::Right => self.pane = self.pane.offset(1),
+            KeyCode::BackTab | KeyCode::Left => self.pane = self.pane.offset(-1),
+            KeyCode::Char(c @ '1'..='4') => self.pane = Pane::ALL[c as usize - '1' as usize],
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::Home | KeyCode::Char('g') => self.selected[self.pane.index()] = 0,
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Char('c') => self.output.clear(),
+            KeyCode::Char('r') => return Action::Reload,
+            KeyCode::Char('R') => return Action::ToggleRemote,
+            KeyCode::Char('e') => return Action::Edit,
+            KeyCode::Enter if self.pane == Pane::Commands => return Action::Run,
+            KeyCode::Enter | KeyCode::Char('t') if self.pane == Pane::Tasks => {
+                return Action::ToggleStatus;
+            }
+            _ => {}
+        }
+
+        Action::None
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        let row = self.selected_row() as isize;
+        self.selected[self.pane.index()] = row.saturating_add(by).max(0) as usize;
+        self.clamp_selection();
+    }
+
+    fn clamp_selection(&mut self) {
+        for pane in Pane::ALL {
+            let len = self.visible(pane).len();
+            let row = &mut self.selected[pane.index()];
+            *row = (*row).min(len.saturating_sub(1));
+        }
+    }
+}
+
+/// The status a task moves to when toggled.
+pub fn next_status(status: &TaskStatus) -> TaskStatus {
+    match status {
+        TaskStatus::Todo => TaskStatus::InProgress,
+        TaskStatus::InProgress => TaskStatus::Done,
+        TaskStatus::Done => TaskStatus::Todo,
+    }
+}
+
+fn status_marker(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Todo => "[ ]",
+        TaskStatus::InProgress => "[~]",
+        TaskStatus::Done => "[x]",
+    }
+}
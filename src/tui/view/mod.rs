@@ -0,0 +1,285 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Tabs, Wrap},
+};
+
+use crate::flowlet_db::metadata::Metadata;
+
+use super::state::{Pane, State};
+
+const HELP: &str =
+    "tab panes · / filter · enter run · e edit · t status · r reload · R remote · c clear · q quit";
+
+pub fn draw(frame: &mut Frame, state: &State) {
+    let [tabs, main, output, status] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Percentage(35),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let [list, details] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(main);
+
+    draw_tabs(frame, state, tabs);
+    draw_list(frame, state, list);
+    draw_details(frame, state, details);
+    draw_output(frame, state, output);
+    draw_status(frame, state, status);
+}
+
+fn draw_tabs(frame: &mut Frame, state: &State, area: Rect) {
+    let titles = Pane::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, pane)| format!("{} {}", i + 1, pane.title()));
+
+    let view = if state.remote { "remote" } else { "local" };
+
+    let tabs = Tabs::new(titles)
+        .select(state.pane.index())
+        .highlight_style(Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .block(Block::bordered().title(format!(" 🌊 Flowlet · {} ", view)));
+
+    frame.render_widget(tabs, area);
+}
+
+fn draw_list(frame: &mut Frame, state: &State, area: Rect) {
+    let labels = state.labels(state.pane);
+    let items: Vec<ListItem> = state
+        .visible(state.pane)
+        .into_iter()
+        .map(|i| ListItem::new(labels[i].clone()))
+        .collect();
+
+    let title = if state.filtering || !state.filter.is_empty() {
+        let cursor = if state.filtering { "▏" } else { "" };
+        format!(" {} · /{}{} ", state.pane.title(), state.filter, cursor)
+    } else {
+        format!(" {} ({}) ", state.pane.title(), labels.len())
+    };
+
+    let list = List::new(items)
+        .block(Block::bordered().title(title))
+        .highlight_style(Style::new().reversed())
+        .highlight_symbol("› ");
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected_row()));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+fn draw_details(frame: &mut Frame, state: &State, area: Rect) {
+    let (title, lines) = match state.pane {
+        Pane::Commands => command_details(state),
+        Pane::Variables => variable_details(state),
+        Pane::Projects => project_details(state),
+        Pane::Tasks => task_details(state),
+    };
+
+    let details = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(format!(" {} ", title)));
+
+    frame.render_widget(details, area);
+}
+
+fn command_details(state: &State) -> (&'static str, Vec<Line<'static>>) {
+    let Some(command) = state.selected_command() else {
+        return ("Preview", empty("No commands found."));
+    };
+
+    let mut lines = fields(&[
+        ("Name", command.name.clone()),
+        ("Project", or_dash(&command.project)),
+        ("Mode", command.options.mode.to_string()),
+        (
+            "Interpreter",
+            command
+                .options
+                .interpreter_for(&command.cmd)
+                .unwrap_or_else(|| "shell setting".into()),
+        ),
+    ]);
+
+    lines.push(Line::default());
+    lines.push(heading("Runs"));
+    match &state.preview {
+        Some((_id, preview)) if *_id == command._id => {
+            lines.extend(preview.lines().map(|l| Line::from(l.to_string()).green()));
+        }
+        _ => lines.push(Line::from("…").dark_gray()),
+    }
+
+    lines.push(Line::default());
+    lines.push(heading("Saved"));
+    lines.extend(command.cmd.lines().map(|l| Line::from(l.to_string())));
+
+    lines.push(Line::default());
+    lines.extend(metadata(&command.metadata));
+
+    ("Preview", lines)
+}
+
+fn variable_details(state: &State) -> (&'static str, Vec<Line<'static>>) {
+    let Some(var) = state.selected_variable() else {
+        return ("Variable", empty("No variables found."));
+    };
+
+    let mut lines = fields(&[
+        ("Name", var.name.clone()),
+        ("Value", var.display_value()),
+        ("Scope", var.scope().to_string()),
+        ("Project", or_dash(&var.project)),
+        ("Env", or_dash(&var.environment)),
+        ("Secret", if var.secret { "yes" } else { "no" }.to_string()),
+    ]);
+
+    lines.push(Line::default());
+    lines.extend(metadata(&var.metadata));
+
+    ("Variable", lines)
+}
+
+fn project_details(state: &State) -> (&'static str, Vec<Line<'static>>) {
+    let Some(project) = state.selected_project() else {
+        return ("Project", empty("No projects found."));
+    };
+
+    let in_project = |p: &Option<String>| p.as_deref() == Some(project.name.as_str());
+    let commands = state
+        .records
+        .commands
+        .iter()
+        .filter(|c| in_project(&c.project))
+        .count();
+    let tasks = state
+        .records
+        .tasks
+        .iter()
+        .filter(|t| in_project(&t.project))
+        .count();
+
+    let mut lines = fields(&[
+        ("Name", project.name.clone()),
+        ("Description", or_dash(&project.description)),
+        ("Commands", commands.to_string()),
+        ("Tasks", tasks.to_string()),
+    ]);
+
+    lines.push(Line::default());
+    lines.extend(metadata(&project.metadata));
+
+    ("Project", lines)
+}
+
+fn task_details(state: &State) -> (&'static str, Vec<Line<'static>>) {
+    let Some(task) = state.selected_task() else {
+        return ("Task", empty("No tasks found."));
+    };
+
+    let mut lines = fields(&[
+        ("Title", task.title.clone()),
+        ("Status", task.status.to_string()),
+        ("Project", or_dash(&task.project)),
+        (
+            "Due Date",
+            task.due_date.map_or("-".into(), |d| d.to_string()),
+        ),
+        (
+            "Tags",
+            if task.tags.is_empty() {
+                "-".into()
+            } else {
+                task.tags.join(", ")
+            },
+        ),
+        ("Description", or_dash(&task.description)),
+    ]);
+
+    if !task.milestones.is_empty() {
+        lines.push(Line::default());
+        lines.push(heading("Milestones"));
+        for (i, m) in task.milestones.iter().enumerate() {
+            lines.push(Line::from(format!(
+                "{}. {} (Due: {})",
+                i + 1,
+                m.name,
+                m.due_date.map_or("-".into(), |d| d.to_string())
+            )));
+        }
+    }
+
+    lines.push(Line::default());
+    lines.extend(metadata(&task.metadata));
+
+    ("Task", lines)
+}
+
+fn draw_output(frame: &mut Frame, state: &State, area: Rect) {
+    let title = match &state.running {
+        Some(name) => format!(" Output · {} running… ", name),
+        None => " Output ".to_string(),
+    };
+
+    // Follow the end of the output
+    let height = area.height.saturating_sub(2) as usize;
+    let start = state.output.len().saturating_sub(height);
+    let lines: Vec<Line> = state.output[start..]
+        .iter()
+        .map(|l| Line::from(l.as_str()))
+        .collect();
+
+    let output = Paragraph::new(lines).block(Block::bordered().title(title));
+    frame.render_widget(output, area);
+}
+
+fn draw_status(frame: &mut Frame, state: &State, area: Rect) {
+    let line = match &state.status {
+        Some(status) if status.error => Line::from(status.message.as_str()).red(),
+        Some(status) => Line::from(status.message.as_str()).green(),
+        None => Line::from(HELP).dark_gray(),
+    };
+
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+fn fields(kvs: &[(&str, String)]) -> Vec<Line<'static>> {
+    let width = kvs.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+
+    kvs.iter()
+        .map(|(key, value)| {
+            Line::from(vec![
+                Span::from(format!("{:<width$}  ", key, width = width)).bold(),
+                Span::from(value.clone()),
+            ])
+        })
+        .collect()
+}
+
+fn metadata(metadata: &Metadata) -> Vec<Line<'static>> {
+    let kvs: Vec<(&str, String)> = Metadata::HEADERS
+        .into_iter()
+        .zip(metadata.columns())
+        .collect();
+
+    fields(&kvs)
+        .into_iter()
+        .map(|line| line.dark_gray())
+        .collect()
+}
+
+fn heading(title: &str) -> Line<'static> {
+    Line::from(title.to_string()).bold().underlined()
+}
+
+fn empty(message: &str) -> Vec<Line<'static>> {
+    vec![Line::from(message.to_string()).dark_gray()]
+}
+
+fn or_dash(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "-".into())
+}
@@ -1,34 +1,54 @@
 use crate::flowlet_context::WithContext;
+use crate::flowlet_db::metadata::Tracked;
 use crate::flowlet_db::models::Api;
-use crate::flowlet_db::models::variable::{ReadVariableInput, Variable};
+use crate::flowlet_db::models::variable::{CreateVariableInput, Variable, VariableScope};
 use crate::printer::{Icon, Printer};
+use crate::project_file::ProjectFile;
+use crate::secrets::{decrypt, mask_secrets};
 use chrono::NaiveDate;
-use dialoguer::Input;
-use regex::Regex;
+use dialoguer::{Confirm, Input};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
 use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
 use tempfile::NamedTempFile;
+use thiserror::Error;
 use toml::Value;
+use toml_edit::DocumentMut;
 
 pub type FlowletResult<T> = Result<T, Box<dyn std::error::Error>>;
 
-pub fn launch_editor(initial: &str) -> std::io::Result<String> {
+/// The directory holding Flowlet's own files, `~/.config/flowlet`.
+pub fn config_dir() -> FlowletResult<PathBuf> {
+    let home = dirs::home_dir().ok_or(UtilError::HomeDirAccessDenied)?;
+    Ok(home.join(".config").join("flowlet"))
+}
+
+pub fn launch_editor(initial: &str, editor: Option<&str>) -> std::io::Result<String> {
     // Create a temp file and write the initial content
     let mut file = NamedTempFile::new()?;
     write!(file, "{}", initial)?;
 
-    // Use $EDITOR or fallback
-    let editor = std::env::var("EDITOR").unwrap_or_else(|_| {
-        if which::which("vim").is_ok() {
-            "vim".to_string()
-        } else if which::which("vi").is_ok() {
-            "vi".to_string()
-        } else {
-            "nano".to_string()
-        }
-    });
+    // Use the configured editor, $EDITOR or fallback
+    let editor = match editor {
+        Some(editor) => editor.to_string(),
+        None => std::env::var("EDITOR").unwrap_or_else(|_| {
+            if which::which("vim").is_ok() {
+                "vim".to_string()
+            } else if which::which("vi").is_ok() {
+                "vi".to_string()
+            } else {
+                "nano".to_string()
+            }
+        }),
+    };
 
     // Open the editor
     let status = Command::new(editor).arg(file.path()).status()?;
@@ -48,81 +68,563 @@ pub fn launch_editor(initial: &str) -> std::io::Result<String> {
     Ok(content)
 }
 
-pub fn clean_command(raw: &str) -> String {
-    raw.lines()
-        .map(str::trim_end) // remove trailing whitespace on each line
-        .collect::<Vec<_>>()
-        .join(" ") // join lines with space
-        .replace('\\', "") // remove literal backslashes
-        .replace("  ", " ") // collapse double spaces
-        .trim() // final trim
-        .to_string()
+/// Folds lines ending in a `\` continuation into the next one, as the shell would.
+///
+/// Other newlines and backslashes are kept, so blocks, heredocs and escapes inside the
+/// command still work. Whitespace after a trailing backslash is ignored.
+pub fn fold_continuations(raw: &str) -> String {
+    let mut folded = String::with_capacity(raw.len());
+    let mut lines = raw.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_end();
+        let backslashes = trimmed.len() - trimmed.trim_end_matches('\\').len();
+
+        // An even number of backslashes ends in an escaped, literal backslash
+        if backslashes % 2 == 1 && lines.peek().is_some() {
+            folded.push_str(&trimmed[..trimmed.len() - 1]);
+        } else {
+            folded.push_str(line);
+            folded.push('\n');
+        }
+    }
+
+    folded.trim().to_string()
+}
+
+/// Matches `${name}`, `${name:-default}` and `${name:?message}` placeholders. Names start
+/// with a letter or underscore, so `${1}` is left to [`POSITIONAL_PATTERN`].
+const PLACEHOLDER_PATTERN: &str = r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?:(:-|:\?)([^}]*))?\}";
+
+/// Matches shell positional parameters such as `$1`, `${2}`, `$@` and `$*`.
+const POSITIONAL_PATTERN: &str = r"\$(?:\{([0-9]+)\}|([1-9])|([@*]))";
+
+#[derive(Debug, Error)]
+pub enum UtilError {
+    #[error("Invalid argument `{0}`. Expected `--arg name=value`.")]
+    InvalidArgument(String),
+
+    #[error("Missing required parameter `{0}`: {1}")]
+    MissingRequiredParameter(String, String),
+
+    #[error("Unresolved variables: {0}")]
+    UnresolvedVariables(String),
+
+    #[error("Failed to access home directory.")]
+    HomeDirAccessDenied,
+
+    #[error("No `flowlet.toml` with a [project] section found. Run `flowlet project new` first.")]
+    ProjectConfigNotFound,
+
+    #[error("Invalid duration `{0}`. Use a number with `ms`, `s`, `m` or `h`, such as `30s`.")]
+    InvalidTimespan(String),
+}
+
+/// A named placeholder found in a saved command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    pub default: Option<String>,
+    pub required: bool,
+    pub message: Option<String>,
 }
 
-pub fn extract_json_path<'a>(
-    value: &'a serde_json::Value,
-    path: &str,
-) -> Option<&'a serde_json::Value> {
-    let mut current = value;
-    for key in path.split('.') {
-        current = current.get(key)?;
+/// Arguments passed to a single run of a saved command.
+#[derive(Debug, Default, Clone)]
+pub struct CommandArgs {
+    pub named: HashMap<String, String>,
+    pub positional: Vec<String>,
+}
+
+impl CommandArgs {
+    /// Parses trailing run arguments. `--arg name=value` pairs fill named placeholders,
+    /// everything else is passed through as positional parameters (`$1`, `$@`).
+    pub fn parse(raw: &[String]) -> FlowletResult<Self> {
+        let mut args = CommandArgs::default();
+        let mut iter = raw.iter();
+
+        while let Some(arg) = iter.next() {
+            let pair = if arg == "--arg" {
+                iter.next()
+                    .ok_or_else(|| UtilError::InvalidArgument(arg.clone()))?
+                    .as_str()
+            } else if let Some(pair) = arg.strip_prefix("--arg=") {
+                pair
+            } else {
+                args.positional.push(arg.clone());
+                continue;
+            };
+
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| UtilError::InvalidArgument(pair.to_string()))?;
+
+            args.named.insert(key.to_string(), value.to_string());
+        }
+
+        Ok(args)
+    }
+
+    /// These arguments after the `defaults` saved with a command: positional ones are
+    /// appended and named ones override.
+    pub fn with_defaults(&self, defaults: &[String]) -> FlowletResult<Self> {
+        let mut args = CommandArgs::parse(defaults)?;
+
+        args.positional.extend(self.positional.iter().cloned());
+        args.named.extend(self.named.clone());
+
+        Ok(args)
     }
-    Some(current)
 }
 
-pub async fn inject_variables(ctx: &impl WithContext, command_str: &str) -> FlowletResult<String> {
-    let var_regex = Regex::new(r"\$\{([a-zA-Z0-9_]+)\}").unwrap();
-    let mut result = command_str.to_string();
+/// Lists the unique named placeholders of a command in order of appearance.
+pub fn parse_placeholders(command_str: &str) -> Vec<Placeholder> {
+    let var_regex = Regex::new(PLACEHOLDER_PATTERN).unwrap();
+    let mut placeholders: Vec<Placeholder> = Vec::new();
 
     for caps in var_regex.captures_iter(command_str) {
-        let var_name = &caps[1];
+        let name = caps[1].to_string();
+        let operator = caps.get(2).map(|m| m.as_str());
+        let operand = caps.get(3).map(|m| m.as_str().to_string());
 
-        let var = Variable::read(
-            ctx.get(),
-            ReadVariableInput {
-                query: deeb::Query::eq("name", var_name.to_string()),
+        let placeholder = Placeholder {
+            name: name.clone(),
+            default: if operator == Some(":-") {
+                operand.clone()
+            } else {
+                None
+            },
+            required: operator == Some(":?"),
+            message: if operator == Some(":?") {
+                operand.filter(|m| !m.is_empty())
+            } else {
+                None
             },
+        };
+
+        match placeholders.iter_mut().find(|p| p.name == name) {
+            // Later occurrences may add a default or mark the parameter required
+            Some(existing) => {
+                existing.default = existing.default.take().or(placeholder.default);
+                existing.required |= placeholder.required;
+                existing.message = existing.message.take().or(placeholder.message);
+            }
+            None => placeholders.push(placeholder),
+        }
+    }
+
+    placeholders
+}
+
+/// Lists the positional parameters (`$1`, `$@`, ...) a command reads.
+pub fn parse_positional_parameters(command_str: &str) -> Vec<String> {
+    let positional_regex = Regex::new(POSITIONAL_PATTERN).unwrap();
+    let mut params: Vec<String> = Vec::new();
+
+    for caps in positional_regex.captures_iter(command_str) {
+        let param = caps
+            .get(1)
+            .or_else(|| caps.get(2))
+            .or_else(|| caps.get(3))
+            .map(|m| format!("${}", m.as_str()))
+            .unwrap_or_default();
+
+        if !params.contains(&param) {
+            params.push(param);
+        }
+    }
+
+    params
+}
+
+/// How to handle placeholders that resolve to neither an argument, a variable or a default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum MissingVariables {
+    /// Prompt for each missing value, failing when not attached to a terminal.
+    #[default]
+    Prompt,
+
+    /// Refuse to run while any placeholder is unresolved.
+    Strict,
+
+    /// Print a warning and leave the placeholder in place.
+    Warn,
+}
+
+/// Order of the rows in list tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortBy {
+    /// Alphabetically by name.
+    Name,
+
+    /// Most recently created first.
+    Created,
+
+    /// Most recently updated first.
+    Updated,
+
+    /// Most revised first.
+    Revision,
+}
+
+/// A duration written as `500ms`, `30s`, `5m` or `1h`. A plain number counts seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timespan(pub Duration);
+
+impl FromStr for Timespan {
+    type Err = UtilError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || UtilError::InvalidTimespan(s.to_string());
+
+        let trimmed = s.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+
+        let duration = match unit.trim() {
+            "ms" => Duration::from_millis(number),
+            "" | "s" => Duration::from_secs(number),
+            "m" => Duration::from_secs(number.checked_mul(60).ok_or_else(invalid)?),
+            "h" => Duration::from_secs(number.checked_mul(3600).ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        };
+
+        Ok(Timespan(duration))
+    }
+}
+
+impl std::fmt::Display for Timespan {
+    /// The largest unit the duration is a whole number of.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = self.0.as_millis();
+
+        match ms {
+            0 => write!(f, "0s"),
+            _ if ms.is_multiple_of(3_600_000) => write!(f, "{}h", ms / 3_600_000),
+            _ if ms.is_multiple_of(60_000) => write!(f, "{}m", ms / 60_000),
+            _ if ms.is_multiple_of(1_000) => write!(f, "{}s", ms / 1_000),
+            _ => write!(f, "{}ms", ms),
+        }
+    }
+}
+
+impl Serialize for Timespan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timespan {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Text(String),
+            Seconds(u64),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Text(text) => text.parse().map_err(serde::de::Error::custom),
+            Raw::Seconds(seconds) => Ok(Timespan(Duration::from_secs(seconds))),
+        }
+    }
+}
+
+/// Sorts records for a list table, using `name` for [`SortBy::Name`].
+pub fn sort_records<T: Tracked>(records: &mut [T], sort: SortBy, name: impl Fn(&T) -> &str) {
+    match sort {
+        SortBy::Name => records.sort_by(|a, b| name(a).cmp(name(b))),
+        SortBy::Created => records.sort_by_key(|r| Reverse(r.metadata().created_at)),
+        SortBy::Updated => records.sort_by_key(|r| Reverse(r.metadata().updated_at)),
+        SortBy::Revision => records.sort_by_key(|r| Reverse(r.metadata().revision)),
+    }
+}
+
+/// A command with its placeholders filled in.
+pub struct InjectedCommand {
+    pub command: String,
+    /// Decrypted secret values used in `command`, for masking output.
+    pub secrets: Vec<String>,
+}
+
+impl InjectedCommand {
+    /// The command with secret values masked, safe to print or store.
+    pub fn masked(&self) -> String {
+        mask_secrets(&self.command, &self.secrets)
+    }
+}
+
+/// Values found for the placeholders of a command.
+pub struct ResolvedVariables {
+    pub values: HashMap<String, String>,
+    /// Decrypted secret values among `values`, for masking output.
+    pub secrets: Vec<String>,
+}
+
+pub async fn inject_variables(
+    ctx: &impl WithContext,
+    command_str: &str,
+    args: &CommandArgs,
+    on_missing: MissingVariables,
+) -> FlowletResult<InjectedCommand> {
+    let var_regex = Regex::new(PLACEHOLDER_PATTERN).unwrap();
+    let resolved = resolve_variables(ctx, command_str, args, on_missing).await?;
+
+    let result = var_regex.replace_all(command_str, |caps: &Captures| {
+        resolved
+            .values
+            .get(&caps[1])
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    });
+
+    Ok(InjectedCommand {
+        command: result.to_string(),
+        secrets: resolved.secrets,
+    })
+}
+
+/// Resolves the placeholders of a command from run arguments, saved variables,
+/// `flowlet.toml` and defaults, handling the missing ones as `on_missing` says.
+pub async fn resolve_variables(
+    ctx: &impl WithContext,
+    command_str: &str,
+    args: &CommandArgs,
+    on_missing: MissingVariables,
+) -> FlowletResult<ResolvedVariables> {
+    let mut values: HashMap<String, String> = HashMap::new();
+    let mut unresolved: Vec<Placeholder> = Vec::new();
+    let mut secrets: Vec<String> = Vec::new();
+
+    // Variables scoped to the current project and environment win over global ones
+    let project = find_project_config().ok().flatten();
+    let environment = find_project_environment().ok().flatten();
+    let declared = declared_variables();
+
+    for placeholder in parse_placeholders(command_str) {
+        // Run arguments take precedence over saved variables
+        if let Some(value) = args.named.get(&placeholder.name) {
+            values.insert(placeholder.name, value.clone());
+            continue;
+        }
+
+        let resolved = resolve_placeholder(
+            ctx,
+            &placeholder.name,
+            project.as_deref(),
+            environment.as_deref(),
+            &declared,
         )
         .await?;
 
-        if let Some(var) = var {
-            result = result.replace(&caps[0], &var.value);
+        if let Some(Resolved::Variable(var)) = resolved {
+            // Secrets are only ever decrypted here, right before running
+            let value = if var.secret {
+                let value = decrypt(&var.value)?;
+                secrets.push(value.clone());
+                value
+            } else {
+                var.value
+            };
+            values.insert(placeholder.name, value);
+        } else if let Some(Resolved::Declared(value)) = resolved {
+            values.insert(placeholder.name, value);
+        } else if let Some(default) = &placeholder.default {
+            values.insert(placeholder.name.clone(), default.clone());
         } else {
+            unresolved.push(placeholder);
+        }
+    }
+
+    if !unresolved.is_empty() {
+        match on_missing {
+            MissingVariables::Warn => {
+                for placeholder in &unresolved {
+                    if placeholder.required {
+                        return Err(Box::new(UtilError::MissingRequiredParameter(
+                            placeholder.name.clone(),
+                            placeholder
+                                .message
+                                .clone()
+                                .unwrap_or_else(|| "parameter not set".to_string()),
+                        )));
+                    }
+
+                    Printer::warning(
+                        Icon::Warning,
+                        "Missing Variable",
+                        &format!("${{{}}}", placeholder.name),
+                    );
+                }
+            }
+            MissingVariables::Strict => {
+                return Err(Box::new(UtilError::UnresolvedVariables(
+                    format_placeholder_names(&unresolved),
+                )));
+            }
+            MissingVariables::Prompt => {
+                if !std::io::stdin().is_terminal() {
+                    return Err(Box::new(UtilError::UnresolvedVariables(
+                        format_placeholder_names(&unresolved),
+                    )));
+                }
+
+                for placeholder in unresolved {
+                    let value = prompt_missing_variable(ctx, &placeholder).await?;
+                    values.insert(placeholder.name, value);
+                }
+            }
+        }
+    }
+
+    Ok(ResolvedVariables { values, secrets })
+}
+
+/// The command as [`inject_variables`] would fill it in, with secrets masked and
+/// unresolved placeholders left in place.
+///
+/// Secrets are never decrypted, so a preview does not prompt for a passphrase.
+pub async fn preview_variables(ctx: &impl WithContext, command_str: &str) -> FlowletResult<String> {
+    let var_regex = Regex::new(PLACEHOLDER_PATTERN).unwrap();
+    let mut values: HashMap<String, String> = HashMap::new();
+
+    let project = find_project_config().ok().flatten();
+    let environment = find_project_environment().ok().flatten();
+    let declared = declared_variables();
+
+    for placeholder in parse_placeholders(command_str) {
+        let resolved = resolve_placeholder(
+            ctx,
+            &placeholder.name,
+            project.as_deref(),
+            environment.as_deref(),
+            &declared,
+        )
+        .await?;
+
+        let value = match (resolved, placeholder.default) {
+            (Some(Resolved::Variable(var)), _) => var.display_value(),
+            (Some(Resolved::Declared(value)), _) => value,
+            (None, Some(default)) => default,
+            (None, None) => continue,
+        };
+        values.insert(placeholder.name, value);
+    }
+
+    let result = var_regex.replace_all(command_str, |caps: &Captures| {
+        values
+            .get(&caps[1])
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    });
+
+    Ok(result.to_string())
+}
+
+/// Where the value of a placeholder comes from.
+enum Resolved {
+    Variable(Variable),
+    /// A `[vars]` value from `flowlet.toml`.
+    Declared(String),
+}
+
+/// The `[vars]` of the nearest `flowlet.toml`.
+///
+/// Like the project name and environment, they are skipped when the file cannot be
+/// read, so a broken `flowlet.toml` does not stop every run.
+fn declared_variables() -> BTreeMap<String, String> {
+    match ProjectFile::load() {
+        Ok(file) => file.map(|file| file.vars).unwrap_or_default(),
+        Err(e) => {
             Printer::warning(
                 Icon::Warning,
-                "Missing Variable",
-                &format!("${{{}}}", var_name),
+                "Project File",
+                &format!("Ignoring `[vars]`. {}", e),
             );
+            BTreeMap::new()
         }
     }
+}
+
+/// Resolves a placeholder from saved variables and `[vars]` in `flowlet.toml`.
+///
+/// Declared values count as project scoped: saved project and environment values win
+/// over them, global ones do not.
+async fn resolve_placeholder(
+    ctx: &impl WithContext,
+    name: &str,
+    project: Option<&str>,
+    environment: Option<&str>,
+    declared: &BTreeMap<String, String>,
+) -> FlowletResult<Option<Resolved>> {
+    let var = Variable::resolve(ctx.get(), name, project, environment).await?;
 
-    Ok(result)
+    Ok(match var {
+        Some(var) if var.scope() != VariableScope::Global => Some(Resolved::Variable(var)),
+        var => declared
+            .get(name)
+            .cloned()
+            .map(Resolved::Declared)
+            .or(var.map(Resolved::Variable)),
+    })
 }
 
-/// Walks up the directory tree to find a `flowlet.toml` file and returns the project name.
-pub fn find_project_config() -> std::io::Result<Option<String>> {
+/// Asks the user for the value of a missing placeholder, optionally saving it as a variable.
+async fn prompt_missing_variable(
+    ctx: &impl WithContext,
+    placeholder: &Placeholder,
+) -> FlowletResult<String> {
+    let prompt = match &placeholder.message {
+        Some(message) => format!("Value for ${{{}}} ({})", placeholder.name, message),
+        None => format!("Value for ${{{}}}", placeholder.name),
+    };
+
+    let value: String = Input::new()
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()?;
+
+    let save = Confirm::new()
+        .with_prompt(format!("Save `{}` as a variable?", placeholder.name))
+        .default(false)
+        .interact()?;
+
+    if save {
+        Variable::create(
+            ctx.get(),
+            CreateVariableInput {
+                name: placeholder.name.clone(),
+                value: value.clone(),
+                project: find_project_config().ok().flatten(),
+                environment: find_project_environment().ok().flatten(),
+                secret: false,
+            },
+        )
+        .await?;
+    }
+
+    Ok(value)
+}
+
+fn format_placeholder_names(placeholders: &[Placeholder]) -> String {
+    placeholders
+        .iter()
+        .map(|p| format!("${{{}}}", p.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Walks up the directory tree to find the nearest `flowlet.toml` file.
+pub fn find_project_file() -> std::io::Result<Option<PathBuf>> {
     let mut dir = std::env::current_dir()?;
 
     loop {
         let config_path = dir.join("flowlet.toml");
         if config_path.exists() {
-            let contents = fs::read_to_string(&config_path)?;
-
-            let parsed: Value = match toml::from_str(&contents) {
-                Ok(v) => v,
-                Err(err) => {
-                    eprintln!("❌ Failed to parse TOML: {err}");
-                    return Ok(None);
-                }
-            };
-
-            if let Some(project) = parsed.get("project") {
-                if let Some(name) = project.get("name").and_then(|v| v.as_str()) {
-                    return Ok(Some(name.to_string()));
-                }
-            }
-
-            return Ok(None);
+            return Ok(Some(config_path));
         }
 
         if !dir.pop() {
@@ -133,6 +635,64 @@ pub fn find_project_config() -> std::io::Result<Option<String>> {
     Ok(None)
 }
 
+/// The directory holding the nearest `flowlet.toml`.
+pub fn find_project_root() -> std::io::Result<Option<PathBuf>> {
+    Ok(find_project_file()?.and_then(|path| path.parent().map(PathBuf::from)))
+}
+
+/// Reads a `[project]` field from the nearest `flowlet.toml`.
+fn read_project_field(field: &str) -> std::io::Result<Option<String>> {
+    let config_path = match find_project_file()? {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    let contents = fs::read_to_string(&config_path)?;
+
+    let parsed: Value = match toml::from_str(&contents) {
+        Ok(v) => v,
+        Err(err) => {
+            eprintln!("❌ Failed to parse TOML: {err}");
+            return Ok(None);
+        }
+    };
+
+    Ok(parsed
+        .get("project")
+        .and_then(|project| project.get(field))
+        .and_then(|v| v.as_str())
+        .map(str::to_string))
+}
+
+/// Walks up the directory tree to find a `flowlet.toml` file and returns the project name.
+pub fn find_project_config() -> std::io::Result<Option<String>> {
+    read_project_field("name")
+}
+
+/// Returns the environment selected in the nearest `flowlet.toml`.
+pub fn find_project_environment() -> std::io::Result<Option<String>> {
+    read_project_field("environment")
+}
+
+/// Rewrites `project.environment` in the nearest `flowlet.toml`, keeping everything else.
+pub fn set_project_environment(environment: &str) -> FlowletResult<PathBuf> {
+    let config_path = find_project_file()?.ok_or(UtilError::ProjectConfigNotFound)?;
+    let contents = fs::read_to_string(&config_path)?;
+
+    // Edit the document in place so comments and formatting survive
+    let mut document: DocumentMut = contents.parse()?;
+    let project = document
+        .get_mut("project")
+        .and_then(|p| p.as_table_like_mut())
+        .ok_or(UtilError::ProjectConfigNotFound)?;
+
+    project.insert("environment", toml_edit::value(environment));
+
+    fs::write(&config_path, document.to_string())?;
+
+    Ok(config_path)
+}
+
 pub fn truncate_with_ellipsis(s: &str, max_len: usize) -> String {
     if s.chars().count() > max_len {
         let truncated: String = s.chars().take(max_len).collect();
@@ -166,3 +726,35 @@ pub fn request_date_input(prompt: &str, allow_empty: bool) -> FlowletResult<Opti
 
     Ok(requested_date)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(command: &str) -> Vec<String> {
+        parse_placeholders(command)
+            .into_iter()
+            .map(|p| p.name)
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_start_with_a_letter_or_underscore() {
+        assert_eq!(
+            names("echo ${name} ${_private} ${v2:-x}"),
+            vec!["name", "_private", "v2"]
+        );
+    }
+
+    #[test]
+    fn numbered_braces_are_positional_only() {
+        let command = "cp ${1} ${2} --tag ${TAG:-latest} $@";
+
+        assert_eq!(names(command), vec!["TAG"]);
+        assert_eq!(parse_positional_parameters(command), vec!["$1", "$2", "$@"]);
+
+        let args = CommandArgs::parse(&["a.txt".to_string(), "b.txt".to_string()]).unwrap();
+        assert_eq!(args.positional, vec!["a.txt", "b.txt"]);
+        assert!(args.named.is_empty());
+    }
+}